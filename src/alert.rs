@@ -0,0 +1,173 @@
+//! Alerts raised when error records are logged.
+
+use banner;
+use clock;
+use console::Stream;
+use log::Level;
+use palette;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use style::Style;
+use width::{self, Align};
+
+/// The minimum time between bells rung in attention mode.
+const BELL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The minimum time between desktop notifications.
+#[cfg(feature = "notify")]
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The least severe level that gets attention, or 0 if attention mode is off.
+static ATTENTION: AtomicUsize = AtomicUsize::new(0);
+
+/// The monotonic time of the last bell rung in attention mode, in milliseconds, plus one, or 0 if none was rung.
+static LAST_BELL: AtomicU64 = AtomicU64::new(0);
+
+/// The monotonic time of the last desktop notification, in milliseconds, plus one, or 0 if none was shown.
+#[cfg(feature = "notify")]
+static LAST_NOTIFICATION: AtomicU64 = AtomicU64::new(0);
+
+/// Ring the terminal bell, if stderr is a terminal.
+pub(crate) fn bell() {
+    if io::stderr().is_terminal() {
+        Stream::Stderr.write_str("\x07");
+    }
+}
+
+/// Get the least severe level of records that are [rendered to get attention](set_attention), if enabled.
+pub fn attention() -> Option<Level> {
+    Level::iter().nth(ATTENTION.load(Ordering::SeqCst).checked_sub(1)?)
+}
+
+/// Turn attention mode on for records at the given level or more severe, or off with `None`. The default is off.
+///
+/// Attention mode is intended for operators following a long-running program over SSH, where a single failure is
+/// easily lost in scrollback. Each record that gets attention is rendered as a block with high-contrast colors from
+/// the [palette](::set_palette), filling the width of the terminal:
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::Level;
+///
+/// clogger::set_attention(Some(Level::Error));
+/// # }
+/// ```
+///
+/// The terminal bell is rung for these records as well, but at most once every 10 seconds, so that a burst of failures
+/// does not cause a storm of bells. Blocks are only rendered when colored output is written in the
+/// [text format](::Format::Text), which requires the `color` feature, and [error banners](::set_error_banners) take
+/// precedence over them. The bell is only rung if stderr is a terminal.
+///
+/// This function may be called at any time.
+pub fn set_attention(level: Option<Level>) {
+    ATTENTION.store(level.map_or(0, |level| level as usize), Ordering::SeqCst);
+}
+
+/// Check if a record at the given level gets attention.
+pub(crate) fn needs_attention(level: Level) -> bool {
+    attention().is_some_and(|attention| level <= attention)
+}
+
+/// Render a formatted record as a block that fills the width of the terminal, with a blank line above and below.
+pub(crate) fn attention_block(line: &str) -> String {
+    let (fg, bg) = palette::palette().attention();
+    let style = Style::new().fg(fg).bg(bg).bold();
+    let width = banner::terminal_width();
+    let blank = style.paint(" ".repeat(width)).to_string();
+
+    let mut block = blank.clone();
+    for line in line.lines() {
+        block.push('\n');
+        block.push_str(
+            &style
+                .paint(width::pad(&format!(" {}", line), Align::Left, width))
+                .to_string(),
+        );
+    }
+    block.push('\n');
+    block.push_str(&blank);
+    block
+}
+
+/// Ring the terminal bell for a record that gets attention, unless it was rung recently.
+pub(crate) fn attention_bell() {
+    if due(&LAST_BELL, BELL_INTERVAL) {
+        bell();
+    }
+}
+
+/// Check if at least the given interval has passed since the given time, and if so, set it to the current time.
+fn due(last: &AtomicU64, interval: Duration) -> bool {
+    let now = clock::monotonic().as_millis() as u64 + 1;
+    let interval = interval.as_millis() as u64;
+
+    last.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+        if last == 0 || now.saturating_sub(last) >= interval {
+            Some(now)
+        } else {
+            None
+        }
+    })
+    .is_ok()
+}
+
+/// Show a desktop notification with the given message.
+///
+/// The notification is shown using `notify-send` on Linux and other Unix-like systems, and `osascript` on macOS.
+/// Nothing happens on other platforms, or if the notification command is not available. At most one notification is
+/// shown every 10 seconds, so that a burst of errors does not start a process for each one.
+#[cfg(feature = "notify")]
+pub(crate) fn notify(message: &str) {
+    use std::env;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::thread;
+
+    if !due(&LAST_NOTIFICATION, NOTIFY_INTERVAL) {
+        return;
+    }
+
+    let title = env::args_os()
+        .next()
+        .and_then(|arg0| {
+            Path::new(&arg0)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "error".to_owned());
+
+    let mut command = if cfg!(target_os = "macos") {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape(message),
+            escape(&title)
+        ));
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        // The message may start with a dash, so it must not be parsed as an option.
+        command
+            .arg("--urgency=critical")
+            .arg("--")
+            .arg(title)
+            .arg(message);
+        command
+    } else {
+        return;
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Reap the child in the background so that logging is never blocked on the notification being shown.
+    if let Ok(mut child) = command.spawn() {
+        thread::spawn(move || child.wait());
+    }
+}
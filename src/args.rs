@@ -0,0 +1,160 @@
+//! Common command line options for configuring the logger, for programs that do not use an argument parsing library
+//! with its own integration.
+
+#[cfg(feature = "color")]
+use console::ColorChoice;
+use error::Error;
+use log::LevelFilter;
+
+/// A setting given on the command line.
+enum Setting {
+    Verbose(usize),
+    Quiet,
+    Level(LevelFilter),
+    TargetLevels(String),
+    #[cfg(feature = "color")]
+    Color(ColorChoice),
+}
+
+/// Apply the common logging options among the given command line arguments, and return the remaining arguments.
+///
+/// This is a small alternative to integrating with an argument parsing library, for programs that parse their
+/// arguments by hand or using a minimal library such as `pico-args`. The following options are recognized:
+///
+/// - `-v`, `--verbose`: Increase the [verbosity](::set_verbosity) by one. Can be repeated, such as `-vv`.
+/// - `-q`, `--quiet`: Enable [quiet mode](::set_quiet).
+/// - `--log-level <level>`: Display records up to the given level, such as `debug`, as if by the corresponding
+///   verbosity. `error` and `off` enable quiet mode with that [quiet level](::set_quiet_level) instead. A list of
+///   `target=level` directives, such as `myapp=debug,hyper=warn`, sets [target levels](::set_target_levels) instead.
+/// - `--color <when>`: Set when to [use color](::set_color), which is one of `auto`, `always` or `never`. Only
+///   recognized with the `color` feature.
+///
+/// Options that take a value can also be written as `--log-level=debug`. Everything after `--` is left alone.
+///
+/// ```
+/// let args = clogger::parse_args(["-vv", "build", "--log-level", "hyper=warn", "--release"]).unwrap();
+///
+/// assert_eq!(args, ["build", "--release"]);
+/// assert_eq!(clogger::verbosity(), 2);
+/// ```
+///
+/// Usually the arguments of the program are passed, skipping the program name:
+///
+/// ```no_run
+/// let args = clogger::parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+///     eprintln!("{}", e);
+///     std::process::exit(2);
+/// });
+/// ```
+///
+/// Returns an error if an option has a missing or invalid value, in which case none of the options are applied.
+pub fn parse_args<I>(args: I) -> Result<Vec<String>, Error>
+where
+    I: IntoIterator,
+    I::Item: Into<String>,
+{
+    let mut settings = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter().map(Into::into);
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            rest.push(arg);
+            rest.extend(args);
+            break;
+        }
+
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                (name.to_owned(), Some(value.to_owned()))
+            }
+            _ => (arg.clone(), None),
+        };
+
+        match name.as_str() {
+            "--verbose" if value.is_none() => settings.push(Setting::Verbose(1)),
+            "--quiet" if value.is_none() => settings.push(Setting::Quiet),
+            "--log-level" => {
+                let value = value.or_else(|| args.next());
+                settings.push(level(&arg, value.as_deref())?);
+            }
+            #[cfg(feature = "color")]
+            "--color" => {
+                let value = value.or_else(|| args.next());
+                settings.push(Setting::Color(color(&arg, value.as_deref())?));
+            }
+            // Short flags can be combined, such as `-vvq`.
+            _ if name.len() > 1
+                && name.starts_with('-')
+                && name[1..].chars().all(|c| c == 'v' || c == 'q') =>
+            {
+                let verbose = name.matches('v').count();
+                if verbose > 0 {
+                    settings.push(Setting::Verbose(verbose));
+                }
+                if name.contains('q') {
+                    settings.push(Setting::Quiet);
+                }
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    for setting in settings {
+        match setting {
+            Setting::Verbose(count) => super::set_verbosity(super::verbosity() + count),
+            Setting::Quiet => super::set_quiet(true),
+            Setting::Level(level) => match level {
+                LevelFilter::Off | LevelFilter::Error => {
+                    super::set_quiet_level(level);
+                    super::set_quiet(true);
+                }
+                _ => super::set_verbosity(level as usize - LevelFilter::Warn as usize),
+            },
+            Setting::TargetLevels(ref directives) => {
+                super::set_target_levels(directives)?;
+            }
+            #[cfg(feature = "color")]
+            Setting::Color(choice) => super::set_color(choice),
+        }
+    }
+
+    Ok(rest)
+}
+
+fn invalid(argument: &str, reason: &str) -> Error {
+    Error::Argument {
+        argument: argument.to_owned(),
+        reason: reason.to_owned(),
+    }
+}
+
+fn level(argument: &str, value: Option<&str>) -> Result<Setting, Error> {
+    let value = value.ok_or_else(|| invalid(argument, "expected a level"))?;
+
+    if value.contains('=') {
+        for directive in value.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            super::parse_directive(directive)?;
+        }
+        return Ok(Setting::TargetLevels(value.to_owned()));
+    }
+
+    value
+        .trim()
+        .parse()
+        .map(Setting::Level)
+        .map_err(|_| invalid(argument, &format!("unknown level `{}`", value.trim())))
+}
+
+#[cfg(feature = "color")]
+fn color(argument: &str, value: Option<&str>) -> Result<ColorChoice, Error> {
+    match value.ok_or_else(|| invalid(argument, "expected `auto`, `always` or `never`"))? {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        other => Err(invalid(
+            argument,
+            &format!("expected `auto`, `always` or `never`, found `{}`", other),
+        )),
+    }
+}
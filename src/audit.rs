@@ -0,0 +1,128 @@
+//! Tamper-evident audit logs.
+
+use error::Error;
+use format;
+use record::Entry;
+use sha2::{Digest, Sha256};
+use sink::Sink;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// The hash preceding the first line of an audit log.
+const INITIAL_HASH: [u8; 32] = [0; 32];
+
+/// A sink that appends records to a file as a hash chain, so that any modification of the file can be detected.
+///
+/// Each line of the file contains the following fields separated by tabs:
+///
+/// ```text
+/// <previous hash>\t<sequence>\t<unix time>\t<level>\t<target>\t<message>
+/// ```
+///
+/// The previous hash is the hex-encoded SHA-256 hash of the entire previous line, or all zeros for the first line. The
+/// last three fields use the same format as [machine mode](::set_machine_mode). Use [`AuditSink::verify`] to check
+/// that a file has not been tampered with.
+///
+/// ```no_run
+/// let sink = clogger::AuditSink::open("audit.log").unwrap();
+/// clogger::add_sink(sink);
+/// ```
+pub struct AuditSink {
+    state: Mutex<State>,
+}
+
+struct State {
+    file: File,
+    previous: [u8; 32],
+}
+
+impl AuditSink {
+    /// Open an audit log, creating it if it does not exist.
+    ///
+    /// If the file already contains records, new records continue its hash chain.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut previous = INITIAL_HASH;
+        for line in BufReader::new(&file).lines() {
+            let line = line?;
+            if !line.is_empty() {
+                previous = Sha256::digest(line.as_bytes()).into();
+            }
+        }
+
+        Ok(AuditSink {
+            state: Mutex::new(State { file, previous }),
+        })
+    }
+
+    /// Verify the hash chain of an audit log, returning the number of records in it.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if any line has been modified, inserted or removed,
+    /// except for lines removed from the end of the file.
+    pub fn verify<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut previous = INITIAL_HASH;
+        let mut count = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+            count += 1;
+
+            let hash = line.split('\t').next().unwrap_or("");
+            if hash != hex(&previous) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("hash chain broken at line {}", count),
+                ));
+            }
+
+            previous = Sha256::digest(line.as_bytes()).into();
+        }
+
+        Ok(count)
+    }
+}
+
+impl Sink for AuditSink {
+    fn write(&self, entry: &Entry, _: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let time = entry.time().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        let mut line = hex(&state.previous);
+        let _ = write!(
+            line,
+            "\t{}\t{}.{:09}\t{}",
+            entry.sequence(),
+            time.as_secs(),
+            time.subsec_nanos(),
+            format::machine(entry.level(), entry.target(), entry.message())
+        );
+
+        // Only advance the chain once the line has been written in full, so that a failed write can be retried.
+        state.file.write_all(format!("{}\n", line).as_bytes())?;
+        state.previous = Sha256::digest(line.as_bytes()).into();
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.file.sync_data()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
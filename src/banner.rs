@@ -0,0 +1,143 @@
+//! Boxed rendering for error records.
+
+use log::Record;
+use std::env;
+use style::Style;
+use translation;
+use width;
+
+const MAX_WIDTH: usize = 80;
+
+/// Collect the chain of errors attached to a record as key-value pairs.
+///
+/// Errors are attached using the `:err` capture modifier, such as `error!(e:err; "failed to read config")`.
+#[cfg(feature = "kv")]
+pub(crate) fn causes(record: &Record) -> Vec<String> {
+    use log::kv::{self, Key, Value, VisitSource};
+
+    struct Visitor(Vec<String>);
+
+    impl<'kvs> VisitSource<'kvs> for Visitor {
+        fn visit_pair(&mut self, _: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+            let mut error = value.to_borrowed_error();
+
+            while let Some(e) = error {
+                self.0.push(e.to_string());
+                error = e.source();
+            }
+
+            Ok(())
+        }
+    }
+
+    let mut visitor = Visitor(Vec::new());
+    let _ = record.key_values().visit(&mut visitor);
+    visitor.0
+}
+
+#[cfg(not(feature = "kv"))]
+pub(crate) fn causes(_: &Record) -> Vec<String> {
+    Vec::new()
+}
+
+/// Render a message and its causes inside of a box drawn using the given style.
+pub(crate) fn render(name: &str, border: Style, message: &str, causes: &[String]) -> String {
+    let width = terminal_width().min(MAX_WIDTH);
+    let inner = width.saturating_sub(4).max(20);
+    let mut lines = wrap(message, inner);
+
+    if !causes.is_empty() {
+        lines.push(String::new());
+        lines.push(translation::current().caused_by_text().to_owned());
+
+        for (i, cause) in causes.iter().enumerate() {
+            let prefix = format!("  {}: ", i);
+            let indent = " ".repeat(prefix.len());
+
+            for (j, line) in wrap(cause, inner - prefix.len()).into_iter().enumerate() {
+                lines.push(format!(
+                    "{}{}",
+                    if j == 0 { &prefix } else { &indent },
+                    line
+                ));
+            }
+        }
+    }
+
+    let title = format!(" {} ", name);
+    let mut output = format!(
+        "{}{}{}{}\n",
+        border.paint("╭─"),
+        border.bold().paint(title.as_str()),
+        border.paint("─".repeat((inner + 1).saturating_sub(width::width(&title)))),
+        border.paint("╮"),
+    );
+
+    for line in lines {
+        let padding = inner.saturating_sub(width::width(&line));
+        output.push_str(&format!(
+            "{} {}{} {}\n",
+            border.paint("│"),
+            line,
+            " ".repeat(padding),
+            border.paint("│"),
+        ));
+    }
+
+    output.push_str(
+        &border
+            .paint(format!("╰{}╯", "─".repeat(inner + 2)))
+            .to_string(),
+    );
+    output
+}
+
+/// Word-wrap text to the given width, preserving explicit line breaks.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+
+            if !line.is_empty() && width::width(&line) + 1 + width::width(word) > width {
+                lines.push(line);
+                line = String::new();
+            }
+
+            // Hard-break words that are too long to fit on any line.
+            while width::width(word) > width {
+                // Always make progress, even if the first character is wider than the line.
+                let split = match width::truncate(word, width).len() {
+                    0 => word.chars().next().map_or(word.len(), char::len_utf8),
+                    split => split,
+                };
+                lines.push(word[..split].to_owned());
+                word = &word[split..];
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+/// Get the width of the terminal in columns, or a default width if it is not known.
+pub(crate) fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(MAX_WIDTH)
+}
@@ -0,0 +1,91 @@
+//! Temporary increases of the displayed level.
+
+use change::{self, Change};
+use clock;
+use log::LevelFilter;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The level of the current boost, or 0 if there is none.
+static LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+/// The monotonic time in milliseconds at which the current boost ends.
+static UNTIL: AtomicU64 = AtomicU64::new(0);
+
+/// The ID of the current boost, so that an older guard does not end a newer boost.
+static ID: AtomicU64 = AtomicU64::new(0);
+
+/// A guard for a temporary increase of the displayed level, returned by [`boost_verbosity_for`].
+///
+/// The boost ends when its window has passed or the guard is dropped, whichever happens first.
+#[must_use = "the boost ends as soon as it is dropped"]
+pub struct Boost {
+    id: u64,
+}
+
+impl Boost {
+    /// Let the boost run until its window has passed, even after the guard is dropped.
+    pub fn detach(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Boost {
+    fn drop(&mut self) {
+        if ID.load(Ordering::SeqCst) == self.id {
+            end();
+        }
+    }
+}
+
+/// Temporarily display records up to the given level for all targets, reverting automatically once the given
+/// duration has passed or the returned guard is dropped.
+///
+/// This is useful for diagnosing a specific operation of a long-running process without leaving trace output on:
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use std::time::Duration;
+/// use log::LevelFilter;
+///
+/// let _boost = clogger::boost_verbosity_for(Duration::from_secs(30), LevelFilter::Trace);
+/// // Trace records are displayed until the end of this scope, or for at most 30 seconds.
+/// # }
+/// ```
+///
+/// A boost only ever raises the displayed level, so targets that are already configured more verbosely are not
+/// affected, and [quiet mode](::set_quiet) still takes precedence. Starting a new boost replaces the current one.
+///
+/// This function may be called at any time.
+pub fn boost_verbosity_for(duration: Duration, level: LevelFilter) -> Boost {
+    let until = clock::monotonic().saturating_add(duration).as_millis() as u64;
+    let id = ID.fetch_add(1, Ordering::SeqCst) + 1;
+
+    UNTIL.store(until, Ordering::SeqCst);
+    LEVEL.store(level as usize, Ordering::SeqCst);
+    super::update_max_level();
+    change::notify(Change::Verbosity);
+
+    Boost { id }
+}
+
+/// Get the level of the current boost, ending it if its window has passed.
+pub(crate) fn level() -> LevelFilter {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LevelFilter::Off,
+        _ if clock::monotonic().as_millis() as u64 >= UNTIL.load(Ordering::Relaxed) => {
+            end();
+            LevelFilter::Off
+        }
+        level => super::level_filter_from_usize(level),
+    }
+}
+
+fn end() {
+    if LEVEL.swap(0, Ordering::SeqCst) != 0 {
+        super::update_max_level();
+        change::notify(Change::Verbosity);
+    }
+}
@@ -0,0 +1,42 @@
+//! Buffering of records logged before the logger is fully initialized.
+
+use record::Entry;
+use std::sync::Mutex;
+
+/// The maximum number of records that are buffered before initialization. Any further records are dropped.
+const CAPACITY: usize = 1024;
+
+struct Buffer {
+    entries: Vec<Entry>,
+    dropped: usize,
+}
+
+static BUFFER: Mutex<Option<Buffer>> = Mutex::new(None);
+
+/// Start buffering records.
+pub(crate) fn start() {
+    *BUFFER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Buffer {
+        entries: Vec::new(),
+        dropped: 0,
+    });
+}
+
+/// Buffer a record, or return it if buffering has already been stopped.
+pub(crate) fn buffer(entry: Entry) -> Option<Entry> {
+    match *BUFFER.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref mut buffer) if buffer.entries.len() < CAPACITY => buffer.entries.push(entry),
+        Some(ref mut buffer) => buffer.dropped += 1,
+        None => return Some(entry),
+    }
+
+    None
+}
+
+/// Stop buffering records, returning all buffered records and the number of records that were dropped.
+pub(crate) fn finish() -> Option<(Vec<Entry>, usize)> {
+    BUFFER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .map(|buffer| (buffer.entries, buffer.dropped))
+}
@@ -0,0 +1,116 @@
+//! Identifying information about the build of the application.
+
+use log::{log, Level};
+
+/// Log the name, version, git commit, target and enabled features of the application at debug level.
+///
+/// Logging this on startup gives every bug report that includes verbose output the same identifying information. The
+/// values are read at compile time from the following environment variables of the crate that invokes the macro:
+///
+/// - `CARGO_PKG_NAME` and `CARGO_PKG_VERSION`, which are always set by Cargo.
+/// - `GIT_HASH` or `VERGEN_GIT_SHA`, if set, such as by a build script that prints `cargo:rustc-env=GIT_HASH=...`.
+/// - `BUILD_FEATURES`, if set, as a comma-separated list of enabled features.
+///
+/// The target is derived from the architecture, operating system and environment being compiled for.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// clogger::init();
+///
+/// // Logs something like "mytool 1.2.3 (git 1a2b3c4, x86_64-linux-gnu, features: tls)".
+/// log_build_info!();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! log_build_info {
+    () => {
+        $crate::__log_build_info(
+            module_path!(),
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            option_env!("GIT_HASH").or(option_env!("VERGEN_GIT_SHA")),
+            &[
+                if cfg!(target_arch = "x86_64") {
+                    "x86_64"
+                } else if cfg!(target_arch = "x86") {
+                    "x86"
+                } else if cfg!(target_arch = "aarch64") {
+                    "aarch64"
+                } else if cfg!(target_arch = "arm") {
+                    "arm"
+                } else if cfg!(target_arch = "riscv64") {
+                    "riscv64"
+                } else if cfg!(target_arch = "wasm32") {
+                    "wasm32"
+                } else {
+                    "unknown"
+                },
+                if cfg!(target_os = "linux") {
+                    "linux"
+                } else if cfg!(target_os = "macos") {
+                    "macos"
+                } else if cfg!(target_os = "windows") {
+                    "windows"
+                } else if cfg!(target_os = "freebsd") {
+                    "freebsd"
+                } else if cfg!(target_os = "android") {
+                    "android"
+                } else if cfg!(target_os = "ios") {
+                    "ios"
+                } else {
+                    "unknown"
+                },
+                if cfg!(target_env = "gnu") {
+                    "gnu"
+                } else if cfg!(target_env = "musl") {
+                    "musl"
+                } else if cfg!(target_env = "msvc") {
+                    "msvc"
+                } else {
+                    ""
+                },
+            ],
+            option_env!("BUILD_FEATURES"),
+        )
+    };
+}
+
+#[doc(hidden)]
+pub fn __log_build_info(
+    target: &str,
+    name: &str,
+    version: &str,
+    git_hash: Option<&str>,
+    triple: &[&str],
+    features: Option<&str>,
+) {
+    let mut details = Vec::new();
+
+    if let Some(hash) = git_hash.filter(|hash| !hash.is_empty()) {
+        details.push(format!("git {}", hash.get(..12).unwrap_or(hash)));
+    }
+
+    let triple = triple
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>();
+    details.push(triple.join("-"));
+
+    if let Some(features) = features {
+        let features = features
+            .split(',')
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .collect::<Vec<_>>();
+
+        details.push(if features.is_empty() {
+            "no features".to_owned()
+        } else {
+            format!("features: {}", features.join(", "))
+        });
+    }
+
+    log!(target: target, Level::Debug, "{} {} ({})", name, version, details.join(", "));
+}
@@ -0,0 +1,278 @@
+//! Configuring and installing the logger in one expression.
+
+#[cfg(feature = "color")]
+use console::ColorChoice;
+use error::Error;
+#[cfg(feature = "file")]
+use file::FileSink;
+use format::Format;
+use log::LevelFilter;
+use palette::Palette;
+use sink::{self, Sink};
+#[cfg(feature = "file")]
+use std::path::{Path, PathBuf};
+
+/// The size at which the log file of the [daemon preset](Builder::daemon) is rotated.
+#[cfg(feature = "file")]
+const DAEMON_ROTATE_AT: u64 = 10 * 1024 * 1024;
+
+/// A set of configuration options that are applied together when the logger is installed.
+///
+/// Each option that is not set is left at its current value, so a builder can be combined freely with the global
+/// setter functions. The presets bundle sensible combinations for common kinds of programs:
+///
+/// ```no_run
+/// clogger::Builder::cli().verbosity(2).init().unwrap();
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    verbosity: Option<usize>,
+    quiet: Option<bool>,
+    #[cfg(feature = "color")]
+    color: Option<ColorChoice>,
+    palette: Option<Palette>,
+    #[cfg(feature = "timestamps")]
+    timestamps: Option<bool>,
+    trace_timing: Option<bool>,
+    error_banners: Option<bool>,
+    format: Option<Format>,
+    line_format: Option<String>,
+    host_fields: Option<bool>,
+    run_id: Option<bool>,
+    target_levels: Vec<(String, LevelFilter)>,
+    #[cfg(feature = "file")]
+    file: Option<(PathBuf, Option<u64>)>,
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl Builder {
+    /// Create a builder that leaves every option at its current value.
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// A preset for interactive command line tools: color is used when the console supports it, and lines are kept
+    /// short by leaving out timestamps.
+    pub fn cli() -> Self {
+        let builder = Builder::new().error_banners(true);
+        #[cfg(feature = "color")]
+        let builder = builder.color(ColorChoice::Auto);
+        #[cfg(feature = "timestamps")]
+        let builder = builder.timestamps(false);
+        builder
+    }
+
+    /// A preset for long-running background services: every line is timestamped, and records are also written to the
+    /// given file, which is rotated once it grows larger than 10 MiB. Requires the `file` feature.
+    #[cfg(feature = "file")]
+    pub fn daemon<P: AsRef<Path>>(log_file: P) -> Self {
+        let builder = Builder::new()
+            .error_banners(false)
+            .file(log_file)
+            .rotate_at(DAEMON_ROTATE_AT);
+        #[cfg(feature = "timestamps")]
+        let builder = builder.timestamps(true);
+        builder
+    }
+
+    /// A preset for continuous integration jobs: color is never used, since CI logs are often viewed as plain text,
+    /// and every line is annotated with the time elapsed since the program started, so slow steps stand out.
+    pub fn ci() -> Self {
+        let builder = Builder::new().error_banners(false).trace_timing(true);
+        #[cfg(feature = "color")]
+        let builder = builder.color(ColorChoice::Never);
+        builder
+    }
+
+    /// Set the [verbosity](::set_verbosity).
+    pub fn verbosity(self, verbosity: usize) -> Self {
+        Builder {
+            verbosity: Some(verbosity),
+            ..self
+        }
+    }
+
+    /// Turn [quiet mode](::set_quiet) on or off.
+    pub fn quiet(self, enabled: bool) -> Self {
+        Builder {
+            quiet: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Set when [color](::set_color) is used.
+    #[cfg(feature = "color")]
+    pub fn color(self, choice: ColorChoice) -> Self {
+        Builder {
+            color: Some(choice),
+            ..self
+        }
+    }
+
+    /// Set the [palette](::set_palette) of colors used by the logger.
+    pub fn palette(self, palette: Palette) -> Self {
+        Builder {
+            palette: Some(palette),
+            ..self
+        }
+    }
+
+    /// Turn [timestamps](::set_timestamps) on or off.
+    #[cfg(feature = "timestamps")]
+    pub fn timestamps(self, enabled: bool) -> Self {
+        Builder {
+            timestamps: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Turn [trace timing](::set_trace_timing) on or off.
+    pub fn trace_timing(self, enabled: bool) -> Self {
+        Builder {
+            trace_timing: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Turn [error banners](::set_error_banners) on or off.
+    pub fn error_banners(self, enabled: bool) -> Self {
+        Builder {
+            error_banners: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Set the [output format](::set_output_format).
+    pub fn output_format(self, format: Format) -> Self {
+        Builder {
+            format: Some(format),
+            ..self
+        }
+    }
+
+    /// Set a custom [line format](::set_line_format). The format is validated when the logger is installed.
+    pub fn line_format(self, template: &str) -> Self {
+        Builder {
+            line_format: Some(template.to_owned()),
+            ..self
+        }
+    }
+
+    /// Include [host fields](::set_host_fields) in the key-value format.
+    pub fn host_fields(self, enabled: bool) -> Self {
+        Builder {
+            host_fields: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Include a [run ID](::set_run_id) in structured output and the final summary.
+    pub fn run_id(self, enabled: bool) -> Self {
+        Builder {
+            run_id: Some(enabled),
+            ..self
+        }
+    }
+
+    /// Set the [maximum level for a target](::set_target_level). May be called multiple times.
+    pub fn target_level(mut self, target: &str, level: LevelFilter) -> Self {
+        self.target_levels.push((target.to_owned(), level));
+        self
+    }
+
+    /// Also write records to the given file, which is opened when the logger is installed. Requires the `file`
+    /// feature.
+    #[cfg(feature = "file")]
+    pub fn file<P: AsRef<Path>>(self, path: P) -> Self {
+        Builder {
+            file: Some((path.as_ref().to_owned(), None)),
+            ..self
+        }
+    }
+
+    /// Rotate the [file](Builder::file) once it would grow larger than the given number of bytes.
+    ///
+    /// Has no effect if no file is set.
+    #[cfg(feature = "file")]
+    pub fn rotate_at(self, max_bytes: u64) -> Self {
+        Builder {
+            file: self.file.map(|(path, _)| (path, Some(max_bytes))),
+            ..self
+        }
+    }
+
+    /// Add a [sink](::add_sink) that records are written to. May be called multiple times.
+    pub fn sink<S: Sink>(mut self, sink: S) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Apply the options and install the global logger.
+    ///
+    /// Returns an error if the line format is invalid, the file could not be opened, or a global logger is already
+    /// installed. Nothing is applied if the line format or file fail.
+    pub fn init(self) -> Result<(), Error> {
+        #[cfg(feature = "file")]
+        let file = match self.file {
+            Some((ref path, rotate_at)) => {
+                let file = FileSink::open(path)?;
+                Some(match rotate_at {
+                    Some(max_bytes) => file.rotate_at(max_bytes),
+                    None => file,
+                })
+            }
+            None => None,
+        };
+
+        if let Some(ref template) = self.line_format {
+            super::set_line_format(Some(template))?;
+        }
+
+        if let Some(verbosity) = self.verbosity {
+            super::set_verbosity(verbosity);
+        }
+        if let Some(enabled) = self.quiet {
+            super::set_quiet(enabled);
+        }
+        #[cfg(feature = "color")]
+        if let Some(choice) = self.color {
+            super::set_color(choice);
+        }
+        if let Some(palette) = self.palette {
+            super::set_palette(palette);
+        }
+        #[cfg(feature = "timestamps")]
+        if let Some(enabled) = self.timestamps {
+            super::set_timestamps(enabled);
+        }
+        if let Some(enabled) = self.trace_timing {
+            super::set_trace_timing(enabled);
+        }
+        if let Some(enabled) = self.error_banners {
+            super::set_error_banners(enabled);
+        }
+        if let Some(format) = self.format {
+            super::set_output_format(format);
+        }
+        if let Some(enabled) = self.host_fields {
+            super::set_host_fields(enabled);
+        }
+        if let Some(enabled) = self.run_id {
+            super::set_run_id(enabled);
+        }
+
+        for (target, level) in self.target_levels {
+            super::set_target_level(&target, level);
+        }
+
+        #[cfg(feature = "file")]
+        if let Some(file) = file {
+            sink::add_sink(file);
+        }
+        for sink in self.sinks {
+            sink::add_sink(sink);
+        }
+
+        super::try_init()
+    }
+}
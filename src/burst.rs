@@ -0,0 +1,157 @@
+//! Protection against bursts of records from tight loops.
+
+use clock;
+use drops::{self, Cause};
+use log::{Level, Record};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use translation::{self, Message};
+
+/// How often the number of suppressed records is reported while a burst continues.
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The maximum number of records kept per second at each level, or 0 if unlimited.
+static LIMIT: AtomicU64 = AtomicU64::new(0);
+
+static WINDOWS: [Window; 5] = [const { Window::new() }; 5];
+
+/// The rate of records at a single level.
+struct Window {
+    /// The time in milliseconds at which the current one-second window started.
+    start: AtomicU64,
+    count: AtomicU64,
+    suppressed: AtomicU64,
+
+    /// The time in milliseconds at which the first record was suppressed since the last summary, or 0 if none were.
+    since: AtomicU64,
+}
+
+impl Window {
+    const fn new() -> Self {
+        Window {
+            start: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+            since: AtomicU64::new(0),
+        }
+    }
+
+    /// Take the number of records suppressed since the first one, if a summary is due.
+    fn take(&self, now: u64, force: bool) -> Option<(u64, u64)> {
+        let since = self.since.load(Ordering::Relaxed);
+
+        if since == 0
+            || (!force && now.saturating_sub(since) < SUMMARY_INTERVAL.as_millis() as u64)
+            || self
+                .since
+                .compare_exchange(since, 0, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return None;
+        }
+
+        Some((
+            self.suppressed.swap(0, Ordering::Relaxed),
+            now.saturating_sub(since),
+        ))
+    }
+}
+
+/// Get the maximum number of records per second that are written at each level, if limited.
+pub fn burst_limit() -> Option<u64> {
+    match LIMIT.load(Ordering::SeqCst) {
+        0 => None,
+        limit => Some(limit),
+    }
+}
+
+/// Limit the number of records written per second at each level, or `None` to write every record. Disabled by default.
+///
+/// Once more records than the limit arrive at a level within a second, the rest of that second's records at the level
+/// are discarded, so a tight loop that goes wrong cannot lock up the terminal. While records are being discarded, a
+/// summary such as `suppressed 1,240 similar debug messages in the last 5s` is written every 5 seconds, and when the
+/// logger is flushed.
+///
+/// This function may be called at any time.
+pub fn set_burst_limit(limit: Option<u64>) {
+    LIMIT.store(limit.unwrap_or(0), Ordering::SeqCst);
+}
+
+/// Decide whether an enabled record should be kept under the burst limit.
+///
+/// When a summary is due for the level of the record, it is written first.
+pub(crate) fn keep(record: &Record) -> bool {
+    let limit = LIMIT.load(Ordering::Relaxed);
+    if limit == 0 {
+        return true;
+    }
+
+    let window = &WINDOWS[record.level() as usize - 1];
+    let now = clock::monotonic().as_millis() as u64;
+    let start = window.start.load(Ordering::Relaxed);
+
+    if now.saturating_sub(start) >= 1000
+        && window
+            .start
+            .compare_exchange(start, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    {
+        window.count.store(0, Ordering::Relaxed);
+    }
+
+    let keep = window.count.fetch_add(1, Ordering::Relaxed) < limit;
+    if !keep {
+        drops::count(Cause::Burst);
+
+        if window.suppressed.fetch_add(1, Ordering::Relaxed) == 0 {
+            window.since.store(now.max(1), Ordering::Relaxed);
+        }
+    }
+
+    if let Some(summary) = window.take(now, false) {
+        report(record.level(), summary);
+    }
+
+    keep
+}
+
+/// Write the summaries of all levels that have suppressed records, regardless of when the last one was written.
+pub(crate) fn flush() {
+    let now = clock::monotonic().as_millis() as u64;
+
+    for (window, level) in WINDOWS.iter().zip(Level::iter()) {
+        if let Some(summary) = window.take(now, true) {
+            report(level, summary);
+        }
+    }
+}
+
+fn report(level: Level, (suppressed, millis): (u64, u64)) {
+    let translation = translation::current();
+    let message = translation.format(
+        Message::BurstSummary,
+        &[
+            ("count", &group_digits(suppressed)),
+            ("level", &translation.level_name(level)),
+            ("seconds", &millis.div_ceil(1000).max(1)),
+        ],
+    );
+    drop(translation);
+
+    super::self_report(level, super::SELF_TARGET, format_args!("{}", message));
+}
+
+/// Format a number with commas between groups of thousands.
+pub(crate) fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    grouped
+}
@@ -0,0 +1,120 @@
+//! Capturing output written directly to file descriptors, such as by C libraries.
+
+use console::{self, Stream};
+use error::Error;
+use log::{log, Level};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::io::RawFd;
+use std::thread;
+
+/// Capture everything written to a file descriptor, and log it line by line at the given level and target.
+///
+/// A pipe is installed over the file descriptor, so that output written to it by any code in the process, such as a
+/// noisy C library printing to standard error, is logged instead and obeys the verbosity, quiet mode and filters like
+/// any other record. Output of the logger itself is still written to the original stream.
+///
+/// ```no_run
+/// # extern crate clogger;
+/// # extern crate log;
+/// use log::Level;
+///
+/// # fn main() {
+/// clogger::init();
+///
+/// // Messages printed by the library are only shown at verbosity 2 or above.
+/// let _capture = clogger::capture_fd(2, "libfoo", Level::Debug).unwrap();
+/// # }
+/// ```
+///
+/// The file descriptor is restored when the returned guard is dropped, after all captured lines have been logged. Note
+/// that output buffered by the C standard library is only captured once it is flushed, which for standard output
+/// usually happens at the end of each line only when it is a terminal.
+///
+/// Only available on Unix.
+pub fn capture_fd(fd: RawFd, target: &str, level: Level) -> Result<FdCapture, Error> {
+    let stream = [Stream::Stdout, Stream::Stderr]
+        .iter()
+        .copied()
+        .find(|stream| stream.fd() == fd);
+
+    match stream {
+        Some(Stream::Stdout) => io::stdout().flush()?,
+        Some(Stream::Stderr) => io::stderr().flush()?,
+        None => {}
+    }
+
+    let (original, reader) = console::redirect(fd)?;
+    let target = target.to_owned();
+
+    let reader = thread::Builder::new()
+        .name("clogger-capture".into())
+        .spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+
+            while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+                let text = String::from_utf8_lossy(&line);
+                log!(target: &target, level, "{}", text.trim_end_matches(['\n', '\r']));
+                line.clear();
+            }
+        });
+
+    let reader = match reader {
+        Ok(reader) => reader,
+        Err(e) => {
+            console::restore(fd, &original);
+            return Err(e.into());
+        }
+    };
+
+    // The logger keeps writing to a standard stream through the original, so that its output is not captured too.
+    let original = match stream {
+        Some(stream) => {
+            let copy = original.try_clone()?;
+            console::set_original(stream, Some(original));
+            copy
+        }
+        None => original,
+    };
+
+    Ok(FdCapture {
+        fd,
+        stream,
+        original,
+        reader: Some(reader),
+    })
+}
+
+/// A guard that captures output written to a file descriptor until it is dropped, returned by [`capture_fd`].
+#[must_use = "the file descriptor is restored as soon as the guard is dropped"]
+pub struct FdCapture {
+    fd: RawFd,
+    stream: Option<Stream>,
+    original: File,
+    reader: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for FdCapture {
+    fn drop(&mut self) {
+        match self.stream {
+            Some(Stream::Stdout) => {
+                let _ = io::stdout().flush();
+            }
+            Some(Stream::Stderr) => {
+                let _ = io::stderr().flush();
+            }
+            None => {}
+        }
+
+        console::restore(self.fd, &self.original);
+
+        if let Some(stream) = self.stream {
+            console::set_original(stream, None);
+        }
+
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
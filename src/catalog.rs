@@ -0,0 +1,218 @@
+//! A catalog of message templates identified by stable codes.
+//!
+//! A record carries a code either by a `code` key-value pair, or by being logged using [`coded!`] with a code that has
+//! a registered template. The code is displayed along with the level, such as `error[E0042]: ...`, so that tools can
+//! document and suppress individual diagnostics the way compilers and linters do.
+
+use burst;
+use log::{log, Level};
+use record::Entry;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Mutex, RwLock};
+use translation::{self, Message};
+
+/// The key of the key-value pair that codes are read from.
+pub(crate) const KEY: &str = "code";
+
+static TEMPLATES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// The number of records discarded for each code since the last report.
+static SUPPRESSED: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    /// The code of the record being logged by [`coded!`] on the current thread.
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Register the template of the message identified by a code, replacing any previous template for the code.
+///
+/// The template may contain placeholders such as `{path}`, which are replaced with the values of the same names given
+/// to [`coded!`](::coded!). Literal braces are written as `{{` and `}}`.
+///
+/// ```
+/// clogger::register_message("E0042", "failed to open {path}");
+/// clogger::register_message("W0007", "{count} files were skipped");
+/// ```
+///
+/// This function may be called at any time.
+pub fn register_message(code: &str, template: &str) {
+    TEMPLATES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(code.to_owned(), template.to_owned());
+}
+
+/// Get the template registered for a code, if any.
+pub fn message_template(code: &str) -> Option<String> {
+    TEMPLATES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(code)
+        .cloned()
+}
+
+/// Log a record using the [registered template](::register_message) for a code.
+///
+/// The code is displayed along with the level in the human-readable formats, and included as the `code` field in the
+/// [JSON](::Format::Json) and [key-value](::Format::KeyValue) formats. It is also available to sinks using
+/// [`Entry::code`](::Entry::code). The [machine-readable format](::set_machine_mode) is not affected.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::Level;
+///
+/// clogger::register_message("E0042", "failed to open {path}");
+///
+/// // Displayed as "error[E0042]: failed to open config.toml".
+/// coded!(Level::Error, "E0042", path = "config.toml");
+/// # }
+/// ```
+///
+/// If no template is registered for the code, the values are displayed as key-value pairs instead, so that nothing is
+/// lost. With the `kv` feature, any record can also be given a code directly, such as
+/// `error!(code = "E0042"; "failed to open {}", path)`.
+#[macro_export]
+macro_rules! coded {
+    (target: $target:expr, $level:expr, $code:expr $(, $key:ident = $value:expr)* $(,)*) => {
+        $crate::__coded(
+            $target,
+            $level,
+            $code,
+            &[$((stringify!($key), &$value)),*],
+        )
+    };
+    ($level:expr, $code:expr $(, $key:ident = $value:expr)* $(,)*) => {
+        $crate::__coded(
+            module_path!(),
+            $level,
+            $code,
+            &[$((stringify!($key), &$value)),*],
+        )
+    };
+}
+
+#[doc(hidden)]
+pub fn __coded(target: &str, level: Level, code: &str, values: &[(&str, &dyn fmt::Display)]) {
+    struct Reset(Option<String>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let message = match message_template(code) {
+        Some(template) => render(&template, values),
+        None => {
+            let mut message = String::new();
+            for (i, &(key, value)) in values.iter().enumerate() {
+                if i > 0 {
+                    message.push(' ');
+                }
+                let _ = write!(message, "{}={}", key, value);
+            }
+            message
+        }
+    };
+
+    let _reset = Reset(CURRENT.with(|current| current.borrow_mut().replace(code.to_owned())));
+    log!(target: target, level, "{}", message);
+}
+
+/// Replace the placeholders of a template with the given values, leaving unknown placeholders as they are.
+fn render(template: &str, values: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(index) = rest.find(['{', '}']) {
+        message.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            message.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        let value = rest
+            .find('}')
+            .filter(|_| rest.starts_with('{'))
+            .and_then(|end| {
+                let name = &rest[1..end];
+                values
+                    .iter()
+                    .find(|&&(key, _)| key == name)
+                    .map(|&(_, value)| (value, end))
+            });
+
+        match value {
+            Some((value, end)) => {
+                let _ = write!(message, "{}", value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    message.push_str(rest);
+    message
+}
+
+/// Take the code of a record being logged on the current thread out of its fields, or from [`coded!`].
+pub(crate) fn collect(fields: &mut Vec<(String, String)>) -> Option<String> {
+    let field = fields
+        .iter()
+        .position(|(key, _)| key == KEY)
+        .map(|index| fields.remove(index).1);
+
+    field.or_else(|| CURRENT.with(|current| current.borrow().clone()))
+}
+
+/// Count a record discarded because of its code.
+pub(crate) fn count_suppressed(entry: &Entry) {
+    if let Some(code) = entry.code() {
+        *SUPPRESSED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(code.to_owned())
+            .or_default() += 1;
+    }
+}
+
+/// Write a single info record summarizing the records discarded for each code since the last summary, if any were.
+///
+/// Unlike other diagnostics about the logger itself, the summary is displayed regardless of the
+/// [self-log level](::set_self_log_level), since it was asked for by suppressing codes.
+pub(crate) fn report() {
+    let counts = std::mem::take(&mut *SUPPRESSED.lock().unwrap_or_else(|e| e.into_inner()));
+
+    if counts.is_empty() {
+        return;
+    }
+
+    let total = counts.values().sum::<u64>();
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let details = counts
+        .iter()
+        .map(|(code, count)| format!("{} {}", burst::group_digits(*count), code))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = translation::current().format(
+        if total == 1 {
+            Message::SuppressedByCodeOne
+        } else {
+            Message::SuppressedByCode
+        },
+        &[("count", &burst::group_digits(total)), ("codes", &details)],
+    );
+    super::self_report(Level::Info, super::SELF_TARGET, format_args!("{}", message));
+}
@@ -0,0 +1,68 @@
+//! Notifications of configuration changes.
+
+use std::cell::Cell;
+use std::sync::RwLock;
+
+type Callback = Box<dyn Fn(Change) + Send + Sync>;
+
+static CALLBACKS: RwLock<Vec<Callback>> = RwLock::new(Vec::new());
+
+thread_local! {
+    /// Set while callbacks are being run on the current thread.
+    static NOTIFYING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A kind of configuration change that callbacks registered with [`on_change`] are notified about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The [verbosity](::set_verbosity) changed.
+    Verbosity,
+
+    /// [Quiet mode](::set_quiet) was turned on or off, or the [quiet level](::set_quiet_level) changed.
+    Quiet,
+
+    /// A filter was added, removed, enabled or disabled.
+    Filters,
+}
+
+/// Register a function to be called whenever the verbosity, quiet mode or filters change.
+///
+/// This is useful for keeping other subsystems in sync with the logger, such as a process manager that passes `-v` on
+/// to child processes. The function is called on the thread that made the change, after the change has been applied.
+/// Changes made by the function itself do not cause it to be called again.
+///
+/// ```
+/// use clogger::Change;
+///
+/// clogger::on_change(|change| {
+///     if change == Change::Verbosity {
+///         println!("verbosity is now {}", clogger::verbosity());
+///     }
+/// });
+/// ```
+///
+/// The function must not register further callbacks.
+///
+/// This function may be called at any time.
+pub fn on_change<F>(callback: F)
+where
+    F: Fn(Change) + Send + Sync + 'static,
+{
+    CALLBACKS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Box::new(callback));
+}
+
+/// Notify all registered callbacks about a change.
+pub(crate) fn notify(change: Change) {
+    if NOTIFYING.with(|notifying| notifying.replace(true)) {
+        return;
+    }
+
+    for callback in CALLBACKS.read().unwrap_or_else(|e| e.into_inner()).iter() {
+        callback(change);
+    }
+
+    NOTIFYING.with(|notifying| notifying.set(false));
+}
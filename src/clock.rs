@@ -0,0 +1,109 @@
+//! Sources of time.
+
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+static CLOCK: RwLock<Option<Box<dyn Clock>>> = RwLock::new(None);
+
+/// A source of the current time, used for timestamps and trace timing.
+///
+/// The system clock is used by default. Tests can replace it using [`set_clock`] so that timestamped output is
+/// deterministic.
+pub trait Clock: Send + Sync + 'static {
+    /// Get the current wall-clock time.
+    fn now(&self) -> SystemTime;
+
+    /// Get the time elapsed since an arbitrary fixed point, which must never decrease.
+    fn monotonic(&self) -> Duration;
+}
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        EPOCH.get_or_init(Instant::now).elapsed()
+    }
+}
+
+/// A clock that only advances when told to, for use in tests.
+///
+/// Clones of a manual clock share the same time, so a clone can be kept to control the clock after it has been set.
+///
+/// ```
+/// use clogger::ManualClock;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+/// clogger::set_clock(clock.clone());
+///
+/// clock.advance(Duration::from_millis(250));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    state: Arc<Mutex<(SystemTime, Duration)>>,
+}
+
+impl ManualClock {
+    /// Create a clock that starts at the given time.
+    pub fn new(start: SystemTime) -> Self {
+        ManualClock {
+            state: Arc::new(Mutex::new((start, Duration::ZERO))),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 += duration;
+        state.1 += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new(UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).0
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
+
+/// Replace the clock used for timestamps and trace timing.
+///
+/// Trace timing is measured from the time the logger was initialized, so the clock should be set before initializing
+/// the logger.
+///
+/// This function may be called at any time.
+pub fn set_clock<C: Clock>(clock: C) {
+    *CLOCK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(clock));
+}
+
+/// Get the current wall-clock time.
+pub(crate) fn now() -> SystemTime {
+    match *CLOCK.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+/// Get the current monotonic time.
+pub(crate) fn monotonic() -> Duration {
+    match *CLOCK.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref clock) => clock.monotonic(),
+        None => SystemClock.monotonic(),
+    }
+}
@@ -0,0 +1,286 @@
+//! Console output streams and color detection.
+
+use log::Level;
+use progress;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::io::PipeReader;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, BorrowedFd, RawFd};
+#[cfg(feature = "color")]
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// Controls whether console output is colored.
+#[cfg(feature = "color")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color output written to a stream only if that stream is a terminal that supports color. This is the default.
+    ///
+    /// The `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE` environment variables are respected.
+    Auto,
+
+    /// Always color output.
+    Always,
+
+    /// Never color output.
+    Never,
+}
+
+#[cfg(feature = "color")]
+impl ColorChoice {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ColorChoice::Always,
+            2 => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            ColorChoice::Auto => 0,
+            ColorChoice::Always => 1,
+            ColorChoice::Never => 2,
+        }
+    }
+}
+
+/// One of the standard output streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The original standard output and error streams while they are redirected, such as by an
+/// [`OutputGuard`](::test::OutputGuard), so that records still reach them.
+#[cfg(unix)]
+static ORIGINALS: Mutex<[Option<File>; 2]> = Mutex::new([None, None]);
+
+/// The number of bytes of [buffered output](::set_buffered_output) held back for a stream before it is written.
+const BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Buffered output of each stream that has not been written yet.
+static PENDING: [Mutex<String>; 2] = [const { Mutex::new(String::new()) }; 2];
+
+/// Cached color support for each stream: 0 if not yet detected, 1 if unsupported, 2 if supported.
+#[cfg(feature = "color")]
+static STDOUT_COLOR: AtomicU8 = AtomicU8::new(0);
+#[cfg(feature = "color")]
+static STDERR_COLOR: AtomicU8 = AtomicU8::new(0);
+
+impl Stream {
+    /// Get the file descriptor of the stream.
+    #[cfg(unix)]
+    pub(crate) fn fd(self) -> RawFd {
+        match self {
+            Stream::Stdout => 1,
+            Stream::Stderr => 2,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Stream::Stdout => 0,
+            Stream::Stderr => 1,
+        }
+    }
+
+    /// Check if the stream supports color, detecting it on first use.
+    ///
+    /// Each stream is detected independently, since it is common for one to be redirected to a file while the other
+    /// is still attached to a terminal.
+    #[cfg(feature = "color")]
+    pub(crate) fn supports_color(self) -> bool {
+        let cache = match self {
+            Stream::Stdout => &STDOUT_COLOR,
+            Stream::Stderr => &STDERR_COLOR,
+        };
+
+        match cache.load(Ordering::Relaxed) {
+            0 => {
+                let supported = self.detect_color();
+                cache.store(if supported { 2 } else { 1 }, Ordering::Relaxed);
+                supported
+            }
+            value => value == 2,
+        }
+    }
+
+    #[cfg(feature = "color")]
+    fn detect_color(self) -> bool {
+        let choice = match self {
+            Stream::Stdout => anstream::AutoStream::choice(&io::stdout()),
+            Stream::Stderr => anstream::AutoStream::choice(&io::stderr()),
+        };
+
+        choice != anstream::ColorChoice::Never
+    }
+
+    /// Write a string to the stream, ignoring any errors.
+    pub(crate) fn write_str(self, s: &str) {
+        let _ = self.with_writer(|writer| writer.write_all(s.as_bytes()));
+    }
+
+    /// Write a line to the stream, ignoring any errors.
+    ///
+    /// The line and its terminator are written using a single call, so that lines of a multi-line record cannot be
+    /// interleaved with output written to the stream by other threads or processes. Any buffered output is written
+    /// first.
+    pub(crate) fn write_line(self, line: &str) {
+        self.write_pending(Some(line));
+    }
+
+    /// Write a line of a record at the given level to the stream, holding it back while output is
+    /// [buffered](::set_buffered_output) unless it is a warning or error.
+    pub(crate) fn write_record(self, level: Level, line: &str) {
+        if level <= Level::Warn || !super::buffered_output() {
+            return self.write_line(line);
+        }
+
+        let mut pending = PENDING[self.index()]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        pending.push_str(line);
+        pending.push('\n');
+
+        if pending.len() >= BUFFER_CAPACITY {
+            drop(pending);
+            self.write_pending(None);
+        }
+    }
+
+    /// Write any buffered output followed by the given line, if any.
+    fn write_pending(self, line: Option<&str>) {
+        // Holding the lock while writing keeps buffered output in order with lines written by other threads.
+        let mut pending = PENDING[self.index()]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        if let Some(line) = line {
+            pending.push_str(line);
+            pending.push('\n');
+        }
+
+        if pending.is_empty() {
+            return;
+        }
+
+        progress::suspend(|| {
+            let _ = self.with_writer(|writer| writer.write_all(pending.as_bytes()));
+        });
+        pending.clear();
+    }
+
+    /// Run a function with a writer for the stream.
+    ///
+    /// Escape sequences are passed through as-is on terminals that understand them, and translated into console API
+    /// calls on legacy Windows consoles that do not.
+    fn with_writer<T>(self, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+        #[cfg(unix)]
+        {
+            let mut originals = ORIGINALS.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Some(ref mut original) = originals[self.index()] {
+                return wrap(original, f);
+            }
+        }
+
+        // The output of the crate's own unit tests is captured by the test harness, like that of the `print!` family of
+        // macros, so that it is only shown for failing tests.
+        #[cfg(test)]
+        {
+            f(&mut Harness(self))
+        }
+
+        #[cfg(not(test))]
+        match self {
+            Stream::Stdout => wrap(io::stdout().lock(), f),
+            Stream::Stderr => wrap(io::stderr().lock(), f),
+        }
+    }
+
+    /// Write any buffered output and flush the stream, ignoring any errors.
+    pub(crate) fn flush(self) {
+        self.write_pending(None);
+
+        let _ = match self {
+            Stream::Stdout => io::stdout().flush(),
+            Stream::Stderr => io::stderr().flush(),
+        };
+    }
+}
+
+/// Write the logger's output for a stream to the given file instead, returning the file previously set.
+#[cfg(unix)]
+pub(crate) fn set_original(stream: Stream, original: Option<File>) -> Option<File> {
+    std::mem::replace(
+        &mut ORIGINALS.lock().unwrap_or_else(|e| e.into_inner())[stream.index()],
+        original,
+    )
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn dup2(src: c_int, dst: c_int) -> c_int;
+}
+
+/// Redirect a file descriptor into a new pipe, returning a duplicate of the original file descriptor and the read end
+/// of the pipe.
+#[cfg(unix)]
+pub(crate) fn redirect(fd: RawFd) -> io::Result<(File, PipeReader)> {
+    let original = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+    let (reader, writer) = io::pipe()?;
+
+    if unsafe { dup2(writer.as_raw_fd(), fd) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok((File::from(original), reader))
+}
+
+/// Restore a file descriptor redirected using [`redirect`], which closes the write end of the pipe.
+#[cfg(unix)]
+pub(crate) fn restore(fd: RawFd, original: &File) {
+    unsafe {
+        dup2(original.as_raw_fd(), fd);
+    }
+}
+
+#[cfg(feature = "color")]
+fn wrap<W: anstream::stream::RawStream + anstream::stream::AsLockedWrite, T>(
+    writer: W,
+    f: impl FnOnce(&mut dyn Write) -> T,
+) -> T {
+    f(&mut anstream::AutoStream::always(writer))
+}
+
+/// A writer for a stream that goes through the output capture of the test harness.
+#[cfg(test)]
+struct Harness(Stream);
+
+#[cfg(test)]
+impl Write for Harness {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        match self.0 {
+            Stream::Stdout => print!("{}", text),
+            Stream::Stderr => eprint!("{}", text),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "color"))]
+fn wrap<W: Write, T>(mut writer: W, f: impl FnOnce(&mut dyn Write) -> T) -> T {
+    f(&mut writer)
+}
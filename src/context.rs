@@ -0,0 +1,40 @@
+//! Global key-value pairs that describe the whole program run.
+
+use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard};
+
+static CONTEXT: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+pub(crate) fn read() -> RwLockReadGuard<'static, Vec<(String, String)>> {
+    CONTEXT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set a key-value pair that is included with every record in the [key-value format](::Format::KeyValue), such as
+/// the name of the host or the environment being deployed to.
+///
+/// ```
+/// clogger::set_context("env", "staging");
+/// ```
+///
+/// This replaces any value previously set for the key.
+///
+/// This function may be called at any time.
+pub fn set_context<V: fmt::Display>(key: &str, value: V) {
+    let mut context = CONTEXT.write().unwrap_or_else(|e| e.into_inner());
+    let value = value.to_string();
+
+    match context.iter_mut().find(|(k, _)| k == key) {
+        Some(pair) => pair.1 = value,
+        None => context.push((key.to_owned(), value)),
+    }
+}
+
+/// Remove a key-value pair set using [`set_context`].
+///
+/// This function may be called at any time.
+pub fn remove_context(key: &str) {
+    CONTEXT
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(k, _)| k != key);
+}
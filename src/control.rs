@@ -0,0 +1,173 @@
+//! A command interface for adjusting and inspecting logging at runtime.
+
+#[cfg(unix)]
+use error::Error;
+use log::LevelFilter;
+use record::Entry;
+use sink::{self, FailurePolicy, Sink};
+#[cfg(unix)]
+use std::io::BufReader;
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const HELP: &str = "\
+commands:
+  verbosity <n>            set the verbosity
+  level <level>            set the verbosity to show records up to a level
+  filter <target>=<level>  set the level for a target
+  quiet <on|off>           turn quiet mode on or off
+  status                   show the current settings
+  tail                     stream records until another line is sent
+  help                     show this help";
+
+/// Serve control commands read from a stream, writing responses to another stream.
+///
+/// Commands are read one per line on a background thread until the input ends. This can be used to control a
+/// long-running program through a file descriptor or pipe set up by its parent. The following commands are accepted:
+///
+/// - `verbosity <n>`: Set the [verbosity](::set_verbosity).
+/// - `level <level>`: Set the verbosity so that records up to the given level are shown, such as `level debug`.
+/// - `filter <target>=<level>`: Set the [level for a target](::set_target_level), such as `filter myapp::net=trace`.
+/// - `quiet <on|off>`: Turn [quiet mode](::set_quiet) on or off.
+/// - `status`: Show the current settings.
+/// - `tail`: Stream records as they are logged, until another line is sent.
+/// - `help`: Show the list of commands.
+///
+/// Each command is answered with `ok`, the requested output, or a line starting with `error: `.
+pub fn serve_control<R, W>(input: R, output: W)
+where
+    R: BufRead + Send + 'static,
+    W: Write + Send + 'static,
+{
+    thread::Builder::new()
+        .name("clogger-control".into())
+        .spawn(move || serve(input, Arc::new(Mutex::new(output))))
+        .expect("failed to spawn control thread");
+}
+
+/// Serve control commands on a Unix domain socket at the given path.
+///
+/// Any existing file at the path is removed first. Each connection is served on its own thread using the commands
+/// described in [`serve_control`], so that an operator can connect using a tool such as `socat`:
+///
+/// ```text
+/// $ socat - UNIX-CONNECT:/run/mytool/control.sock
+/// level debug
+/// ok
+/// ```
+#[cfg(unix)]
+pub fn listen_control<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+
+    thread::Builder::new()
+        .name("clogger-control".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Ok(reader) = stream.try_clone() {
+                    serve_control(BufReader::new(reader), stream);
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+fn serve<R: BufRead, W: Write + Send + 'static>(input: R, output: Arc<Mutex<W>>) {
+    let mut lines = input.lines();
+
+    while let Some(Ok(line)) = lines.next() {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let argument = words.next();
+
+        let response = match (command, argument) {
+            ("tail", None) => {
+                let handle = sink::add_sink(Tail(output.clone()));
+                handle.set_failure_policy(FailurePolicy::Disable);
+
+                // Stream until the client sends another line or disconnects.
+                let next = lines.next();
+                handle.remove();
+
+                if next.is_none() {
+                    return;
+                }
+                Ok(None)
+            }
+            _ => run(command, argument).map(Some),
+        };
+
+        let mut output = output.lock().unwrap_or_else(|e| e.into_inner());
+        let written = match response {
+            Ok(Some(text)) => writeln!(output, "{}", text),
+            Ok(None) => writeln!(output, "ok"),
+            Err(e) => writeln!(output, "error: {}", e),
+        };
+
+        if written.and_then(|_| output.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Run a command other than `tail`, returning the text to respond with.
+fn run(command: &str, argument: Option<&str>) -> Result<String, String> {
+    match (command, argument) {
+        ("verbosity", Some(n)) => {
+            let verbosity = n.parse().map_err(|_| format!("invalid verbosity: {}", n))?;
+            super::set_verbosity(verbosity);
+        }
+        ("level", Some(level)) => {
+            let verbosity = match parse_level(level)? {
+                LevelFilter::Info => 1,
+                LevelFilter::Debug => 2,
+                LevelFilter::Trace => 3,
+                _ => 0,
+            };
+            super::set_verbosity(verbosity);
+        }
+        ("filter", Some(directive)) => {
+            super::set_target_levels(directive).map_err(|e| e.to_string())?;
+        }
+        ("quiet", Some("on")) => super::set_quiet(true),
+        ("quiet", Some("off")) => super::set_quiet(false),
+        ("status", None) => {
+            return Ok(format!(
+                "verbosity {}\nquiet {}",
+                super::verbosity(),
+                if super::quiet() { "on" } else { "off" }
+            ));
+        }
+        ("help", None) => return Ok(HELP.to_owned()),
+        _ => return Err(format!("unknown command or missing argument: {}", command)),
+    }
+
+    Ok("ok".to_owned())
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse()
+        .map_err(|_| format!("invalid level: {}", level))
+}
+
+/// A sink that streams records to a control connection.
+struct Tail<W>(Arc<Mutex<W>>);
+
+impl<W: Write + Send + 'static> Sink for Tail<W> {
+    fn write(&self, _: &Entry, line: &str) -> io::Result<()> {
+        let mut output = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        output.write_all(format!("{}\n", line).as_bytes())?;
+        output.flush()
+    }
+}
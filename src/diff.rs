@@ -0,0 +1,44 @@
+//! Rendering of unified diffs.
+
+use log::{log, log_enabled, Level};
+use palette;
+use style::Style;
+
+/// Log a unified diff or other multi-line text, one record per line.
+///
+/// Lines are colored according to their role in the diff: added lines starting with `+` are green, removed lines
+/// starting with `-` are red, hunk headers starting with `@@` are cyan and file headers are bold. Other lines are
+/// logged as-is. Colors are only applied on the console when colored output is enabled.
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// let diff = "--- a/config.toml\n+++ b/config.toml\n@@ -1 +1 @@\n-debug = false\n+debug = true\n";
+///
+/// clogger::log_diff(module_path!(), log::Level::Info, diff);
+/// # }
+/// ```
+pub fn log_diff(target: &str, level: Level, diff: &str) {
+    if !log_enabled!(target: target, level) {
+        return;
+    }
+
+    let palette = palette::palette();
+
+    for line in diff.lines() {
+        let style = if line.starts_with("+++") || line.starts_with("---") {
+            Style::new().bold()
+        } else if line.starts_with('+') {
+            Style::new().fg(palette.positive())
+        } else if line.starts_with('-') {
+            Style::new().fg(palette.negative())
+        } else if line.starts_with("@@") {
+            Style::new().fg(palette.accent())
+        } else {
+            Style::new()
+        };
+
+        log!(target: target, level, "{}", style.paint(line));
+    }
+}
@@ -0,0 +1,85 @@
+//! Totals of records that were discarded instead of being written.
+
+use burst;
+use log::Level;
+use std::sync::atomic::{AtomicU64, Ordering};
+use translation::{self, Message};
+
+/// Why a record was discarded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cause {
+    /// Sampled out by the [sampling rule](::set_target_sampling) of its target.
+    Sampling,
+
+    /// Over the [burst limit](::set_burst_limit).
+    Burst,
+
+    /// The [queue](::SinkHandle::set_queue_capacity) of a sink was full. Counted once for each sink.
+    SinkQueue,
+
+    /// Logged while another record was being handled on the same thread, and discarded under the
+    /// [re-entrancy setting](::set_reentrancy).
+    Reentrant,
+}
+
+static SAMPLING: AtomicU64 = AtomicU64::new(0);
+static BURST: AtomicU64 = AtomicU64::new(0);
+static SINK_QUEUE: AtomicU64 = AtomicU64::new(0);
+static REENTRANT: AtomicU64 = AtomicU64::new(0);
+
+fn counter(cause: Cause) -> &'static AtomicU64 {
+    match cause {
+        Cause::Sampling => &SAMPLING,
+        Cause::Burst => &BURST,
+        Cause::SinkQueue => &SINK_QUEUE,
+        Cause::Reentrant => &REENTRANT,
+    }
+}
+
+/// Count a discarded record.
+pub(crate) fn count(cause: Cause) {
+    counter(cause).fetch_add(1, Ordering::Relaxed);
+}
+
+/// Write a single warning summarizing the records discarded since the last summary, if any were.
+///
+/// This is called when the program [finishes](::finish) or [exits](::exit), so that missing diagnostics are noticed.
+pub(crate) fn report() {
+    let causes = [
+        (Cause::Sampling, Message::DroppedBySampling),
+        (Cause::Burst, Message::DroppedByBurstLimit),
+        (Cause::SinkQueue, Message::DroppedBySinkQueues),
+        (Cause::Reentrant, Message::DroppedReentrant),
+    ];
+
+    let counts = causes
+        .iter()
+        .map(|&(cause, text)| (counter(cause).swap(0, Ordering::Relaxed), text))
+        .filter(|&(count, _)| count > 0)
+        .collect::<Vec<_>>();
+
+    if counts.is_empty() {
+        return;
+    }
+
+    let translation = translation::current();
+    let total = counts.iter().map(|&(count, _)| count).sum::<u64>();
+    let details = counts
+        .iter()
+        .map(|&(count, message)| {
+            translation.format(message, &[("count", &burst::group_digits(count))])
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = translation.format(
+        if total == 1 {
+            Message::DroppedOne
+        } else {
+            Message::Dropped
+        },
+        &[("count", &burst::group_digits(total)), ("causes", &details)],
+    );
+    drop(translation);
+
+    super::self_log(Level::Warn, format_args!("{}", message));
+}
@@ -0,0 +1,106 @@
+//! Errors returned when configuring the logger.
+
+use format::LineFormatError;
+use log::SetLoggerError;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// An error returned by a fallible configuration function, such as [`try_init`](::try_init) or
+/// [`FileSink::open`](::FileSink::open).
+///
+/// More variants may be added as the configuration options grow, so matching on this type should include a wildcard
+/// arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The global logger could not be installed, because another logger is already installed.
+    Init(SetLoggerError),
+
+    /// A filter directive could not be parsed.
+    Filter {
+        /// The directive as it was given.
+        directive: String,
+
+        /// Why the directive is invalid.
+        reason: String,
+    },
+
+    /// A command line argument given to [`parse_args`](::parse_args) could not be parsed.
+    Argument {
+        /// The argument as it was given.
+        argument: String,
+
+        /// Why the argument is invalid.
+        reason: String,
+    },
+
+    /// A [line format](::set_line_format) contains placeholders that are not recognized.
+    LineFormat(LineFormatError),
+
+    /// A regular expression is not valid.
+    #[cfg(feature = "regex")]
+    Pattern(regex::Error),
+
+    /// A sink or other output could not be set up, such as because a file could not be opened.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Init(ref e) => write!(f, "failed to install logger: {}", e),
+            Error::Filter {
+                ref directive,
+                ref reason,
+            } => write!(f, "invalid filter directive `{}`: {}", directive, reason),
+            Error::Argument {
+                ref argument,
+                ref reason,
+            } => write!(f, "invalid argument `{}`: {}", argument, reason),
+            Error::LineFormat(ref e) => e.fmt(f),
+            #[cfg(feature = "regex")]
+            Error::Pattern(ref e) => write!(f, "invalid pattern: {}", e),
+            Error::Io(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Init(ref e) => Some(e),
+            Error::Filter { .. } => None,
+            Error::Argument { .. } => None,
+            Error::LineFormat(ref e) => Some(e),
+            #[cfg(feature = "regex")]
+            Error::Pattern(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<SetLoggerError> for Error {
+    fn from(e: SetLoggerError) -> Self {
+        Error::Init(e)
+    }
+}
+
+impl From<LineFormatError> for Error {
+    fn from(e: LineFormatError) -> Self {
+        Error::LineFormat(e)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Error::Pattern(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
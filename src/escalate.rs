@@ -0,0 +1,103 @@
+//! Escalation of record levels.
+
+use clock;
+use log::Level;
+use record::Entry;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The number of distinct messages tracked by a repetition rule before messages that have not repeated recently are
+/// forgotten.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// A single escalation rule.
+#[derive(Clone)]
+pub(crate) enum Rule {
+    /// Escalate records whose message matches a pattern.
+    #[cfg(feature = "regex")]
+    Matching(Regex, Level),
+
+    /// Escalate records whose message repeats too often.
+    Repeated(Arc<Repeats>, Level),
+}
+
+/// Tracks how often each message has been logged recently.
+pub(crate) struct Repeats {
+    times: usize,
+    window: Duration,
+    seen: Mutex<HashMap<(String, String), VecDeque<Duration>>>,
+}
+
+impl Repeats {
+    pub(crate) fn new(times: usize, window: Duration) -> Self {
+        Repeats {
+            times,
+            window,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that a message was logged, returning true if it has been logged more than the allowed number of times
+    /// within the window.
+    fn check(&self, entry: &Entry) -> bool {
+        let now = clock::monotonic();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+
+        if seen.len() >= PRUNE_THRESHOLD {
+            seen.retain(|_, times| {
+                times
+                    .back()
+                    .is_some_and(|&time| now.saturating_sub(time) <= self.window)
+            });
+        }
+
+        let times = seen
+            .entry((entry.target().to_owned(), entry.message().to_owned()))
+            .or_default();
+
+        while times
+            .front()
+            .is_some_and(|&time| now.saturating_sub(time) > self.window)
+        {
+            times.pop_front();
+        }
+
+        times.push_back(now);
+        times.len() > self.times
+    }
+}
+
+/// The enabled escalation rules.
+pub(crate) struct Escalations {
+    rules: Vec<Rule>,
+}
+
+impl Escalations {
+    pub(crate) const fn new() -> Self {
+        Escalations { rules: Vec::new() }
+    }
+
+    pub(crate) fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Raise the level of an entry to the most severe level of all rules that apply to it.
+    ///
+    /// Every repetition rule sees every entry, so that repeats are counted even while another rule applies.
+    pub(crate) fn apply(&self, entry: &mut Entry) {
+        for rule in &self.rules {
+            let (applies, level) = match *rule {
+                #[cfg(feature = "regex")]
+                Rule::Matching(ref regex, level) => (regex.is_match(entry.message()), level),
+                Rule::Repeated(ref repeats, level) => (repeats.check(entry), level),
+            };
+
+            if applies && level < entry.level() {
+                entry.set_level(level);
+            }
+        }
+    }
+}
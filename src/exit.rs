@@ -0,0 +1,163 @@
+//! Process exit codes derived from what was logged.
+
+use burst;
+use catalog;
+use drops;
+use log::{log, Level};
+use metrics;
+use pager;
+#[cfg(feature = "async")]
+use queue;
+use status;
+use std::fmt;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+type Policy = Box<dyn Fn(Option<Level>) -> i32 + Send + Sync>;
+
+/// The most severe level of any record written so far, or 0 if none were.
+static WORST: AtomicUsize = AtomicUsize::new(0);
+
+static POLICY: RwLock<Option<Policy>> = RwLock::new(None);
+
+/// Note that a record was written at the given level.
+pub(crate) fn observe(level: Level) {
+    let level = level as usize;
+
+    let _ = WORST.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |worst| {
+        if worst == 0 || level < worst {
+            Some(level)
+        } else {
+            None
+        }
+    });
+}
+
+/// Get the most severe level of any record written so far, including [failed footers](::finish).
+///
+/// Records that were not displayed because of the verbosity or filters are not counted, while escalated records are
+/// counted at their escalated level.
+pub fn worst_level() -> Option<Level> {
+    super::level_from_usize(WORST.load(Ordering::SeqCst))
+}
+
+/// Set the policy that maps the [most severe level written](worst_level) to the code that the process should exit
+/// with, replacing any previous policy.
+///
+/// The default policy returns 1 if an error was written, and 0 otherwise. Registering the same policy in each of a
+/// suite of related tools keeps their exit codes consistent, such as one that also treats warnings as a distinct
+/// outcome:
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::Level;
+///
+/// clogger::set_exit_policy(|worst| match worst {
+///     Some(Level::Error) => 1,
+///     Some(Level::Warn) => 2,
+///     _ => 0,
+/// });
+/// # }
+/// ```
+///
+/// This function may be called at any time.
+pub fn set_exit_policy<F>(policy: F)
+where
+    F: Fn(Option<Level>) -> i32 + Send + Sync + 'static,
+{
+    *POLICY.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(policy));
+}
+
+/// Get the code that the process should exit with according to the [exit policy](set_exit_policy) and what has been
+/// written so far.
+pub fn exit_code() -> i32 {
+    let worst = worst_level();
+
+    match *POLICY.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref policy) => policy(worst),
+        None if worst == Some(Level::Error) => 1,
+        None => 0,
+    }
+}
+
+/// Flush all output, including output held back for the [pager](::set_pager), and exit the process with the code given by the [exit policy](set_exit_policy).
+///
+/// Like [`finish`](::finish), this first writes a warning summarizing any records that were dropped.
+///
+/// ```no_run
+/// clogger::init();
+///
+/// // ...
+///
+/// clogger::exit();
+/// ```
+pub fn exit() -> ! {
+    terminate(None)
+}
+
+/// Log an error and [exit](exit()) the process.
+///
+/// The message is logged the same way as with [`error!`](log::error), all sinks are flushed, and the process exits with
+/// the code given by the [exit policy](set_exit_policy), which is 1 by default. An exit code can also be given
+/// explicitly, which takes precedence over the policy:
+///
+/// ```no_run
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// # let path = "config.toml";
+/// # let found = false;
+/// if !found {
+///     fatal!("config file {} not found", path);
+/// }
+///
+/// // Exits with code 78, the conventional code for a configuration error.
+/// fatal!(code: 78, "config file {} is not valid", path);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! fatal {
+    (code: $code:expr, target: $target:expr, $($arg:tt)+) => {
+        $crate::__fatal($target, Some($code), format_args!($($arg)+))
+    };
+    (code: $code:expr, $($arg:tt)+) => {
+        $crate::__fatal(module_path!(), Some($code), format_args!($($arg)+))
+    };
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::__fatal($target, None, format_args!($($arg)+))
+    };
+    ($($arg:tt)+) => {
+        $crate::__fatal(module_path!(), None, format_args!($($arg)+))
+    };
+}
+
+#[doc(hidden)]
+pub fn __fatal(target: &str, code: Option<i32>, args: fmt::Arguments) -> ! {
+    log!(target: target, Level::Error, "{}", args);
+    terminate(code)
+}
+
+/// Flush all output and exit with the given code, or the code given by the exit policy.
+fn terminate(code: Option<i32>) -> ! {
+    shut_down();
+    log::logger().flush();
+    process::exit(code.unwrap_or_else(exit_code))
+}
+
+/// Write everything that is still pending when the program [finishes](::finish) or [exits](exit), before the footer
+/// and the final flush: queued records, the summaries of dropped and suppressed records and of the timings, and output
+/// held back for the pager, and then clear the status line.
+///
+/// Summaries of records suppressed by the burst limit are written first, so that they are not left in a queue.
+pub(crate) fn shut_down() {
+    burst::flush();
+    #[cfg(feature = "async")]
+    queue::flush();
+    drops::report();
+    catalog::report();
+    metrics::report();
+    pager::show();
+    status::reset();
+}
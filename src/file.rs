@@ -0,0 +1,640 @@
+//! Log files.
+
+use error::Error;
+use log::Level;
+use record::Entry;
+use sink::Sink;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use timestamp::{self, Precision, TimestampFormat, Timezone};
+
+/// A sink that writes records to a file.
+///
+/// ```no_run
+/// use clogger::{FileSink, Retention};
+///
+/// let dir = clogger::log_dir("mytool").unwrap();
+/// let sink = FileSink::per_run(&dir, Retention::new().max_files(10)).unwrap();
+/// clogger::add_sink(sink);
+/// ```
+pub struct FileSink {
+    path: PathBuf,
+    state: Mutex<State>,
+    rotate_at: Option<u64>,
+    compression: Compression,
+    retention: Retention,
+    instance: Option<String>,
+    /// The background thread that compresses rotated files and applies the retention policy, started on the first
+    /// rotation. Rotated files are handled one at a time, so that retention never deletes a file while it is still
+    /// being compressed.
+    worker: Mutex<Option<Sender<PathBuf>>>,
+}
+
+struct State {
+    file: File,
+    size: u64,
+}
+
+/// How rotated log files are compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Rotated files are not compressed. This is the default.
+    None,
+
+    /// Rotated files are compressed using gzip, and given a `.gz` extension.
+    #[cfg(feature = "gzip")]
+    Gzip,
+
+    /// Rotated files are compressed using Zstandard, and given a `.zst` extension.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl FileSink {
+    /// Open a file to append records to, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(FileSink {
+            path,
+            state: Mutex::new(State { file, size }),
+            rotate_at: None,
+            compression: Compression::None,
+            retention: Retention::new(),
+            instance: None,
+            worker: Mutex::new(None),
+        })
+    }
+
+    /// Rotate the file once it would grow larger than the given number of bytes.
+    ///
+    /// When the file is rotated, it is renamed by appending the time of rotation to its name, such as
+    /// `app.log.2024-05-01T12-00-00.000`, and a new file is started in its place. Any compression and retention of
+    /// rotated files happens on a background thread, so that logging is not blocked.
+    pub fn rotate_at(self, max_bytes: u64) -> Self {
+        FileSink {
+            rotate_at: Some(max_bytes),
+            ..self
+        }
+    }
+
+    /// Set how rotated files are compressed.
+    pub fn compress(self, compression: Compression) -> Self {
+        FileSink {
+            compression,
+            ..self
+        }
+    }
+
+    /// Set which rotated files are kept. Files are only deleted after they have been compressed, so compressed files
+    /// count towards the retention policy.
+    pub fn retain(self, retention: Retention) -> Self {
+        FileSink { retention, ..self }
+    }
+
+    /// Coordinate with other processes writing to the same file, such as parallel jobs of a build.
+    ///
+    /// Each line is prefixed with the given instance ID, such as `[job3]`, or with the process ID if `None`, so that
+    /// lines from different processes can be told apart. On Unix, an advisory lock is held on the file while each line
+    /// is written and while the file is rotated, and a file that was rotated by another process is reopened before
+    /// writing to it. Even without the lock, each line is appended using a single write, so lines from different
+    /// processes do not corrupt each other.
+    ///
+    /// ```no_run
+    /// use clogger::FileSink;
+    ///
+    /// let job = std::env::var("JOB").ok();
+    /// let sink = FileSink::open("build.log").unwrap().shared(job.as_deref());
+    /// clogger::add_sink(sink);
+    /// ```
+    pub fn shared(self, instance: Option<&str>) -> Self {
+        FileSink {
+            instance: Some(
+                instance
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_else(|| format!("pid{}", process::id())),
+            ),
+            ..self
+        }
+    }
+
+    /// Create a new, uniquely named file for the current run of the program in the given directory.
+    ///
+    /// The directory is created if it does not exist. Files are named after the time the program was started and its
+    /// process ID, such as `2024-05-01T12-00-00-pid1234.log`. Files from previous runs that are no longer retained
+    /// under the given retention policy are deleted. Other files in the directory are left alone.
+    pub fn per_run<P: AsRef<Path>>(dir: P, retention: Retention) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let time = timestamp::format(
+            SystemTime::now(),
+            TimestampFormat::Rfc3339,
+            Precision::Seconds,
+            Timezone::Utc,
+        );
+        let name = format!(
+            "{}-pid{}.log",
+            time.trim_end_matches('Z').replace(':', "-"),
+            process::id()
+        );
+
+        let sink = FileSink::open(dir.join(name))?;
+        retention.apply(dir, &sink.path, is_per_run_file)?;
+        Ok(sink)
+    }
+
+    /// Get the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotate(&self, state: &mut State) -> io::Result<()> {
+        let time = timestamp::format(
+            SystemTime::now(),
+            TimestampFormat::Rfc3339,
+            Precision::Millis,
+            Timezone::Utc,
+        );
+        let mut name = self.path.file_name().unwrap_or_default().to_owned();
+        name.push(format!(".{}", time.trim_end_matches('Z').replace(':', "-")));
+
+        // Files rotated within the same millisecond, such as by other processes sharing the file, are told apart
+        // using a counter.
+        let mut rotated = self.path.with_file_name(&name);
+        let mut count = 1;
+        while rotated.exists() {
+            let mut numbered = name.clone();
+            numbered.push(format!("-{}", count));
+            rotated = self.path.with_file_name(numbered);
+            count += 1;
+        }
+
+        state.file.flush()?;
+        fs::rename(&self.path, &rotated)?;
+
+        state.file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        state.size = 0;
+
+        let mut worker = self.worker.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = worker
+            .get_or_insert_with(|| self.spawn_worker())
+            .send(rotated);
+
+        Ok(())
+    }
+
+    /// Start the background thread that compresses rotated files and applies the retention policy.
+    fn spawn_worker(&self) -> Sender<PathBuf> {
+        let (sender, receiver) = mpsc::channel::<PathBuf>();
+        let path = self.path.clone();
+        let compression = self.compression;
+        let retention = self.retention;
+
+        thread::spawn(move || {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let prefix = format!(
+                "{}.",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            );
+            let is_rotated = |file: &Path| {
+                file.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix))
+            };
+
+            for rotated in receiver {
+                if let Err(e) = compress(&rotated, compression) {
+                    super::self_log(
+                        Level::Warn,
+                        format_args!("failed to compress log file {}: {}", rotated.display(), e),
+                    );
+                }
+
+                let _ = retention.apply(dir, &path, is_rotated);
+            }
+        });
+
+        sender
+    }
+
+    /// Write a line while holding the lock on the file.
+    #[cfg(unix)]
+    fn write_shared(&self, state: &mut State, line: &str) -> io::Result<()> {
+        lock(&state.file, LOCK_EX)?;
+        let result = self.write_locked(state, line);
+
+        // A file replaced by rotation has been closed already, which released its lock.
+        let _ = lock(&state.file, LOCK_UN);
+        result
+    }
+
+    #[cfg(unix)]
+    fn write_locked(&self, state: &mut State, line: &str) -> io::Result<()> {
+        let current = state.file.metadata()?;
+        let replaced = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.dev() != current.dev() || metadata.ino() != current.ino(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => true,
+            Err(e) => return Err(e),
+        };
+
+        if replaced {
+            state.file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&self.path)?;
+            lock(&state.file, LOCK_EX)?;
+        }
+
+        // Other processes append to the file too, so the size must be read from the file itself.
+        state.size = state.file.metadata()?.len();
+
+        if let Some(max_bytes) = self.rotate_at {
+            if state.size > 0 && state.size + line.len() as u64 > max_bytes {
+                self.rotate(state)?;
+                lock(&state.file, LOCK_EX)?;
+            }
+        }
+
+        state.file.write_all(line.as_bytes())?;
+        state.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+const LOCK_EX: c_int = 2;
+#[cfg(unix)]
+const LOCK_UN: c_int = 8;
+
+#[cfg(unix)]
+extern "C" {
+    fn flock(fd: c_int, operation: c_int) -> c_int;
+}
+
+/// Apply or remove an advisory lock on a file, waiting until it can be applied.
+#[cfg(unix)]
+fn lock(file: &File, operation: c_int) -> io::Result<()> {
+    loop {
+        if unsafe { flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&self, _: &Entry, line: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let line = match self.instance {
+            Some(ref instance) => format!("[{}] {}\n", instance, line),
+            None => format!("{}\n", line),
+        };
+
+        #[cfg(unix)]
+        {
+            if self.instance.is_some() {
+                return self.write_shared(&mut state, &line);
+            }
+        }
+
+        if let Some(max_bytes) = self.rotate_at {
+            if state.size > 0 && state.size + line.len() as u64 > max_bytes {
+                self.rotate(&mut state)?;
+            }
+        }
+
+        state.file.write_all(line.as_bytes())?;
+        state.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .file
+            .flush()
+    }
+}
+
+/// Compress a rotated log file, replacing it with the compressed file.
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+fn compress(path: &Path, compression: Compression) -> io::Result<()> {
+    match compression {
+        Compression::None => Ok(()),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => replace_with(path, "gz", |input, output| {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(input, &mut encoder)?;
+            encoder.finish()?.sync_all()
+        }),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => replace_with(path, "zst", |input, output| {
+            let mut encoder = zstd::Encoder::new(output, 0)?;
+            io::copy(input, &mut encoder)?;
+            encoder.finish()?.sync_all()
+        }),
+    }
+}
+
+/// Replace a file with an encoded copy that has an additional extension.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn replace_with<F>(path: &Path, extension: &str, encode: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File, File) -> io::Result<()>,
+{
+    let mut encoded_path = path.as_os_str().to_owned();
+    encoded_path.push(".");
+    encoded_path.push(extension);
+
+    encode(&mut File::open(path)?, File::create(&encoded_path)?)?;
+    fs::remove_file(path)
+}
+
+/// Which old log files are kept.
+///
+/// A retention policy is enforced when a [per-run file](FileSink::per_run) is created, and each time a file is
+/// [rotated](FileSink::rotate_at). A file is deleted if it exceeds any of the limits. By default, all files are kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Retention {
+    max_age: Option<Duration>,
+    max_files: Option<usize>,
+    max_total_size: Option<u64>,
+}
+
+impl Retention {
+    /// Create a retention policy that keeps all files.
+    pub fn new() -> Self {
+        Retention::default()
+    }
+
+    /// Delete files that were last modified longer ago than the given duration.
+    pub fn max_age(self, age: Duration) -> Self {
+        Retention {
+            max_age: Some(age),
+            ..self
+        }
+    }
+
+    /// Keep at most the given number of files, including the current file, deleting the oldest files first.
+    pub fn max_files(self, count: usize) -> Self {
+        Retention {
+            max_files: Some(count),
+            ..self
+        }
+    }
+
+    /// Keep at most the given number of bytes in total across all files, including the current file, deleting the
+    /// oldest files first.
+    pub fn max_total_size(self, bytes: u64) -> Self {
+        Retention {
+            max_total_size: Some(bytes),
+            ..self
+        }
+    }
+
+    /// Delete old log files in a directory that match the given predicate, never deleting the current file.
+    fn apply(&self, dir: &Path, current: &Path, matches: impl Fn(&Path) -> bool) -> io::Result<()> {
+        if *self == Retention::new() {
+            return Ok(());
+        }
+
+        let mut files = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path == current || !matches(&path) {
+                continue;
+            }
+
+            if let Ok(metadata) = entry.metadata() {
+                if let Ok(modified) = metadata.modified() {
+                    files.push((modified, path, metadata.len()));
+                }
+            }
+        }
+
+        // Newest first.
+        files.sort_by(|a, b| b.cmp(a));
+
+        let now = SystemTime::now();
+        let keep = self.max_files.map(|count| count.saturating_sub(1));
+        let mut total_size = fs::metadata(current).map_or(0, |metadata| metadata.len());
+
+        for (i, (modified, path, size)) in files.into_iter().enumerate() {
+            total_size += size;
+
+            let too_old = self.max_age.is_some_and(|age| {
+                now.duration_since(modified)
+                    .is_ok_and(|elapsed| elapsed > age)
+            });
+            let too_many = keep.is_some_and(|keep| i >= keep);
+            let too_large = self.max_total_size.is_some_and(|max| total_size > max);
+
+            if too_old || too_many || too_large {
+                if let Err(e) = fs::remove_file(&path) {
+                    super::self_log(
+                        Level::Warn,
+                        format_args!("failed to remove old log file {}: {}", path.display(), e),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check if a path looks like a file created by [`FileSink::per_run`].
+fn is_per_run_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(".log"))
+        .and_then(|name| name.rsplit_once("-pid"))
+        .is_some_and(|(_, pid)| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Get the conventional directory for the log files of an application, such as `~/.cache/mytool/logs` on Linux.
+///
+/// Returns `None` if the home directory cannot be determined.
+pub fn log_dir(app: &str) -> Option<PathBuf> {
+    let cache = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| Path::new(&home).join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| Path::new(dir).is_absolute())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+    };
+
+    cache.map(|cache| cache.join(app).join("logs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// A directory of log files, deleted when dropped.
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("clogger-test-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Dir(path)
+        }
+
+        /// Create a file of the given size, last modified the given number of hours ago.
+        fn create(&self, name: &str, size: usize, hours: u64) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, vec![b'x'; size]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(hours * 3600))
+                .unwrap();
+            path
+        }
+
+        fn names(&self) -> Vec<String> {
+            let mut names: Vec<String> = fs::read_dir(&self.0)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Create a current file and three older per-run files of 10 bytes each, along with an unrelated file.
+    fn files(name: &str) -> (Dir, PathBuf) {
+        let dir = Dir::new(name);
+        let current = dir.create("app-4-pid4.log", 10, 0);
+        dir.create("app-3-pid3.log", 10, 1);
+        dir.create("app-2-pid2.log", 10, 2);
+        dir.create("app-1-pid1.log", 10, 3);
+        dir.create("notes.txt", 10, 4);
+        (dir, current)
+    }
+
+    #[test]
+    fn per_run_files_are_recognized() {
+        assert!(is_per_run_file(Path::new("logs/app-2024-pid42.log")));
+        assert!(!is_per_run_file(Path::new("logs/app-pid.log")));
+        assert!(!is_per_run_file(Path::new("logs/app-pid42.txt")));
+        assert!(!is_per_run_file(Path::new("logs/notes.log")));
+    }
+
+    #[test]
+    fn all_files_are_kept_by_default() {
+        let (dir, current) = files("default");
+        Retention::new()
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(dir.names().len(), 5);
+    }
+
+    #[test]
+    fn max_files_includes_the_current_file() {
+        let (dir, current) = files("max-files");
+        Retention::new()
+            .max_files(2)
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(
+            dir.names(),
+            ["app-3-pid3.log", "app-4-pid4.log", "notes.txt"]
+        );
+    }
+
+    #[test]
+    fn max_files_never_deletes_the_current_file() {
+        let (dir, current) = files("max-files-zero");
+        Retention::new()
+            .max_files(0)
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(dir.names(), ["app-4-pid4.log", "notes.txt"]);
+    }
+
+    #[test]
+    fn max_age_deletes_old_files() {
+        let (dir, current) = files("max-age");
+        Retention::new()
+            .max_age(Duration::from_secs(90 * 60))
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(
+            dir.names(),
+            ["app-3-pid3.log", "app-4-pid4.log", "notes.txt"]
+        );
+    }
+
+    #[test]
+    fn max_total_size_deletes_the_oldest_files() {
+        let (dir, current) = files("max-total-size");
+        Retention::new()
+            .max_total_size(35)
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(
+            dir.names(),
+            [
+                "app-2-pid2.log",
+                "app-3-pid3.log",
+                "app-4-pid4.log",
+                "notes.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn any_exceeded_limit_deletes_a_file() {
+        let (dir, current) = files("combined");
+        Retention::new()
+            .max_files(3)
+            .max_age(Duration::from_secs(90 * 60))
+            .apply(&dir.0, &current, is_per_run_file)
+            .unwrap();
+        assert_eq!(
+            dir.names(),
+            ["app-3-pid3.log", "app-4-pid4.log", "notes.txt"]
+        );
+    }
+}
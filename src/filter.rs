@@ -0,0 +1,679 @@
+//! Record filtering.
+//!
+//! Every filter that is registered is kept in a list along with a handle that can be used to remove or disable it
+//! later. Whenever the list changes, the enabled filters are compiled into structures that are cheap to query when
+//! filtering records.
+//!
+//! Targets are organized into a tree keyed by module path segment, so that settings configured for a module are
+//! inherited by all of its submodules unless they are overridden further down.
+
+use change::{self, Change};
+use escalate::{self, Escalations};
+use log::{Level, LevelFilter};
+#[cfg(feature = "regex")]
+use regex::Regex;
+use sample::Sampler;
+#[cfg(feature = "regex")]
+use sink;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use style::Color;
+
+static FILTERS: RwLock<Filters> = RwLock::new(Filters::new());
+
+pub(crate) fn read() -> RwLockReadGuard<'static, Filters> {
+    FILTERS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write() -> RwLockWriteGuard<'static, Filters> {
+    FILTERS.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A handle to a registered filter.
+///
+/// Handles are returned by every function that registers a filter, including the filters of a
+/// [sink](::SinkHandle::suppress_matching), and can be used to remove the filter or to temporarily disable it. Dropping
+/// a handle does not remove the filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FilterHandle {
+    id: u64,
+    /// The ID of the sink the filter belongs to, if it is not a global filter.
+    #[cfg(feature = "regex")]
+    sink: Option<u64>,
+}
+
+impl FilterHandle {
+    /// Create a handle to a filter of the sink with the given ID.
+    #[cfg(feature = "regex")]
+    pub(crate) fn for_sink(sink: u64, id: u64) -> Self {
+        FilterHandle {
+            id,
+            sink: Some(sink),
+        }
+    }
+
+    /// Check if the filter is currently enabled.
+    ///
+    /// Returns `false` if the filter has been removed.
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "regex")]
+        {
+            if let Some(sink) = self.sink {
+                return sink::filter_enabled(sink, self.id);
+            }
+        }
+
+        read()
+            .entries
+            .iter()
+            .any(|entry| entry.id == self.id && entry.enabled)
+    }
+
+    /// Enable or disable the filter.
+    ///
+    /// A disabled filter has no effect until it is enabled again. Has no effect if the filter has been removed.
+    pub fn set_enabled(&self, enabled: bool) {
+        #[cfg(feature = "regex")]
+        {
+            if let Some(sink) = self.sink {
+                return sink::set_filter_enabled(sink, self.id, Some(enabled));
+            }
+        }
+
+        let mut filters = write();
+
+        if let Some(entry) = filters.entries.iter_mut().find(|entry| entry.id == self.id) {
+            entry.enabled = enabled;
+        }
+
+        filters.compile();
+        drop(filters);
+        super::update_max_level();
+        change::notify(Change::Filters);
+    }
+
+    /// Remove the filter permanently.
+    pub fn remove(self) {
+        #[cfg(feature = "regex")]
+        {
+            if let Some(sink) = self.sink {
+                return sink::set_filter_enabled(sink, self.id, None);
+            }
+        }
+
+        let mut filters = write();
+        filters.entries.retain(|entry| entry.id != self.id);
+        filters.compile();
+        drop(filters);
+        super::update_max_level();
+        change::notify(Change::Filters);
+    }
+}
+
+/// A single filter rule.
+pub(crate) enum Filter {
+    /// Mark a target as belonging to the application.
+    AppTarget(String),
+
+    /// Set the level for a target.
+    TargetLevel(String, LevelFilter),
+
+    /// Sample the records of a target.
+    Sample(String, Arc<Sampler>),
+
+    /// Only allow messages matching a pattern.
+    #[cfg(feature = "regex")]
+    Include(Regex),
+
+    /// Reject messages matching a pattern.
+    #[cfg(feature = "regex")]
+    Exclude(Regex),
+
+    /// Escalate the level of records.
+    Escalate(escalate::Rule),
+
+    /// Display a target using a different name.
+    Alias(String, String),
+
+    /// Only allow records with a tag.
+    IncludeTag(String),
+
+    /// Reject records with a tag.
+    ExcludeTag(String),
+
+    /// Color records with a tag.
+    TagColor(String, Color),
+
+    /// Suppress or change the level of records with a code.
+    Code(String, CodeRule),
+}
+
+struct Entry {
+    id: u64,
+    enabled: bool,
+    filter: Filter,
+}
+
+/// All registered filters, along with their compiled form.
+pub(crate) struct Filters {
+    next_id: u64,
+    entries: Vec<Entry>,
+    targets: TargetTree,
+    messages: MessageFilters,
+    escalations: Escalations,
+    aliases: Aliases,
+    tags: TagFilters,
+    codes: CodeFilters,
+}
+
+impl Filters {
+    const fn new() -> Self {
+        Filters {
+            next_id: 0,
+            entries: Vec::new(),
+            targets: TargetTree::new(),
+            messages: MessageFilters::new(),
+            escalations: Escalations::new(),
+            aliases: Aliases::new(),
+            tags: TagFilters::new(),
+            codes: CodeFilters::new(),
+        }
+    }
+
+    /// Register a new filter, returning a handle to it.
+    pub(crate) fn add(filter: Filter) -> FilterHandle {
+        let mut filters = write();
+        let id = filters.next_id;
+
+        filters.next_id += 1;
+        filters.entries.push(Entry {
+            id,
+            enabled: true,
+            filter,
+        });
+        filters.compile();
+        drop(filters);
+        super::update_max_level();
+        change::notify(Change::Filters);
+
+        FilterHandle {
+            id,
+            #[cfg(feature = "regex")]
+            sink: None,
+        }
+    }
+
+    pub(crate) fn targets(&self) -> &TargetTree {
+        &self.targets
+    }
+
+    pub(crate) fn messages(&self) -> &MessageFilters {
+        &self.messages
+    }
+
+    pub(crate) fn escalations(&self) -> &Escalations {
+        &self.escalations
+    }
+
+    pub(crate) fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    pub(crate) fn tags(&self) -> &TagFilters {
+        &self.tags
+    }
+
+    pub(crate) fn codes(&self) -> &CodeFilters {
+        &self.codes
+    }
+
+    /// Rebuild the compiled filters from the enabled entries.
+    fn compile(&mut self) {
+        let mut targets = TargetTree::new();
+        #[cfg_attr(not(feature = "regex"), allow(unused_mut))]
+        let mut messages = MessageFilters::new();
+        let mut escalations = Escalations::new();
+        let mut aliases = Aliases::new();
+        let mut tags = TagFilters::new();
+        let mut codes = CodeFilters::new();
+
+        // Aliases are collected first, so that other filters can refer to targets by alias regardless of the order in
+        // which they were added.
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            if let Filter::Alias(ref target, ref alias) = entry.filter {
+                aliases.add(target, alias);
+            }
+        }
+
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            match entry.filter {
+                Filter::AppTarget(ref target) => targets.add_app_target(&aliases.resolve(target)),
+                Filter::TargetLevel(ref target, level) => {
+                    targets.set_level(&aliases.resolve(target), level)
+                }
+                Filter::Sample(ref target, ref sampler) => {
+                    targets.set_sampler(&aliases.resolve(target), sampler.clone())
+                }
+                #[cfg(feature = "regex")]
+                Filter::Include(ref regex) => messages.include(regex.clone()),
+                #[cfg(feature = "regex")]
+                Filter::Exclude(ref regex) => messages.exclude(regex.clone()),
+                Filter::Escalate(ref rule) => escalations.add(rule.clone()),
+                Filter::Alias(..) => {}
+                Filter::IncludeTag(ref tag) => tags.include.push(tag.clone()),
+                Filter::ExcludeTag(ref tag) => tags.exclude.push(tag.clone()),
+                Filter::TagColor(ref tag, color) => tags.colors.insert(0, (tag.clone(), color)),
+                Filter::Code(ref code, rule) => {
+                    codes.rules.insert(code.clone(), rule);
+                }
+            }
+        }
+
+        self.targets = targets;
+        self.messages = messages;
+        self.escalations = escalations;
+        self.aliases = aliases;
+        self.tags = tags;
+        self.codes = codes;
+    }
+}
+
+/// A prefix tree of module path segments holding the filter settings for each configured target.
+pub(crate) struct TargetTree {
+    root: Node,
+    has_app_targets: bool,
+}
+
+#[derive(Default)]
+struct Node {
+    level: Option<LevelFilter>,
+    app: bool,
+    sampler: Option<Arc<Sampler>>,
+    children: BTreeMap<String, Node>,
+}
+
+/// The settings that apply to a specific target, combined from all of its ancestors.
+pub(crate) struct TargetMatch {
+    /// The level configured for the closest matching ancestor, if any.
+    pub(crate) level: Option<LevelFilter>,
+
+    /// Whether the target belongs to the application, as opposed to a dependency.
+    pub(crate) app: bool,
+}
+
+impl TargetTree {
+    const fn new() -> Self {
+        TargetTree {
+            root: Node {
+                level: None,
+                app: false,
+                sampler: None,
+                children: BTreeMap::new(),
+            },
+            has_app_targets: false,
+        }
+    }
+
+    /// Set the level for the given target and all of its children.
+    fn set_level(&mut self, target: &str, level: LevelFilter) {
+        self.node_mut(target).level = Some(level);
+    }
+
+    /// Sample the records of the given target and all of its children.
+    fn set_sampler(&mut self, target: &str, sampler: Arc<Sampler>) {
+        self.node_mut(target).sampler = Some(sampler);
+    }
+
+    /// Mark the given target and all of its children as belonging to the application.
+    fn add_app_target(&mut self, target: &str) {
+        self.node_mut(target).app = true;
+        self.has_app_targets = true;
+    }
+
+    /// Find the settings that apply to the given target.
+    pub(crate) fn lookup(&self, target: &str) -> TargetMatch {
+        let mut node = &self.root;
+        let mut result = TargetMatch {
+            level: node.level,
+            app: !self.has_app_targets,
+        };
+
+        for segment in target.split("::") {
+            node = match node.children.get(segment) {
+                Some(child) => child,
+                None => break,
+            };
+
+            if node.level.is_some() {
+                result.level = node.level;
+            }
+
+            result.app |= node.app;
+        }
+
+        result
+    }
+
+    /// Find the sampler of the closest ancestor of the given target that is sampled, if any.
+    pub(crate) fn sampler(&self, target: &str) -> Option<&Sampler> {
+        let mut node = &self.root;
+        let mut sampler = node.sampler.as_deref();
+
+        for segment in target.split("::") {
+            node = match node.children.get(segment) {
+                Some(child) => child,
+                None => break,
+            };
+
+            if node.sampler.is_some() {
+                sampler = node.sampler.as_deref();
+            }
+        }
+
+        sampler
+    }
+
+    /// Get the most verbose level configured for any target.
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.root.max_level()
+    }
+
+    fn node_mut(&mut self, target: &str) -> &mut Node {
+        target.split("::").fold(&mut self.root, |node, segment| {
+            node.children.entry(segment.to_owned()).or_default()
+        })
+    }
+}
+
+impl Node {
+    fn max_level(&self) -> LevelFilter {
+        self.children
+            .values()
+            .map(Node::max_level)
+            .fold(self.level.unwrap_or(LevelFilter::Off), Ord::max)
+    }
+}
+
+/// Filters applied to the rendered message text of a record.
+///
+/// Without the `regex` feature there is no way to add any message filters, so every message is allowed.
+pub(crate) struct MessageFilters {
+    #[cfg(feature = "regex")]
+    include: Vec<Regex>,
+    #[cfg(feature = "regex")]
+    exclude: Vec<Regex>,
+}
+
+impl MessageFilters {
+    pub(crate) const fn new() -> Self {
+        MessageFilters {
+            #[cfg(feature = "regex")]
+            include: Vec::new(),
+            #[cfg(feature = "regex")]
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Only allow messages matching the given pattern, or any other included pattern.
+    #[cfg(feature = "regex")]
+    pub(crate) fn include(&mut self, regex: Regex) {
+        self.include.push(regex);
+    }
+
+    /// Reject messages matching the given pattern.
+    #[cfg(feature = "regex")]
+    pub(crate) fn exclude(&mut self, regex: Regex) {
+        self.exclude.push(regex);
+    }
+
+    /// Check if a message passes all configured filters.
+    #[cfg(feature = "regex")]
+    pub(crate) fn allows(&self, message: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(message)))
+            && !self.exclude.iter().any(|regex| regex.is_match(message))
+    }
+
+    #[cfg(not(feature = "regex"))]
+    pub(crate) fn allows(&self, _: &str) -> bool {
+        true
+    }
+}
+
+/// Filters and colors applied to the tags of a record.
+pub(crate) struct TagFilters {
+    include: Vec<String>,
+    exclude: Vec<String>,
+
+    /// Pairs of tags and their colors, with the most recently added first.
+    colors: Vec<(String, Color)>,
+}
+
+impl TagFilters {
+    const fn new() -> Self {
+        TagFilters {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            colors: Vec::new(),
+        }
+    }
+
+    /// Check if a record with the given tags passes all configured filters.
+    pub(crate) fn allows(&self, tags: &[String]) -> bool {
+        (self.include.is_empty() || tags.iter().any(|tag| self.include.contains(tag)))
+            && !tags.iter().any(|tag| self.exclude.contains(tag))
+    }
+
+    /// Get the color of a record with the given tags, if any of them has one.
+    pub(crate) fn color(&self, tags: &[String]) -> Option<Color> {
+        self.colors
+            .iter()
+            .find(|(tag, _)| tags.contains(tag))
+            .map(|&(_, color)| color)
+    }
+}
+
+/// What to do with records that have a specific code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CodeRule {
+    /// Discard the records.
+    Suppress,
+
+    /// Display the records at a different level.
+    Level(Level),
+}
+
+/// Rules applied to the codes of records, with the most recently added rule for a code taking precedence.
+pub(crate) struct CodeFilters {
+    rules: BTreeMap<String, CodeRule>,
+}
+
+impl CodeFilters {
+    const fn new() -> Self {
+        CodeFilters {
+            rules: BTreeMap::new(),
+        }
+    }
+
+    /// Get the rule for a code, if any.
+    pub(crate) fn rule(&self, code: &str) -> Option<CodeRule> {
+        self.rules.get(code).copied()
+    }
+}
+
+/// Friendly display names for targets.
+pub(crate) struct Aliases {
+    /// Pairs of targets and their aliases, with the most recently added first.
+    aliases: Vec<(String, String)>,
+}
+
+impl Aliases {
+    const fn new() -> Self {
+        Aliases {
+            aliases: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, target: &str, alias: &str) {
+        self.aliases
+            .insert(0, (target.to_owned(), alias.to_owned()));
+    }
+
+    /// Get the name to display for a target.
+    ///
+    /// Children of an aliased target are displayed using the alias in place of the aliased part, so that
+    /// `myapp::db::pool` is displayed as `db::pool` if `myapp::db` is aliased as `db`.
+    pub(crate) fn display<'a>(&self, target: &'a str) -> Cow<'a, str> {
+        Self::replace(target, self.aliases.iter().map(|(t, a)| (t, a)))
+    }
+
+    /// Get the target that a name refers to, which may be an alias or a child of an alias.
+    fn resolve<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        Self::replace(name, self.aliases.iter().map(|(t, a)| (a, t)))
+    }
+
+    /// Replace the longest prefix of a target that matches one of the given pairs.
+    fn replace<'a, 'b>(
+        target: &'a str,
+        pairs: impl Iterator<Item = (&'b String, &'b String)>,
+    ) -> Cow<'a, str> {
+        let mut best: Option<(&str, &str)> = None;
+
+        for (from, to) in pairs {
+            let matches = target
+                .strip_prefix(from.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"));
+
+            if matches && best.is_none_or(|(best, _)| from.len() > best.len()) {
+                best = Some((from, to));
+            }
+        }
+
+        match best {
+            Some((from, to)) => Cow::Owned(format!("{}{}", to, &target[from.len()..])),
+            None => Cow::Borrowed(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(levels: &[(&str, LevelFilter)]) -> TargetTree {
+        let mut tree = TargetTree::new();
+        for &(target, level) in levels {
+            tree.set_level(target, level);
+        }
+        tree
+    }
+
+    fn add(filters: &mut Filters, filter: Filter) -> u64 {
+        let id = filters.next_id;
+        filters.next_id += 1;
+        filters.entries.push(Entry {
+            id,
+            enabled: true,
+            filter,
+        });
+        filters.compile();
+        id
+    }
+
+    #[test]
+    fn lookup_inherits_level_of_closest_ancestor() {
+        let tree = tree(&[
+            ("myapp", LevelFilter::Warn),
+            ("myapp::db", LevelFilter::Trace),
+        ]);
+
+        assert_eq!(tree.lookup("myapp").level, Some(LevelFilter::Warn));
+        assert_eq!(tree.lookup("myapp::net").level, Some(LevelFilter::Warn));
+        assert_eq!(tree.lookup("myapp::db").level, Some(LevelFilter::Trace));
+        assert_eq!(
+            tree.lookup("myapp::db::pool").level,
+            Some(LevelFilter::Trace)
+        );
+        assert_eq!(tree.lookup("hyper").level, None);
+    }
+
+    #[test]
+    fn lookup_matches_whole_segments_only() {
+        let tree = tree(&[("myapp::db", LevelFilter::Debug)]);
+
+        assert_eq!(tree.lookup("myapp::dbx").level, None);
+        assert_eq!(tree.lookup("myapp").level, None);
+        assert_eq!(tree.lookup("myapp::db::x").level, Some(LevelFilter::Debug));
+    }
+
+    #[test]
+    fn lookup_marks_app_targets() {
+        let mut tree = TargetTree::new();
+        assert!(tree.lookup("hyper").app);
+
+        tree.add_app_target("myapp");
+        assert!(tree.lookup("myapp::db").app);
+        assert!(!tree.lookup("hyper").app);
+    }
+
+    #[test]
+    fn max_level_is_most_verbose_level() {
+        let tree = tree(&[
+            ("myapp", LevelFilter::Warn),
+            ("myapp::db::pool", LevelFilter::Trace),
+        ]);
+
+        assert_eq!(tree.max_level(), LevelFilter::Trace);
+        assert_eq!(TargetTree::new().max_level(), LevelFilter::Off);
+    }
+
+    #[test]
+    fn later_filters_override_earlier_ones() {
+        let mut filters = Filters::new();
+        add(
+            &mut filters,
+            Filter::TargetLevel("myapp".to_owned(), LevelFilter::Debug),
+        );
+        let id = add(
+            &mut filters,
+            Filter::TargetLevel("myapp".to_owned(), LevelFilter::Error),
+        );
+
+        assert_eq!(
+            filters.targets().lookup("myapp::db").level,
+            Some(LevelFilter::Error)
+        );
+
+        // Disabling the later filter restores the earlier one.
+        filters
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .unwrap()
+            .enabled = false;
+        filters.compile();
+
+        assert_eq!(
+            filters.targets().lookup("myapp::db").level,
+            Some(LevelFilter::Debug)
+        );
+    }
+
+    #[test]
+    fn filters_apply_to_aliased_targets() {
+        let mut filters = Filters::new();
+        add(
+            &mut filters,
+            Filter::TargetLevel("db".to_owned(), LevelFilter::Trace),
+        );
+        add(
+            &mut filters,
+            Filter::Alias("myapp::db".to_owned(), "db".to_owned()),
+        );
+
+        assert_eq!(
+            filters.targets().lookup("myapp::db::pool").level,
+            Some(LevelFilter::Trace)
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Reporting the final outcome of a program.
+
+use clock;
+use console::Stream;
+use exit;
+use format::{self, Format};
+use kv;
+use log::{Level, Record};
+use palette;
+use record::Entry;
+use run;
+use sink;
+use std::fmt;
+use style::Style;
+use test;
+use timing;
+use translation::{self, Message};
+
+/// Report the final outcome of the program, displaying a footer with the total time it ran for.
+///
+/// A successful result is displayed as `finished in 3.2s` in green, and an error as `failed after 3.2s: <error>` in
+/// red. The footer is displayed regardless of the verbosity and filters, but follows the output settings consistently
+/// with log records:
+///
+/// - In [quiet mode](::set_quiet), a successful footer is not displayed, and a failed one only if errors are shown at
+///   the [quiet level](::set_quiet_level).
+/// - In [machine mode](::set_machine_mode) and the [JSON format](::Format::Json), the footer is written in that format.
+/// - The footer is written to all [sinks](::add_sink) as an info or error record.
+///
+/// Any records that are still being written are flushed first, along with output held back for the
+/// [pager](::set_pager), and all output is flushed afterwards. If any records were dropped by
+/// [sampling](::set_target_sampling), the [burst limit](::set_burst_limit) or full
+/// [sink queues](::SinkHandle::set_queue_capacity), a single warning summarizing how many is written before the footer,
+/// so that missing diagnostics are noticed. Records discarded by [code](::suppress_code) are summarized the same way.
+/// The result is returned unchanged, so this can wrap the return value of
+/// `main`:
+///
+/// ```no_run
+/// fn run() -> Result<(), std::io::Error> {
+///     Ok(())
+/// }
+///
+/// fn main() -> Result<(), std::io::Error> {
+///     clogger::init();
+///     clogger::finish(run())
+/// }
+/// ```
+///
+/// If [run IDs](::set_run_id) are enabled, the ID is included in the footer, such as `finished in 3.2s (run 4f9a2c1e)`.
+/// A failed result counts towards the [exit code](::exit_code) as an error. In the [test format](::Format::Test), the
+/// duration is displayed as `[duration]` and the run ID as `[run]`.
+pub fn finish<T, E: fmt::Display>(result: Result<T, E>) -> Result<T, E> {
+    exit::shut_down();
+
+    let format = super::output_format();
+    let elapsed = if format == Format::Test {
+        "[duration]".to_owned()
+    } else {
+        kv::humanize_seconds(timing::elapsed().as_secs_f64())
+    };
+
+    let run = match run::run_id() {
+        Some(_) if format == Format::Test => " (run [run])".to_owned(),
+        Some(id) => format!(" (run {})", id),
+        None => String::new(),
+    };
+
+    let translation = translation::current();
+    let (level, color, message) = match result {
+        Ok(_) => (
+            Level::Info,
+            palette::palette().positive(),
+            format!(
+                "{}{}",
+                translation.format(Message::Finished, &[("elapsed", &elapsed)]),
+                run
+            ),
+        ),
+        Err(ref e) => (
+            Level::Error,
+            palette::palette().negative(),
+            format!(
+                "{}{}: {}",
+                translation.format(Message::Failed, &[("elapsed", &elapsed)]),
+                run,
+                e
+            ),
+        ),
+    };
+    drop(translation);
+
+    let entry = Entry::new(
+        &Record::builder()
+            .level(level)
+            .target(super::SELF_TARGET)
+            .args(format_args!("{}", message))
+            .build(),
+        message.clone(),
+        clock::now(),
+        Vec::new(),
+    );
+
+    if !super::quiet() || level <= super::quiet_level() {
+        let stream = Stream::Stderr;
+
+        if format == Format::Machine {
+            stream.write_line(&format::machine(level, super::SELF_TARGET, &message));
+        } else if format.is_json() {
+            let options = super::options(&entry, format);
+            stream.write_line(&format::line(&entry, &options, Style::new()));
+        } else if super::colored(stream, format) {
+            stream.write_line(&Style::new().fg(color).bold().paint(&message).to_string());
+        } else {
+            stream.write_line(&message);
+        }
+    }
+
+    exit::observe(level);
+    test::record(&entry);
+    let options = super::options(&entry, format);
+    sink::dispatch(
+        &entry,
+        &options,
+        &format::line(&entry, &options, Style::new()),
+    );
+
+    log::logger().flush();
+    result
+}
@@ -0,0 +1,628 @@
+//! Line formats.
+
+use context;
+use host;
+#[cfg(feature = "json")]
+use json;
+use log::Level;
+use record::Entry;
+use run;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard};
+#[cfg(feature = "color")]
+use style;
+use style::Style;
+use target;
+#[cfg(feature = "timestamps")]
+use timestamp::{self, Precision, TimestampFormat, Timezone};
+use translation;
+use width::{self, Align};
+
+/// The overall format of output lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text. This is the default.
+    Text,
+
+    /// The stable machine-readable format. See [`set_machine_mode`](::set_machine_mode) for details.
+    Machine,
+
+    /// Human-readable text followed by all key-value pairs of the record, the global [context](::set_context) and any
+    /// [host metadata](::set_host_fields) in a form that is easy to parse, such as with `grep`.
+    ///
+    /// Each pair is written as `key=value`. Values that are empty or contain whitespace, quotes or `=` are quoted, with
+    /// quotes, backslashes and line breaks inside escaped. Line breaks in the message are escaped as well, so that every
+    /// record occupies exactly one line. The first pair is always `schema`, the [schema version](::SCHEMA_VERSION).
+    KeyValue,
+
+    /// One JSON object per line, for ingestion by log aggregation systems.
+    ///
+    /// Each object has the following members, in this order:
+    ///
+    /// - `schema`: The [schema version](::SCHEMA_VERSION), as a number.
+    /// - `ts`: The time the record was logged, in RFC 3339 format in UTC with nanosecond precision, regardless of the
+    ///   [timestamp settings](::set_timestamp_precision) of the console.
+    /// - `level`: One of `error`, `warn`, `info`, `debug` or `trace`.
+    /// - `target`: The target of the record.
+    /// - `msg`: The message.
+    /// - `fields`: An object with all key-value pairs of the record, the global [context](::set_context) and any
+    ///   [host metadata](::set_host_fields) as strings. Omitted if there are none.
+    /// - `causes`: An array of the messages of the chain of errors that caused the record. Omitted if there are none.
+    ///
+    /// Color, banners and trace timing prefixes are never used. Requires the `json` feature.
+    #[cfg(feature = "json")]
+    Json,
+
+    /// Human-readable text with all volatile parts replaced by stable placeholders, for snapshot testing the output
+    /// of a program.
+    ///
+    /// Timestamps are written as `[timestamp]`, trace timing prefixes as `[elapsed]`, thread names as `[thread]`,
+    /// and the values of key-value pairs displayed as [durations](::KvFormat::Duration) as `[duration]`. Color is
+    /// never used.
+    Test,
+}
+
+impl Format {
+    /// Check if this is the [JSON format](Format::Json), which only exists with the `json` feature.
+    pub(crate) fn is_json(self) -> bool {
+        #[cfg(feature = "json")]
+        {
+            self == Format::Json
+        }
+        #[cfg(not(feature = "json"))]
+        {
+            false
+        }
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Format::Machine,
+            2 => Format::Test,
+            3 => Format::KeyValue,
+            #[cfg(feature = "json")]
+            4 => Format::Json,
+            _ => Format::Text,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Format::Text => 0,
+            Format::Machine => 1,
+            Format::Test => 2,
+            Format::KeyValue => 3,
+            #[cfg(feature = "json")]
+            Format::Json => 4,
+        }
+    }
+}
+
+/// Settings for formatting a single record, captured once so that every output formats it consistently.
+#[derive(Clone)]
+pub(crate) struct Options {
+    pub(crate) format: Format,
+    pub(crate) metadata: bool,
+    #[cfg(feature = "timestamps")]
+    pub(crate) timestamp: Option<(TimestampFormat, Precision, Timezone)>,
+    /// The settings for the timestamp of the JSON format, which is included with full precision regardless of the
+    /// timestamp settings, unless a sink overrides or turns it off.
+    #[cfg(all(feature = "json", feature = "timestamps"))]
+    pub(crate) json_timestamp: Option<(Precision, Timezone)>,
+    pub(crate) timing: Option<(u64, u64)>,
+}
+
+/// The custom line format, if one has been set.
+static TEMPLATE: RwLock<Option<Template>> = RwLock::new(None);
+
+pub(crate) fn template() -> RwLockReadGuard<'static, Option<Template>> {
+    TEMPLATE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn set_template(template: Option<Template>) {
+    *TEMPLATE.write().unwrap_or_else(|e| e.into_inner()) = template;
+}
+
+/// An error returned when a [line format](::set_line_format) contains placeholders that are not recognized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineFormatError {
+    unknown: Vec<String>,
+}
+
+impl LineFormatError {
+    /// Get the unknown placeholders, including their braces, in the order they appear in the format.
+    pub fn unknown_placeholders(&self) -> &[String] {
+        &self.unknown
+    }
+}
+
+impl fmt::Display for LineFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown placeholder{} in line format: {}",
+            if self.unknown.len() == 1 { "" } else { "s" },
+            self.unknown.join(", ")
+        )
+    }
+}
+
+impl Error for LineFormatError {}
+
+/// A custom line format, parsed into a list of segments.
+pub(crate) struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Literal(String),
+    Field {
+        field: Field,
+        align: Align,
+        width: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Timestamp,
+    Level,
+    Target,
+    Thread,
+    Sequence,
+    Message,
+    Fields,
+    Code,
+}
+
+impl Template {
+    /// Parse a format string.
+    ///
+    /// Placeholders are written as `{name}` or `{name:<align><width>}`, and literal braces as `{{` and `}}`. A brace
+    /// that does not start or end a placeholder is kept as literal text.
+    pub(crate) fn parse(source: &str) -> Result<Self, LineFormatError> {
+        let mut segments = Vec::new();
+        let mut unknown = Vec::new();
+        let mut literal = String::new();
+        let mut rest = source;
+
+        while let Some(index) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..index]);
+            rest = &rest[index..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+
+            let end = rest.find('}').filter(|_| rest.starts_with('{'));
+
+            match end.map(|end| (Segment::parse_field(&rest[1..end]), end)) {
+                Some((Some(segment), end)) => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(literal.split_off(0)));
+                    }
+                    segments.push(segment);
+                    rest = &rest[end + 1..];
+                }
+                Some((None, end)) => {
+                    unknown.push(rest[..end + 1].to_owned());
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    literal.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        if !unknown.is_empty() {
+            return Err(LineFormatError { unknown });
+        }
+
+        Ok(Template {
+            source: source.to_owned(),
+            segments,
+        })
+    }
+
+    /// Get the format string the template was parsed from.
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Check if the template includes the timestamp.
+    #[cfg(feature = "timestamps")]
+    pub(crate) fn shows_timestamp(&self) -> bool {
+        self.segments.iter().any(|segment| match *segment {
+            Segment::Field { field, .. } => field == Field::Timestamp,
+            Segment::Literal(_) => false,
+        })
+    }
+
+    #[cfg_attr(not(feature = "timestamps"), allow(unused_variables))]
+    fn render(&self, line: &mut String, entry: &Entry, options: &Options, style: Style) {
+        for segment in &self.segments {
+            let (field, align, width) = match *segment {
+                Segment::Literal(ref literal) => {
+                    line.push_str(literal);
+                    continue;
+                }
+                Segment::Field {
+                    field,
+                    align,
+                    width,
+                } => (field, align, width),
+            };
+
+            let value = match field {
+                #[cfg(feature = "timestamps")]
+                Field::Timestamp => options
+                    .timestamp
+                    .map(|(format, precision, timezone)| {
+                        timestamp(entry, options, format, precision, timezone)
+                    })
+                    .unwrap_or_default(),
+                #[cfg(not(feature = "timestamps"))]
+                Field::Timestamp => String::new(),
+                Field::Level => translation::current().level_name(entry.level()).to_owned(),
+                Field::Target => target::display(entry.target()),
+                Field::Thread if options.format == Format::Test => "[thread]".to_owned(),
+                Field::Thread => entry.thread().unwrap_or("<unnamed>").to_owned(),
+                Field::Sequence => entry.sequence().to_string(),
+                Field::Message => message(entry, options, style).into_owned(),
+                Field::Code => entry.code().unwrap_or_default().to_owned(),
+                Field::Fields => {
+                    let mut fields = String::new();
+                    push_fields(&mut fields, entry, options);
+                    fields.trim_start().to_owned()
+                }
+            };
+
+            // Pad before styling so that escape sequences do not count towards the width.
+            let padded = width::pad(&value, align, width);
+
+            if field == Field::Level {
+                line.push_str(&style.paint(padded).to_string());
+            } else {
+                line.push_str(&padded);
+            }
+        }
+    }
+}
+
+impl Segment {
+    /// Parse the contents of a placeholder, without the surrounding braces.
+    fn parse_field(placeholder: &str) -> Option<Self> {
+        let (name, spec) = match placeholder.find(':') {
+            Some(index) => (&placeholder[..index], &placeholder[index + 1..]),
+            None => (placeholder, ""),
+        };
+
+        let field = match name {
+            "ts" => Field::Timestamp,
+            "level" => Field::Level,
+            "target" => Field::Target,
+            "thread" => Field::Thread,
+            "seq" => Field::Sequence,
+            "msg" => Field::Message,
+            "kv" => Field::Fields,
+            "code" => Field::Code,
+            _ => return None,
+        };
+
+        let (align, width) = match spec.chars().next() {
+            Some('<') => (Align::Left, &spec[1..]),
+            Some('>') => (Align::Right, &spec[1..]),
+            Some('^') => (Align::Center, &spec[1..]),
+            _ => (Align::Left, spec),
+        };
+
+        let width = if width.is_empty() {
+            0
+        } else {
+            width.parse().ok()?
+        };
+
+        Some(Segment::Field {
+            field,
+            align,
+            width,
+        })
+    }
+}
+
+/// Render a record as a single line, using the given style for the level name.
+pub(crate) fn line(entry: &Entry, options: &Options, style: Style) -> String {
+    if options.format == Format::Machine {
+        return machine(entry.level(), entry.target(), entry.message());
+    }
+
+    #[cfg(feature = "json")]
+    {
+        if options.format == Format::Json {
+            return json::line(entry, options);
+        }
+    }
+
+    let mut line = String::new();
+
+    if let Some(ref template) = *template() {
+        push_timing(&mut line, options);
+        template.render(&mut line, entry, options, style);
+        return line;
+    }
+
+    #[cfg(feature = "timestamps")]
+    if let Some((format, precision, timezone)) = options.timestamp {
+        line.push_str(&timestamp(entry, options, format, precision, timezone));
+        line.push(' ');
+    }
+
+    push_timing(&mut line, options);
+
+    line.push_str(&format!(
+        "{}: {}",
+        style.paint(level_label(entry)),
+        message(entry, options, style)
+    ));
+    push_fields(&mut line, entry, options);
+    line
+}
+
+/// Get the translated name of the level of a record, followed by its code if it has one, such as `error[E0042]`.
+pub(crate) fn level_label(entry: &Entry) -> String {
+    let translation = translation::current();
+    let name = translation.level_name(entry.level());
+
+    match entry.code() {
+        Some(code) => format!("{}[{}]", name, code),
+        None => name.to_owned(),
+    }
+}
+
+/// Format the timestamp of a record, or a placeholder in the test format.
+#[cfg(feature = "timestamps")]
+fn timestamp(
+    entry: &Entry,
+    options: &Options,
+    format: TimestampFormat,
+    precision: Precision,
+    timezone: Timezone,
+) -> String {
+    if options.format == Format::Test {
+        return "[timestamp]".to_owned();
+    }
+
+    timestamp::format(entry.time(), format, precision, timezone)
+}
+
+/// Append the trace timing prefix of a record to a line, if it has one.
+fn push_timing(line: &mut String, options: &Options) {
+    if let Some((elapsed, delta)) = options.timing {
+        if options.format == Format::Test {
+            line.push_str("[elapsed] ");
+        } else {
+            line.push_str(&format!("[{:>12}ns +{:>10}ns] ", elapsed, delta));
+        }
+    }
+}
+
+/// Append the key-value pairs of a record to a line, each preceded by a space.
+///
+/// In the key-value format, the global context and host metadata are appended as well, and values are quoted where
+/// necessary.
+fn push_fields(line: &mut String, entry: &Entry, options: &Options) {
+    if options.format != Format::KeyValue {
+        for (key, value) in entry.fields() {
+            line.push(' ');
+            line.push_str(key);
+            line.push('=');
+            line.push_str(value);
+        }
+        return;
+    }
+
+    line.push_str(" schema=");
+    line.push_str(&super::SCHEMA_VERSION.to_string());
+
+    if let Some(code) = entry.code() {
+        line.push_str(" code=");
+        push_value(line, code);
+    }
+
+    let context = context::read();
+
+    let pairs = entry
+        .fields()
+        .iter()
+        .chain(context.iter())
+        .chain(if options.metadata { run::fields() } else { &[] })
+        .chain(if options.metadata {
+            host::fields()
+        } else {
+            &[]
+        });
+
+    for (key, value) in pairs {
+        line.push(' ');
+        line.extend(key.chars().map(|c| {
+            if c.is_whitespace() || c == '=' || c == '"' {
+                '_'
+            } else {
+                c
+            }
+        }));
+        line.push('=');
+        push_value(line, value);
+    }
+}
+
+/// Append the value of a key-value pair to a line, quoting it if necessary.
+fn push_value(line: &mut String, value: &str) {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=');
+
+    if !needs_quotes {
+        line.push_str(value);
+        return;
+    }
+
+    line.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c => line.push(c),
+        }
+    }
+    line.push('"');
+}
+
+/// Get the message of a record, with styled values colored only if the line is colored.
+///
+/// In the key-value format, line breaks in the message are escaped.
+#[cfg_attr(not(feature = "color"), allow(unused_variables))]
+fn message<'a>(entry: &'a Entry, options: &Options, style: Style) -> Cow<'a, str> {
+    #[cfg(feature = "color")]
+    let message = if style.is_plain() {
+        Cow::Borrowed(entry.message())
+    } else {
+        style::render_markup(entry.styled_message())
+    };
+    #[cfg(not(feature = "color"))]
+    let message = Cow::Borrowed(entry.message());
+
+    if options.format == Format::KeyValue {
+        if message.contains(['\n', '\r']) {
+            return Cow::Owned(message.replace('\r', "\\r").replace('\n', "\\n"));
+        }
+    } else if entry.depth() > 0 {
+        let indent = " ".repeat(entry.depth() * 2);
+        return Cow::Owned(format!(
+            "{}{}",
+            indent,
+            message.replace('\n', &format!("\n{}", indent))
+        ));
+    }
+
+    message
+}
+
+/// Render a record in the stable machine-readable format.
+///
+/// The line consists of the level, target and message separated by tabs. Backslashes, tabs and line breaks in the
+/// message are escaped so that every record occupies exactly one line.
+pub(crate) fn machine(level: Level, target: &str, message: &str) -> String {
+    let mut line = String::with_capacity(message.len() + target.len() + 8);
+
+    line.push_str(level_name(level));
+    line.push('\t');
+    line.push_str(target);
+    line.push('\t');
+    escape(message, &mut line);
+    line
+}
+
+/// Append a string to a line, escaping backslashes, tabs and line breaks.
+pub(crate) fn escape(s: &str, line: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\\' => line.push_str("\\\\"),
+            '\t' => line.push_str("\\t"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            c => line.push(c),
+        }
+    }
+}
+
+/// Get the lowercase English name of a level, which is used in machine mode regardless of translation.
+pub(crate) fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown(source: &str) -> Vec<String> {
+        match Template::parse(source) {
+            Ok(_) => Vec::new(),
+            Err(e) => e.unknown_placeholders().to_vec(),
+        }
+    }
+
+    #[test]
+    fn parse_accepts_known_placeholders() {
+        let template = Template::parse("{ts} {level:<5} [{target:>20}] {msg} {kv}").unwrap();
+        assert_eq!(
+            template.source(),
+            "{ts} {level:<5} [{target:>20}] {msg} {kv}"
+        );
+        assert_eq!(template.segments.len(), 9);
+    }
+
+    #[test]
+    fn parse_reports_unknown_placeholders_in_order() {
+        assert_eq!(
+            unknown("{level} {lvl} {msg} {module:>8}"),
+            ["{lvl}", "{module:>8}"]
+        );
+    }
+
+    #[test]
+    fn parse_reports_invalid_widths() {
+        assert_eq!(unknown("{msg:<wide}"), ["{msg:<wide}"]);
+        assert_eq!(unknown("{level:-3}"), ["{level:-3}"]);
+    }
+
+    #[test]
+    fn parse_keeps_escaped_and_unmatched_braces() {
+        assert!(unknown("{{level}} {msg}").is_empty());
+        assert!(unknown("{msg} }").is_empty());
+        assert!(unknown("{msg} {").is_empty());
+
+        match Template::parse("{{{level}}}").unwrap().segments[..] {
+            [Segment::Literal(ref open), Segment::Field { field, .. }, Segment::Literal(ref close)] =>
+            {
+                assert_eq!(open, "{");
+                assert!(field == Field::Level);
+                assert_eq!(close, "}");
+            }
+            _ => panic!("unexpected segments"),
+        }
+    }
+
+    #[test]
+    fn error_lists_unknown_placeholders() {
+        let one = Template::parse("{lvl}").err().unwrap();
+        assert_eq!(one.to_string(), "unknown placeholder in line format: {lvl}");
+
+        let several = Template::parse("{lvl} {module}").err().unwrap();
+        assert_eq!(
+            several.to_string(),
+            "unknown placeholders in line format: {lvl}, {module}"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Forwarding of records to another logger.
+
+use log::{Log, Record};
+use record::Entry;
+use std::sync::RwLock;
+
+static LOGGER: RwLock<Option<Box<dyn Log>>> = RwLock::new(None);
+
+/// Forward written records to another logger instead of writing them to the console.
+///
+/// This allows clogger to be embedded as a layer inside a larger logging setup supplied by the host application.
+/// Records are forwarded after all of clogger's own filtering, sampling, escalation and sanitization have been applied,
+/// with the rendered message and the level, target and source location of the record. Records are still written to
+/// [sinks](::add_sink). Pass `None` to write records to the console again.
+///
+/// This function may be called at any time.
+pub fn set_forward_logger(logger: Option<Box<dyn Log>>) {
+    *LOGGER.write().unwrap_or_else(|e| e.into_inner()) = logger;
+}
+
+/// Forward an entry to the configured logger, returning false if there is none.
+pub(crate) fn forward(entry: &Entry) -> bool {
+    let logger = LOGGER.read().unwrap_or_else(|e| e.into_inner());
+    let logger = match *logger {
+        Some(ref logger) => logger,
+        None => return false,
+    };
+
+    logger.log(
+        &Record::builder()
+            .level(entry.level())
+            .target(entry.target())
+            .module_path(entry.module_path())
+            .file(entry.file())
+            .line(entry.line())
+            .args(format_args!("{}", entry.message()))
+            .build(),
+    );
+
+    true
+}
+
+/// Flush the configured logger, if there is one.
+pub(crate) fn flush() {
+    if let Some(ref logger) = *LOGGER.read().unwrap_or_else(|e| e.into_inner()) {
+        logger.flush();
+    }
+}
@@ -0,0 +1,193 @@
+//! Indented groups of related records and timed phases.
+
+use format::Format;
+use kv;
+use log::{log, Level};
+use metrics::{self, Scope};
+use status;
+use std::cell::Cell;
+use std::fmt;
+use std::thread;
+use translation::{self, Message};
+
+thread_local! {
+    /// The number of groups that are currently open on the current thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Get the number of groups that are currently open on the current thread, which records logged now are indented by.
+pub(crate) fn depth() -> usize {
+    DEPTH.with(Cell::get)
+}
+
+/// Log a heading at the info level, and indent the messages of all records logged until the returned guard is
+/// dropped.
+///
+/// Groups can be nested, and each level of nesting indents messages by two more spaces. Indentation is applied to the
+/// human-readable formats only; the machine-readable and key-value formats are not indented. Groups are tracked per
+/// thread, so records logged by other threads while a group is open are not indented by it.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # #[macro_use] extern crate log;
+/// # fn main() {
+/// let _group = group!("Building {}", "mytool");
+///
+/// // Displayed as "info:   compiling main.rs".
+/// info!("compiling main.rs");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! group {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::__group($target, format_args!($($arg)+))
+    };
+    ($($arg:tt)+) => {
+        $crate::__group(module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Log the start of a named phase of work, and its end along with how long it took when the returned guard is dropped.
+///
+/// The phase opens a [group](group!), so records logged while it runs are indented below its start line, and phases
+/// can be nested to narrate the progress of multi-phase tools:
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// # fn fetch() -> Result<(), ()> { Ok(()) }
+/// // Displayed as "info: download: started", and later as "info: download: finished in 3.2s".
+/// let download = phase!("download");
+///
+/// if fetch().is_err() {
+///     // Displayed as "error: download: failed after 3.2s".
+///     download.fail();
+/// }
+/// # }
+/// ```
+///
+/// A phase that is dropped while its thread is panicking is reported as failed as well. In the
+/// [test format](::Format::Test), the duration is displayed as `[duration]`.
+#[macro_export]
+macro_rules! phase {
+    (target: $target:expr, $name:expr) => {
+        $crate::__phase($target, $name)
+    };
+    ($name:expr) => {
+        $crate::__phase(module_path!(), $name)
+    };
+}
+
+/// A guard for a group of indented records, returned by [`group!`].
+#[must_use = "the group is closed as soon as it is dropped"]
+pub struct Group {
+    /// The timing of the group, unless it belongs to a phase, which is timed by itself.
+    scope: Option<Scope>,
+    /// The depth before the group was opened, which is restored when it is closed.
+    depth: usize,
+}
+
+impl Group {
+    fn open(scope: Option<Scope>) -> Self {
+        let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        Group { scope, depth }
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        if let Some(ref scope) = self.scope {
+            metrics::end(scope, thread::panicking());
+        }
+
+        DEPTH.with(|depth| depth.set(self.depth));
+    }
+}
+
+/// A guard for a timed phase of work, returned by [`phase!`].
+#[must_use = "the phase finishes as soon as it is dropped"]
+pub struct Phase {
+    target: String,
+    name: String,
+    scope: Scope,
+    failed: bool,
+    group: Option<Group>,
+    status: u64,
+}
+
+impl Phase {
+    /// End the phase, reporting it as failed.
+    pub fn fail(mut self) {
+        self.failed = true;
+    }
+
+    /// End the phase, reporting it as finished. This is the same as dropping it.
+    pub fn finish(self) {}
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        // Close the group first, so the end line is aligned with the start line.
+        self.group.take();
+        status::leave_phase(self.status);
+
+        let failed = self.failed || thread::panicking();
+        metrics::end(&self.scope, failed);
+
+        let elapsed = if super::output_format() == Format::Test {
+            "[duration]".to_owned()
+        } else {
+            kv::humanize_seconds(self.scope.elapsed().as_secs_f64())
+        };
+
+        let (level, message) = if failed {
+            (Level::Error, Message::Failed)
+        } else {
+            (Level::Info, Message::Finished)
+        };
+        let message = translation::current().format(message, &[("elapsed", &elapsed)]);
+        log!(target: &self.target, level, "{}: {}", self.name, message);
+    }
+}
+
+#[doc(hidden)]
+pub fn __group(target: &str, args: fmt::Arguments) -> Group {
+    log!(target: target, Level::Info, "{}", args);
+    Group::open(Some(metrics::start(&args.to_string())))
+}
+
+#[doc(hidden)]
+pub fn __phase<N: fmt::Display>(target: &str, name: N) -> Phase {
+    let name = name.to_string();
+    let started = translation::current().format(Message::Started, &[]);
+    log!(target: target, Level::Info, "{}: {}", name, started);
+    let status = status::enter_phase(&name);
+    let scope = metrics::start(&name);
+
+    Phase {
+        target: target.to_owned(),
+        name,
+        scope,
+        failed: false,
+        group: Some(Group::open(None)),
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_is_tracked_per_thread() {
+        let outer = Group::open(None);
+        let inner = Group::open(None);
+        assert_eq!(depth(), 2);
+        assert_eq!(thread::spawn(depth).join().unwrap(), 0);
+
+        drop(inner);
+        assert_eq!(depth(), 1);
+        drop(outer);
+        assert_eq!(depth(), 0);
+    }
+}
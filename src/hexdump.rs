@@ -0,0 +1,194 @@
+//! Logging of binary data as hex dumps.
+
+use burst;
+use format::Format;
+use log::{log, log_enabled, Level};
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use style::Style;
+
+/// The key of the field holding the data in structured formats.
+const KEY: &str = "data";
+
+/// The number of bytes displayed on each line of a dump.
+const WIDTH: usize = 16;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+static LIMIT: AtomicUsize = AtomicUsize::new(256);
+
+thread_local! {
+    /// The encoded data of the record being logged by [`hexdump!`] on the current thread.
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Log binary data, such as a network packet, as a hex dump along with a message.
+///
+/// In the human-readable formats, the data is displayed below the message with the offset, the bytes in hex and the
+/// printable ASCII characters of each line of 16 bytes, like `hexdump -C`:
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::Level;
+///
+/// let packet = b"\x01\x00\x00\x0cHello, world";
+///
+/// // Displayed as:
+/// // debug: received packet from 10.0.0.2 (16 bytes)
+/// // 00000000  01 00 00 0c 48 65 6c 6c  6f 2c 20 77 6f 72 6c 64  |....Hello, world|
+/// hexdump!(Level::Debug, packet, "received packet from {}", "10.0.0.2");
+/// # }
+/// ```
+///
+/// In the [JSON](::Format::Json) and [key-value](::Format::KeyValue) formats, the data is included as a base64 encoded
+/// `data` field instead, and in the [machine-readable format](::set_machine_mode), it is appended to the message in
+/// base64 after a colon.
+///
+/// Only the first [`hexdump_limit`] bytes are included, and the message notes how many bytes there were in total. The
+/// data is not formatted at all if the level is not enabled for the target, so dumps can be left in place at the debug
+/// or trace level.
+#[macro_export]
+macro_rules! hexdump {
+    (target: $target:expr, $level:expr, $data:expr, $($arg:tt)+) => {
+        $crate::__hexdump(
+            $target,
+            $level,
+            ::std::convert::AsRef::<[u8]>::as_ref(&$data),
+            format_args!($($arg)+),
+        )
+    };
+    ($level:expr, $data:expr, $($arg:tt)+) => {
+        $crate::__hexdump(
+            module_path!(),
+            $level,
+            ::std::convert::AsRef::<[u8]>::as_ref(&$data),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Get the maximum number of bytes included in a [hex dump](hexdump!).
+pub fn hexdump_limit() -> usize {
+    LIMIT.load(Ordering::SeqCst)
+}
+
+/// Set the maximum number of bytes included in a [hex dump](hexdump!). The default is 256.
+///
+/// This function may be called at any time.
+pub fn set_hexdump_limit(limit: usize) {
+    LIMIT.store(limit, Ordering::SeqCst);
+}
+
+#[doc(hidden)]
+pub fn __hexdump(target: &str, level: Level, data: &[u8], args: fmt::Arguments) {
+    struct Reset(Option<String>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    if !log_enabled!(target: target, level) {
+        return;
+    }
+
+    let shown = &data[..data.len().min(hexdump_limit())];
+    let size = if shown.len() < data.len() {
+        format!(
+            "{} bytes, first {} shown",
+            burst::group_digits(data.len() as u64),
+            burst::group_digits(shown.len() as u64)
+        )
+    } else if data.len() == 1 {
+        "1 byte".to_owned()
+    } else {
+        format!("{} bytes", burst::group_digits(data.len() as u64))
+    };
+
+    match super::output_format() {
+        format if format == Format::KeyValue || format.is_json() => {
+            let _reset = Reset(CURRENT.with(|current| current.borrow_mut().replace(base64(shown))));
+            log!(target: target, level, "{} ({})", args, size);
+        }
+        Format::Machine => log!(target: target, level, "{} ({}): {}", args, size, base64(shown)),
+        _ => log!(target: target, level, "{} ({}){}", args, size, Dump(shown)),
+    }
+}
+
+/// Add the encoded data of a record being logged by [`hexdump!`] on the current thread to its fields, if any.
+pub(crate) fn extend(fields: &mut Vec<(String, String)>) {
+    if let Some(data) = CURRENT.with(|current| current.borrow().clone()) {
+        fields.push((KEY.to_owned(), data));
+    }
+}
+
+/// Data rendered as the lines of a hex dump, each starting with a newline.
+///
+/// The dump is rendered while the message is formatted, so that its styles are encoded as markup.
+struct Dump<'a>(&'a [u8]);
+
+impl fmt::Display for Dump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let offset_style = Style::new().dimmed();
+
+        for (i, chunk) in self.0.chunks(WIDTH).enumerate() {
+            write!(
+                f,
+                "\n{}  ",
+                offset_style.paint(&format!("{:08x}", i * WIDTH))
+            )?;
+
+            for column in 0..WIDTH {
+                match chunk.get(column) {
+                    Some(byte) => write!(f, "{:02x} ", byte)?,
+                    None => f.write_str("   ")?,
+                }
+
+                // An extra space separates the two halves of the line.
+                if column == WIDTH / 2 - 1 {
+                    f.write_char(' ')?;
+                }
+            }
+
+            f.write_str(" |")?;
+            for &byte in chunk {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    f.write_char(byte as char)?;
+                } else {
+                    f.write_char('.')?;
+                }
+            }
+            f.write_char('|')?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encode data using the standard base64 alphabet, with padding.
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,273 @@
+//! A ring buffer of recent records, including records that were not displayed.
+
+use console::Stream;
+use format;
+use log::Level;
+use palette;
+use record::Entry;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use style::Style;
+use timestamp::{self, Precision, TimestampFormat, Timezone};
+use translation::{self, Message};
+
+/// Recent entries, along with whether each is replayed, which is only the case for entries that were hidden by their
+/// level.
+static ENTRIES: Mutex<VecDeque<(Entry, bool)>> = Mutex::new(VecDeque::new());
+static CAPACITY: AtomicUsize = AtomicUsize::new(10_000);
+static DUMP_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+static DUMPED: AtomicBool = AtomicBool::new(false);
+static REPLAY: AtomicBool = AtomicBool::new(false);
+static REPLAYED: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Check if records should be recorded in the history.
+pub(crate) fn recording() -> bool {
+    CAPACITY.load(Ordering::SeqCst) > 0
+        && (REPLAY.load(Ordering::SeqCst)
+            || DUMP_DIR.read().unwrap_or_else(|e| e.into_inner()).is_some())
+}
+
+/// Add an entry to the history, discarding the oldest entry if it is full.
+pub(crate) fn record(entry: &Entry, hidden: bool) {
+    let capacity = CAPACITY.load(Ordering::SeqCst);
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+
+    while entries.len() >= capacity.max(1) {
+        entries.pop_front();
+    }
+
+    entries.push_back((entry.clone(), hidden));
+}
+
+/// Get the maximum number of records kept in the history.
+pub fn history_capacity() -> usize {
+    CAPACITY.load(Ordering::SeqCst)
+}
+
+/// Set the maximum number of records kept in the history. The default is 10,000.
+///
+/// The history contains the most recent records at every level, including records that were not displayed because of
+/// the current verbosity or filters. It is only kept while a feature that uses it is enabled, such as
+/// [`set_dump_on_error`] or [`set_replay_on_error`].
+///
+/// This function may be called at any time.
+pub fn set_history_capacity(capacity: usize) {
+    CAPACITY.store(capacity, Ordering::SeqCst);
+
+    let mut entries = ENTRIES.lock().unwrap_or_else(|e| e.into_inner());
+    while entries.len() > capacity {
+        entries.pop_front();
+    }
+    drop(entries);
+
+    super::update_max_level();
+}
+
+/// Get the directory that the history is written to when an error occurs, if enabled.
+pub fn dump_on_error() -> Option<PathBuf> {
+    DUMP_DIR.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Write the full history of records to a file in the given directory when the first error occurs, or `None` to
+/// disable.
+///
+/// When an error record is logged or the program panics, every record in the [history](set_history_capacity) is
+/// written to a new file in the directory, including debug and trace records that were not displayed, and the path of
+/// the file is reported in a warning. This gives users a complete log to attach to bug reports without having to run
+/// the program again with a higher verbosity. The history is written at most once per run.
+///
+/// Since every record is kept regardless of verbosity, enabling this has a cost even for records that are not
+/// displayed.
+///
+/// ```no_run
+/// clogger::set_dump_on_error(Some(std::env::temp_dir()));
+/// ```
+///
+/// This function may be called at any time.
+pub fn set_dump_on_error(dir: Option<PathBuf>) {
+    let enabled = dir.is_some();
+    *DUMP_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+
+    if enabled {
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+
+            panic::set_hook(Box::new(move |info| {
+                dump();
+                previous(info);
+            }));
+        });
+    } else if !recording() {
+        ENTRIES.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    super::update_max_level();
+}
+
+/// Check if hidden records are replayed to the console when the first error occurs.
+pub fn replay_on_error() -> bool {
+    REPLAY.load(Ordering::SeqCst)
+}
+
+/// Replay the records in the [history](set_history_capacity) that were not displayed to the console when the first
+/// error occurs. Disabled by default.
+///
+/// This gives users the debug and trace records leading up to a failure without having to run the program at a
+/// higher verbosity all the time. The replayed records are written to stderr just before the first error record,
+/// after a warning announcing how many there are. Records are replayed at most once per run.
+///
+/// Only records hidden by the verbosity or the level of their target are replayed. Records dropped by
+/// [sampling](::set_target_sampling) or the [burst limit](::set_burst_limit) stay dropped, and are only counted in the
+/// summary written when the program [finishes](::finish).
+///
+/// Since every record is kept regardless of verbosity, enabling this has a cost even for records that are not
+/// displayed.
+///
+/// This function may be called at any time.
+pub fn set_replay_on_error(enabled: bool) {
+    REPLAY.store(enabled, Ordering::SeqCst);
+
+    if !recording() {
+        ENTRIES.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    super::update_max_level();
+}
+
+/// Write the records in the history that were not displayed to the console, if enabled and they have not been
+/// replayed already.
+pub(crate) fn replay() {
+    if !replay_on_error() || REPLAYED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let entries: Vec<Entry> = ENTRIES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|(_, hidden)| *hidden)
+        .map(|(entry, _)| entry.clone())
+        .collect();
+
+    if entries.is_empty() {
+        return;
+    }
+
+    let message = translation::current().format(
+        if entries.len() == 1 {
+            Message::ReplayingOne
+        } else {
+            Message::Replaying
+        },
+        &[("count", &entries.len())],
+    );
+    super::self_log(Level::Warn, format_args!("{}", message));
+
+    let format = super::output_format();
+    let colored = super::colored(Stream::Stderr, format);
+
+    for mut entry in entries {
+        entry.sanitize(super::sanitize());
+
+        let style = if colored {
+            Style::new().fg(palette::palette().level(entry.level()))
+        } else {
+            Style::new()
+        };
+
+        Stream::Stderr.write_line(&format::line(
+            &entry,
+            &super::options(&entry, format),
+            style,
+        ));
+    }
+}
+
+/// Write the history to a file, if enabled and it has not been written already.
+pub(crate) fn dump() {
+    let dir = match dump_on_error() {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    if DUMPED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let entries: Vec<Entry> = ENTRIES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(entry, _)| entry.clone())
+        .collect();
+
+    match write(&dir, &entries) {
+        Ok(path) => super::self_log(
+            Level::Warn,
+            format_args!("full log written to {}", path.display()),
+        ),
+        Err(e) => super::self_log(
+            Level::Warn,
+            format_args!("failed to write full log to {}: {}", dir.display(), e),
+        ),
+    }
+}
+
+fn write(dir: &Path, entries: &[Entry]) -> io::Result<PathBuf> {
+    let program = env::args_os()
+        .next()
+        .and_then(|arg0| {
+            Path::new(&arg0)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "log".to_owned());
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let path = dir.join(format!("{}-{}-{}.log", program, process::id(), secs));
+    let mut file = io::BufWriter::new(
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?,
+    );
+
+    for entry in entries {
+        let mut entry = entry.clone();
+        entry.sanitize(super::sanitize());
+        writeln!(file, "{}", line(&entry))?;
+    }
+
+    file.flush()?;
+    Ok(path)
+}
+
+/// Render an entry for the history file, including the time and target regardless of the current configuration.
+fn line(entry: &Entry) -> String {
+    let time = timestamp::format(
+        entry.time(),
+        TimestampFormat::Rfc3339,
+        Precision::Micros,
+        Timezone::Utc,
+    );
+
+    format!(
+        "{} {:<5} {}: {}",
+        time,
+        format::level_name(entry.level()),
+        entry.target(),
+        entry.message()
+    )
+}
@@ -0,0 +1,69 @@
+//! Metadata identifying the host and process that records come from.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FIELDS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Check if host metadata fields are included in structured output.
+pub fn host_fields() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Include the hostname, process ID and binary name of the program as the `host`, `pid` and `bin` fields of every
+/// record in the [key-value format](::Format::KeyValue).
+///
+/// This makes it possible to attribute records when the output of many runs on many machines is aggregated. The values
+/// are determined once, when the logger is initialized. The hostname is omitted if it cannot be determined.
+///
+/// This function may be called at any time.
+pub fn set_host_fields(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the host metadata fields, if they are enabled.
+pub(crate) fn fields() -> &'static [(String, String)] {
+    if host_fields() {
+        init()
+    } else {
+        &[]
+    }
+}
+
+/// Determine the host metadata fields, if they have not been determined yet.
+pub(crate) fn init() -> &'static [(String, String)] {
+    FIELDS.get_or_init(|| {
+        let mut fields = Vec::new();
+
+        if let Some(host) = hostname() {
+            fields.push(("host".to_owned(), host));
+        }
+
+        fields.push(("pid".to_owned(), process::id().to_string()));
+
+        if let Some(bin) = env::args_os().next().and_then(|arg| {
+            Path::new(&arg)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        }) {
+            fields.push(("bin".to_owned(), bin));
+        }
+
+        fields
+    })
+}
+
+fn hostname() -> Option<String> {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty())
+}
@@ -0,0 +1,299 @@
+//! JSON rendering of records.
+//!
+//! Records written to the console and sinks are rendered into a buffer that is reused by each thread, escaping strings
+//! directly into it, so that no memory is allocated per record once the buffer has grown to fit the longest line. Lines
+//! that are kept, such as those rendered with the timestamp settings of a sink, are rendered directly into their own
+//! string instead.
+
+use clock;
+use context;
+use format::Options;
+use host;
+use record::Entry;
+use run;
+use std::cell::RefCell;
+use std::fmt::Write;
+use timestamp::{self, Precision, TimestampFormat, Timezone};
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// The settings for the `ts` member, which is always written with full precision in UTC, regardless of how timestamps
+/// are displayed on the console.
+pub(crate) const TIMESTAMP: (Precision, Timezone) = (Precision::Nanos, Timezone::Utc);
+
+thread_local! {
+    static BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Render a record as a single line of JSON, and pass it to the given function along with a trailing newline.
+pub(crate) fn with_line<T>(entry: &Entry, options: &Options, f: impl FnOnce(&str) -> T) -> T {
+    BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            push_object(&mut buffer, entry, options);
+            buffer.push('\n');
+            f(&buffer)
+        }
+        // Already rendering on this thread, such as when a sink logs while writing a record.
+        Err(_) => {
+            let mut buffer = String::new();
+            push_object(&mut buffer, entry, options);
+            buffer.push('\n');
+            f(&buffer)
+        }
+    })
+}
+
+/// Render a record as a single line of JSON into a new string, without a trailing newline.
+pub(crate) fn line(entry: &Entry, options: &Options) -> String {
+    let mut line = String::new();
+    push_object(&mut line, entry, options);
+    line
+}
+
+fn push_object(buffer: &mut String, entry: &Entry, options: &Options) {
+    let _ = write!(buffer, "{{\"schema\":{}", super::SCHEMA_VERSION);
+    #[cfg(feature = "timestamps")]
+    let timestamp = options.json_timestamp;
+    #[cfg(not(feature = "timestamps"))]
+    let timestamp = Some(TIMESTAMP);
+
+    if let Some((precision, timezone)) = timestamp {
+        buffer.push_str(",\"ts\":\"");
+        timestamp::push(
+            buffer,
+            entry.time(),
+            TimestampFormat::Rfc3339,
+            precision,
+            timezone,
+        );
+        buffer.push('"');
+    }
+
+    buffer.push_str(",\"level\":\"");
+    buffer.push_str(super::format::level_name(entry.level()));
+    if let Some(code) = entry.code() {
+        buffer.push_str("\",\"code\":");
+        push_string(buffer, code);
+        buffer.push_str(",\"target\":");
+    } else {
+        buffer.push_str("\",\"target\":");
+    }
+    push_string(buffer, entry.target());
+    buffer.push_str(",\"msg\":");
+    push_string(buffer, entry.message());
+
+    let context = context::read();
+    let (run, host) = if options.metadata {
+        (run::fields(), host::fields())
+    } else {
+        (&[][..], &[][..])
+    };
+    let mut fields = entry
+        .fields()
+        .iter()
+        .chain(context.iter())
+        .chain(run)
+        .chain(host)
+        .peekable();
+
+    if fields.peek().is_some() {
+        buffer.push_str(",\"fields\":{");
+        for (i, (key, value)) in fields.enumerate() {
+            if i > 0 {
+                buffer.push(',');
+            }
+            push_string(buffer, key);
+            buffer.push(':');
+            push_string(buffer, value);
+        }
+        buffer.push('}');
+    }
+
+    if !entry.causes().is_empty() {
+        buffer.push_str(",\"causes\":[");
+        for (i, cause) in entry.causes().iter().enumerate() {
+            if i > 0 {
+                buffer.push(',');
+            }
+            push_string(buffer, cause);
+        }
+        buffer.push(']');
+    }
+
+    buffer.push('}');
+}
+
+/// Render a `progress` event as a single line of JSON, without a trailing newline.
+pub(crate) fn progress(name: &str, current: u64, total: u64, percent: u64) -> String {
+    let mut buffer = String::new();
+    let _ = write!(buffer, "{{\"schema\":{},\"ts\":\"", super::SCHEMA_VERSION);
+    let (precision, timezone) = TIMESTAMP;
+    timestamp::push(
+        &mut buffer,
+        clock::now(),
+        TimestampFormat::Rfc3339,
+        precision,
+        timezone,
+    );
+
+    buffer.push_str("\",\"event\":\"progress\",\"name\":");
+    push_string(&mut buffer, name);
+    let _ = write!(
+        buffer,
+        ",\"current\":{},\"total\":{},\"percent\":{}}}",
+        current, total, percent
+    );
+    buffer
+}
+
+/// Append a string to a buffer as a quoted JSON string.
+///
+/// Runs of characters that do not need escaping are copied at once.
+fn push_string(buffer: &mut String, s: &str) {
+    buffer.push('"');
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escape = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+
+        buffer.push_str(&s[start..i]);
+        start = i + 1;
+
+        if escape.is_empty() {
+            buffer.push_str("\\u00");
+            buffer.push(HEX[(byte >> 4) as usize] as char);
+            buffer.push(HEX[(byte & 0xf) as usize] as char);
+        } else {
+            buffer.push_str(escape);
+        }
+    }
+
+    buffer.push_str(&s[start..]);
+    buffer.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::Format;
+    use log::{Level, Record};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn string(s: &str) -> String {
+        let mut buffer = String::new();
+        push_string(&mut buffer, s);
+        buffer
+    }
+
+    fn entry(message: &str) -> Entry {
+        Entry::new(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("app::net")
+                .args(format_args!("{}", message))
+                .build(),
+            message.to_owned(),
+            UNIX_EPOCH + Duration::new(1_700_000_000, 5),
+            vec!["connection reset".to_owned()],
+        )
+    }
+
+    /// Render a record the obvious way, formatting and allocating each member separately.
+    fn naive(entry: &Entry) -> String {
+        fn escape(s: &str) -> String {
+            let mut escaped = String::new();
+            for c in s.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    '\t' => escaped.push_str("\\t"),
+                    c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => escaped.push(c),
+                }
+            }
+            format!("\"{}\"", escaped)
+        }
+
+        let (precision, timezone) = TIMESTAMP;
+        let causes: Vec<String> = entry.causes().iter().map(|c| escape(c)).collect();
+        let members = [
+            format!("\"schema\":{}", super::super::SCHEMA_VERSION),
+            format!(
+                "\"ts\":\"{}\"",
+                timestamp::format(entry.time(), TimestampFormat::Rfc3339, precision, timezone)
+            ),
+            format!(
+                "\"level\":\"{}\"",
+                super::super::format::level_name(entry.level())
+            ),
+            format!("\"target\":{}", escape(entry.target())),
+            format!("\"msg\":{}", escape(entry.message())),
+            format!("\"causes\":[{}]", causes.join(",")),
+        ];
+        format!("{{{}}}\n", members.join(","))
+    }
+
+    #[test]
+    fn strings_are_quoted() {
+        assert_eq!(string(""), "\"\"");
+        assert_eq!(string("plain text"), "\"plain text\"");
+    }
+
+    #[test]
+    fn quotes_and_backslashes_are_escaped() {
+        assert_eq!(string("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(string("C:\\temp\\"), "\"C:\\\\temp\\\\\"");
+    }
+
+    #[test]
+    fn control_characters_are_escaped() {
+        assert_eq!(string("a\nb\r\tc"), "\"a\\nb\\r\\tc\"");
+        assert_eq!(
+            string("\x00\x07\x1b[0m\x1f"),
+            "\"\\u0000\\u0007\\u001b[0m\\u001f\""
+        );
+        assert_eq!(string("\x7f"), "\"\x7f\"");
+    }
+
+    #[test]
+    fn unicode_is_kept() {
+        assert_eq!(string("café ✓ 🦀"), "\"café ✓ 🦀\"");
+        assert_eq!(string("✓\n🦀"), "\"✓\\n🦀\"");
+    }
+
+    #[test]
+    fn records_match_the_naive_rendering() {
+        for message in [
+            "fetched 3 crates",
+            "quoted \"path\\to\"\nnext",
+            "\x1b[31mred ✓",
+        ] {
+            let entry = entry(message);
+            let options = super::super::options(&entry, Format::Json);
+            with_line(&entry, &options, |line| assert_eq!(line, naive(&entry)));
+            assert_eq!(line(&entry, &options) + "\n", naive(&entry));
+        }
+    }
+
+    #[test]
+    fn timestamps_have_nanosecond_precision() {
+        let entry = entry("fetched 3 crates");
+        let options = super::super::options(&entry, Format::Json);
+        with_line(&entry, &options, |line| {
+            assert!(line.contains("\"ts\":\"2023-11-14T22:13:20.000000005Z\""));
+        });
+    }
+}
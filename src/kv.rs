@@ -0,0 +1,152 @@
+//! Rendering of key-value pairs attached to records.
+
+#[cfg(feature = "kv")]
+use format::Format;
+use log::Record;
+
+/// Render the key-value pairs attached to a record, except for errors, which are displayed as causes instead.
+#[cfg(feature = "kv")]
+pub(crate) fn fields(record: &Record) -> Vec<(String, String)> {
+    use log::kv::{self, Key, Value, VisitSource};
+
+    struct Visitor<'a> {
+        formatters: &'a [(String, Formatter)],
+        fields: Vec<(String, String)>,
+    }
+
+    impl<'a, 'kvs> VisitSource<'kvs> for Visitor<'a> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+            if value.to_borrowed_error().is_some() {
+                return Ok(());
+            }
+
+            let rendered = self
+                .formatters
+                .iter()
+                .find(|(k, _)| k == key.as_str())
+                .map(|(_, formatter)| formatter(&value))
+                .unwrap_or_else(|| value.to_string());
+
+            self.fields.push((key.as_str().to_owned(), rendered));
+            Ok(())
+        }
+    }
+
+    let formatters = FORMATTERS.read().unwrap_or_else(|e| e.into_inner());
+    let mut visitor = Visitor {
+        formatters: &formatters,
+        fields: Vec::new(),
+    };
+    let _ = record.key_values().visit(&mut visitor);
+    visitor.fields
+}
+
+#[cfg(not(feature = "kv"))]
+pub(crate) fn fields(_: &Record) -> Vec<(String, String)> {
+    Vec::new()
+}
+
+#[cfg(feature = "kv")]
+type Formatter = Box<dyn Fn(&log::kv::Value) -> String + Send + Sync>;
+
+#[cfg(feature = "kv")]
+static FORMATTERS: std::sync::RwLock<Vec<(String, Formatter)>> = std::sync::RwLock::new(Vec::new());
+
+/// A built-in format for the value of a key-value pair.
+#[cfg(feature = "kv")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvFormat {
+    /// An integer number of bytes, displayed using binary units such as `1.4MiB`.
+    Bytes,
+
+    /// A number of seconds, which may be fractional, displayed using the largest suitable units such as `2.3s` or
+    /// `4m12s`.
+    Duration,
+}
+
+/// Display the value of key-value pairs with the given key in a built-in format.
+///
+/// Values that cannot be displayed in the format, such as strings, are displayed as-is.
+///
+/// ```
+/// # #[macro_use] extern crate log;
+/// # extern crate clogger;
+/// # fn main() {
+/// use clogger::KvFormat;
+///
+/// clogger::set_kv_format("size", KvFormat::Bytes);
+/// clogger::set_kv_format("elapsed", KvFormat::Duration);
+///
+/// // Displayed as "info: uploaded size=1.4MiB elapsed=2.3s".
+/// info!(size = 1_468_006, elapsed = 2.3; "uploaded");
+/// # }
+/// ```
+///
+/// This function may be called at any time.
+#[cfg(feature = "kv")]
+pub fn set_kv_format(key: &str, format: KvFormat) {
+    set_kv_formatter(key, move |value| match format {
+        KvFormat::Bytes => value
+            .to_u64()
+            .map_or_else(|| value.to_string(), humanize_bytes),
+        KvFormat::Duration if super::output_format() == Format::Test => "[duration]".to_owned(),
+        KvFormat::Duration => value
+            .to_f64()
+            .map_or_else(|| value.to_string(), humanize_seconds),
+    });
+}
+
+/// Display the value of key-value pairs with the given key using a custom function.
+///
+/// This replaces any format previously set for the key.
+///
+/// This function may be called at any time.
+#[cfg(feature = "kv")]
+pub fn set_kv_formatter<F>(key: &str, formatter: F)
+where
+    F: Fn(&log::kv::Value) -> String + Send + Sync + 'static,
+{
+    let mut formatters = FORMATTERS.write().unwrap_or_else(|e| e.into_inner());
+    formatters.retain(|(k, _)| k != key);
+    formatters.push((key.to_owned(), Box::new(formatter)));
+}
+
+#[cfg(feature = "kv")]
+fn humanize_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Format a number of seconds as a short, human-readable duration, such as `3.2s`.
+pub(crate) fn humanize_seconds(seconds: f64) -> String {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return seconds.to_string();
+    }
+
+    if seconds < 0.001 {
+        format!("{:.0}µs", seconds * 1_000_000.0)
+    } else if seconds < 1.0 {
+        format!("{:.1}ms", seconds * 1000.0)
+    } else if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else if seconds < 3600.0 {
+        let seconds = seconds as u64;
+        format!("{}m{}s", seconds / 60, seconds % 60)
+    } else {
+        let seconds = seconds as u64;
+        format!("{}h{}m", seconds / 3600, seconds / 60 % 60)
+    }
+}
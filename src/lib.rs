@@ -18,56 +18,641 @@
 //!     debug!("verbosity increased, this will now be displayed");
 //! }
 //! ```
-extern crate ansi_term;
+//!
+//! ## Features
+//!
+//! Optional functionality can be disabled to reduce build times and binary size. Without any features, the only
+//! dependency is `log`.
+//!
+//! - `color` (default): Colored output, including on legacy Windows consoles. The [`style`](mod@style) module can be used to
+//!   style custom output consistently.
+//! - `kv` (default): Display of key-value pairs attached to records, including error chains attached using the `:err`
+//!   capture modifier.
+//! - `regex` (default): Filtering of messages using regular expressions.
+//! - `timestamps` (default): Timestamps on each line.
+//! - `async` (default): [Asynchronous mode](set_async_mode), which writes records on a background thread.
+//! - `file` (default): The [`FileSink`] for writing log files, with rotation and retention.
+//! - `json` (default): The [JSON](Format::Json) output format.
+//! - `audit`: A sink that writes tamper-evident audit logs.
+//! - `control`: A command interface for adjusting and inspecting logging while a program is running.
+//! - `gzip`: Gzip compression of rotated log files. Enables `file`.
+//! - `max_level_off`, `max_level_error`, `max_level_warn`, `max_level_info`, `max_level_debug`, `max_level_trace`:
+//!   Compile out records more verbose than the given level, along with the cost of formatting them. These enable the
+//!   features of the same names of the `log` crate, which apply to every crate in the build. The `release_max_level_*`
+//!   features do the same in release builds only, and take precedence over the `max_level_*` features there. At most
+//!   one feature of each kind may be enabled. Setting the [verbosity](set_verbosity) higher than the compiled-in level
+//!   has no effect.
+//! - `notify`: Desktop notifications for error records.
+//! - `task`: Key-value pairs attached to asynchronous tasks, which follow the task across the threads of a runtime.
+//! - `zstd`: Zstandard compression of rotated log files. Enables `file`.
+#[cfg(feature = "color")]
+extern crate anstream;
+#[cfg(feature = "color")]
+extern crate anstyle;
+#[cfg(feature = "gzip")]
+extern crate flate2;
 extern crate log;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "audit")]
+extern crate sha2;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+mod alert;
+mod args;
+#[cfg(feature = "audit")]
+mod audit;
+mod banner;
+mod boost;
+mod bootstrap;
+mod build_info;
+mod builder;
+mod burst;
+#[cfg(unix)]
+mod capture;
+mod catalog;
+mod change;
+mod clock;
+mod console;
+mod context;
+#[cfg(feature = "control")]
+mod control;
+mod diff;
+mod drops;
+mod error;
+mod escalate;
+mod exit;
+#[cfg(feature = "file")]
+mod file;
+mod filter;
+mod finish;
+mod format;
+mod forward;
+mod group;
+mod hexdump;
+mod history;
+mod host;
+#[cfg(feature = "json")]
+mod json;
+mod kv;
+mod metrics;
+mod order;
+mod pager;
+mod palette;
+mod print;
+mod progress;
+#[cfg(feature = "async")]
+mod queue;
+mod raw;
+mod record;
+mod reentry;
+mod run;
+mod sample;
+mod sanitize;
+mod sink;
+mod socket;
+mod status;
+pub mod style;
+mod table;
+mod tag;
+mod tail;
+mod target;
+#[cfg(feature = "task")]
+mod task;
+pub mod test;
+mod timestamp;
+mod timing;
+mod translation;
+mod width;
 
-use ansi_term::Color;
+use console::Stream;
+use drops::Cause;
+use filter::{CodeRule, Filter};
+use format::Options;
 use log::*;
+use std::borrow::Cow;
+use std::fmt;
 use std::sync::atomic::*;
+use style::Style;
+
+pub use alert::{attention, set_attention};
+pub use args::parse_args;
+#[cfg(feature = "audit")]
+pub use audit::AuditSink;
+pub use boost::{boost_verbosity_for, Boost};
+#[doc(hidden)]
+pub use build_info::__log_build_info;
+pub use builder::Builder;
+pub use burst::{burst_limit, set_burst_limit};
+#[cfg(unix)]
+pub use capture::{capture_fd, FdCapture};
+#[doc(hidden)]
+pub use catalog::__coded;
+pub use catalog::{message_template, register_message};
+pub use change::{on_change, Change};
+pub use clock::{set_clock, Clock, ManualClock, SystemClock};
+#[cfg(feature = "color")]
+pub use console::ColorChoice;
+pub use context::{remove_context, set_context};
+#[cfg(all(feature = "control", unix))]
+pub use control::listen_control;
+#[cfg(feature = "control")]
+pub use control::serve_control;
+pub use diff::log_diff;
+pub use error::Error;
+#[doc(hidden)]
+pub use exit::__fatal;
+pub use exit::{exit, exit_code, set_exit_policy, worst_level};
+#[cfg(feature = "file")]
+pub use file::{log_dir, Compression, FileSink, Retention};
+pub use filter::FilterHandle;
+pub use finish::finish;
+pub use format::{Format, LineFormatError};
+pub use forward::set_forward_logger;
+#[doc(hidden)]
+pub use group::{__group, __phase};
+pub use group::{Group, Phase};
+#[doc(hidden)]
+pub use hexdump::__hexdump;
+pub use hexdump::{hexdump_limit, set_hexdump_limit};
+pub use history::{
+    dump_on_error, history_capacity, replay_on_error, set_dump_on_error, set_history_capacity,
+    set_replay_on_error,
+};
+pub use host::{host_fields, set_host_fields};
+#[cfg(feature = "kv")]
+pub use kv::{set_kv_format, set_kv_formatter, KvFormat};
+pub use metrics::{metrics, report_metrics, set_report_metrics, Metrics, Timing};
+pub use pager::{pager, set_pager};
+pub use palette::{palette, set_palette, Palette};
+#[doc(hidden)]
+pub use print::__print;
+pub use progress::{progress, Progress};
+pub use raw::write_formatted;
+pub use record::Entry;
+pub use reentry::{reentrancy, set_reentrancy, Reentrancy};
+pub use run::{run_id, set_run_id};
+pub use sample::Sampling;
+pub use sanitize::Sanitize;
+pub use sink::{
+    add_sink, set_error_handler, sink_scope, with_sink, FailurePolicy, Sink, SinkHandle, SinkScope,
+};
+pub use socket::SocketSink;
+pub use status::{set_terminal_progress, set_terminal_title, terminal_progress, terminal_title};
+pub use style::style;
+pub use table::Table;
+#[doc(hidden)]
+pub use tag::__tagged;
+pub use tail::tail;
+pub use target::{
+    set_target_max_width, set_target_trim_prefix, target_max_width, target_trim_prefix,
+};
+#[cfg(feature = "task")]
+pub use task::{with_task_context, TaskContext};
+#[cfg(feature = "timestamps")]
+pub use timestamp::{Precision, SinkTimestamps, TimestampFormat, Timezone};
+pub use translation::{set_translation, translation, Message, Translation};
+
+/// The version of the schema of the [JSON](Format::Json) and [key-value](Format::KeyValue) formats, which is included
+/// in every record of those formats as the `schema` field.
+///
+/// Within a major version of this crate, the schema only changes in ways that existing parsers can ignore: fields
+/// may be added, and the version is incremented when they are, but fields are never removed, renamed or changed in
+/// meaning. Parsers can therefore rely on the fields of every version up to the one they were written for.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The target used for diagnostics reported by the logger about itself.
+pub const SELF_TARGET: &str = "clogger";
 
 static INSTANCE: Logger = Logger {
     quiet: AtomicBool::new(false),
+    quiet_level: AtomicUsize::new(LevelFilter::Error as usize),
     verbosity: AtomicUsize::new(0),
+    sanitize: AtomicU8::new(2),
+    banners: AtomicBool::new(false),
+    #[cfg(feature = "color")]
+    color: AtomicU8::new(0),
+    stdout_level: AtomicUsize::new(0),
+    #[cfg(feature = "timestamps")]
+    timestamps: AtomicBool::new(false),
+    #[cfg(feature = "timestamps")]
+    timestamp_format: AtomicU8::new(0),
+    #[cfg(feature = "timestamps")]
+    timestamp_precision: AtomicU8::new(0),
+    #[cfg(feature = "timestamps")]
+    timestamp_timezone: AtomicU8::new(0),
+    trace_timing: AtomicBool::new(false),
+    metadata_verbosity: AtomicUsize::new(0),
+    bell: AtomicBool::new(false),
+    whole_line_color: AtomicBool::new(false),
+    format: AtomicU8::new(0),
+    self_log_level: AtomicUsize::new(LevelFilter::Warn as usize),
+    bootstrapping: AtomicBool::new(false),
+    #[cfg(feature = "async")]
+    async_mode: AtomicBool::new(false),
+    buffered_output: AtomicBool::new(false),
+    dry_run: AtomicBool::new(false),
+    ordered: AtomicBool::new(false),
+    dry_run_level: AtomicUsize::new(0),
+    #[cfg(feature = "notify")]
+    notify: AtomicBool::new(false),
 };
 
 struct Logger {
     quiet: AtomicBool,
+    quiet_level: AtomicUsize,
     verbosity: AtomicUsize,
+    sanitize: AtomicU8,
+    banners: AtomicBool,
+    #[cfg(feature = "color")]
+    color: AtomicU8,
+    stdout_level: AtomicUsize,
+    #[cfg(feature = "timestamps")]
+    timestamps: AtomicBool,
+    #[cfg(feature = "timestamps")]
+    timestamp_format: AtomicU8,
+    #[cfg(feature = "timestamps")]
+    timestamp_precision: AtomicU8,
+    #[cfg(feature = "timestamps")]
+    timestamp_timezone: AtomicU8,
+    trace_timing: AtomicBool,
+    metadata_verbosity: AtomicUsize,
+    bell: AtomicBool,
+    whole_line_color: AtomicBool,
+    format: AtomicU8,
+    self_log_level: AtomicUsize,
+    bootstrapping: AtomicBool,
+    #[cfg(feature = "async")]
+    async_mode: AtomicBool,
+    buffered_output: AtomicBool,
+    dry_run: AtomicBool,
+    ordered: AtomicBool,
+    dry_run_level: AtomicUsize,
+    #[cfg(feature = "notify")]
+    notify: AtomicBool,
 }
 
-impl Log for Logger {
-    fn enabled(&self, _: &Metadata) -> bool {
-        true
+impl Logger {
+    /// Apply message filters and sanitization to an entry, then write it if it passes.
+    fn process(&self, mut entry: Entry) {
+        let filters = filter::read();
+        if !filters.messages().allows(entry.message()) || !filters.tags().allows(entry.tags()) {
+            return;
+        }
+
+        let rule = entry.code().and_then(|code| filters.codes().rule(code));
+        if rule == Some(CodeRule::Suppress) {
+            drop(filters);
+            catalog::count_suppressed(&entry);
+            return;
+        }
+
+        if let Some(CodeRule::Level(level)) = rule {
+            entry.set_level(level);
+        }
+
+        filters.escalations().apply(&mut entry);
+        drop(filters);
+
+        // A record moved to a more verbose level is only displayed if that level is enabled.
+        if let Some(CodeRule::Level(level)) = rule {
+            if level > target_level(entry.target()) {
+                catalog::count_suppressed(&entry);
+                return;
+            }
+        }
+
+        entry.sanitize(entry.preformatted_sanitize().unwrap_or_else(sanitize));
+        self.emit(&entry);
     }
 
-    fn log(&self, record: &Record) {
-        let (name, color) = match record.metadata().level() {
-            Level::Error => ("error", Color::Red),
-            Level::Warn => ("warn", Color::Purple),
-            Level::Info => ("info", Color::Yellow),
-            Level::Debug => ("debug", Color::Cyan),
-            Level::Trace => ("trace", Color::Blue),
+    /// Handle a record logged through the `log` facade, or a line that was already formatted by the caller.
+    fn handle(&self, record: &Record, line: Option<(&str, Sanitize)>) {
+        let reentry = reentry::enter();
+        let nested = reentry.depth() > 0;
+
+        if nested && (reentry.depth() > 1 || reentry::reentrancy() == Reentrancy::Suppress) {
+            if self.enabled(record.metadata()) {
+                drops::count(Cause::Reentrant);
+            }
+            return;
+        }
+
+        let bootstrapping = self.bootstrapping.load(Ordering::SeqCst);
+        let level_enabled = self.enabled(record.metadata());
+        let enabled =
+            level_enabled && (bootstrapping || (sample::keep(record) && burst::keep(record)));
+        let recording = history::recording();
+
+        if !bootstrapping && !enabled && !recording {
+            return;
+        }
+
+        // In ordered mode, the record must be sequenced and handed off for writing without any other record being
+        // sequenced in between.
+        // A nested record would wait for the lock held by the record it is nested in forever.
+        let _guard = if self.ordered.load(Ordering::SeqCst) && !nested {
+            order::lock()
+        } else {
+            None
+        };
+
+        let mut entry = Entry::new(
+            record,
+            style::with_markup(record.args()),
+            clock::now(),
+            banner::causes(record),
+        );
+
+        if let Some((line, mode)) = line {
+            entry.set_preformatted(line, mode);
+        }
+
+        if nested {
+            entry.push_field("reentrant", "true");
+        }
+
+        if recording {
+            if record.level() == Level::Error {
+                history::replay();
+            }
+            // Records dropped by sampling or the burst limit are not replayed, which would undo the limits.
+            history::record(&entry, !level_enabled);
+        }
+
+        // Filtering of buffered records is deferred until they are replayed, since the configuration is likely to
+        // change before then.
+        if bootstrapping {
+            if let Some(entry) = bootstrap::buffer(entry) {
+                if enabled {
+                    self.process(entry);
+                }
+            }
+        } else if !enabled {
+            // Only recorded in the history.
+        } else {
+            // In asynchronous mode, the entry is only returned if the background thread could not be started.
+            #[cfg(feature = "async")]
+            let entry = match self.async_mode.load(Ordering::SeqCst) {
+                true => queue::send(entry),
+                false => Some(entry),
+            };
+            #[cfg(not(feature = "async"))]
+            let entry = Some(entry);
+
+            if let Some(entry) = entry {
+                self.process(entry);
+            }
+        }
+
+        if recording && record.level() == Level::Error {
+            history::dump();
+        }
+    }
+
+    /// Write an entry that has passed filtering to the console and all sinks.
+    fn emit(&self, entry: &Entry) {
+        let format = output_format();
+        let machine_mode = format == Format::Machine;
+        let options = options(entry, format);
+
+        let level_color = filter::read()
+            .tags()
+            .color(entry.tags())
+            .unwrap_or_else(|| palette::palette().level(entry.level()));
+
+        let stream = match stdout_level() {
+            Some(level) if entry.level() >= level => Stream::Stdout,
+            _ => Stream::Stderr,
+        };
+
+        let colored = colored(stream, format);
+        let style = if colored {
+            Style::new().fg(level_color)
+        } else {
+            Style::new()
         };
 
-        eprintln!("{}: {}", color.paint(name), record.args());
+        // Output held back for the pager is shown later instead.
+        let write = |text: &str| {
+            if !pager::buffer(entry.level(), text) {
+                stream.write_record(entry.level(), text);
+            }
+        };
+
+        if entry.is_preformatted() {
+            write(entry.message());
+            test::record(entry);
+            sink::dispatch(entry, &options, entry.message());
+        } else if format.is_json() {
+            #[cfg(feature = "json")]
+            json::with_line(entry, &options, |line| {
+                if !forward::forward(entry) {
+                    write(&line[..line.len() - 1]);
+                }
+
+                test::record(entry);
+                sink::dispatch(entry, &options, &line[..line.len() - 1]);
+            });
+        } else {
+            // The line is formatted once for the sinks, and again for the console only if it is styled there.
+            let plain = format::line(entry, &options, Style::new());
+            let styled = || {
+                if colored {
+                    Cow::Owned(format::line(entry, &options, style))
+                } else {
+                    Cow::Borrowed(plain.as_str())
+                }
+            };
+
+            if forward::forward(entry) {
+                // Written by the other logger instead.
+            } else if entry.level() == Level::Error && error_banners() && !machine_mode {
+                write(&banner::render(
+                    &format::level_label(entry),
+                    style,
+                    entry.message(),
+                    entry.causes(),
+                ));
+            } else if alert::needs_attention(entry.level())
+                // Without the `color` feature, styles are displayed as plain text, but a block would only be padding.
+                && cfg!(feature = "color")
+                && colored
+                && format == Format::Text
+            {
+                write(&alert::attention_block(&plain));
+            } else if entry.level() <= Level::Warn && whole_line_color() {
+                write(&style::paint_line(&styled(), style));
+            } else {
+                write(&styled());
+            }
+
+            test::record(entry);
+            sink::dispatch(entry, &options, &plain);
+        }
+
+        exit::observe(entry.level());
+        status::observe(entry.level());
+
+        if entry.level() == Level::Error && bell() {
+            alert::bell();
+        } else if alert::needs_attention(entry.level()) {
+            alert::attention_bell();
+        }
+
+        #[cfg(feature = "notify")]
+        {
+            if entry.level() == Level::Error && notifications() {
+                alert::notify(entry.message());
+            }
+        }
+    }
+}
+
+/// Capture the settings for formatting an entry.
+fn options(entry: &Entry, format: Format) -> Options {
+    let machine_mode = format == Format::Machine;
+    let metadata = verbosity() >= metadata_verbosity();
+
+    Options {
+        format,
+        metadata,
+        #[cfg(feature = "timestamps")]
+        timestamp: if !machine_mode
+            && metadata
+            && (timestamps()
+                || format::template()
+                    .as_ref()
+                    .is_some_and(|t| t.shows_timestamp()))
+        {
+            Some((
+                timestamp_format(),
+                timestamp_precision(),
+                timestamp_timezone(),
+            ))
+        } else {
+            None
+        },
+        #[cfg(all(feature = "json", feature = "timestamps"))]
+        json_timestamp: Some(json::TIMESTAMP),
+        timing: if !machine_mode && verbosity_level(verbosity()) == LevelFilter::Trace {
+            entry.timing()
+        } else {
+            None
+        },
     }
+}
 
-    fn flush(&self) {}
+/// Check if output written to a stream in the given format should be colored.
+#[cfg(feature = "color")]
+fn colored(stream: Stream, format: Format) -> bool {
+    match color() {
+        _ if format == Format::Machine || format.is_json() || format == Format::Test => false,
+        ColorChoice::Always => true,
+        ColorChoice::Auto => stream.supports_color(),
+        ColorChoice::Never => false,
+    }
+}
+
+/// Without the `color` feature, styles are always displayed as plain text.
+#[cfg(not(feature = "color"))]
+fn colored(_: Stream, _: Format) -> bool {
+    true
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= target_level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        self.handle(record, None);
+    }
+
+    fn flush(&self) {
+        burst::flush();
+        #[cfg(feature = "async")]
+        queue::flush();
+        Stream::Stdout.flush();
+        Stream::Stderr.flush();
+        forward::flush();
+        sink::flush();
+    }
 }
 
 /// Initialize the global logger.
 ///
 /// This function should be called at the beginning of your application so that all log messages are handled.
 ///
-/// This function may only be called once. Panics if initialization fails.
+/// This function may only be called once, unless the logger was installed using [`bootstrap`]. Panics if
+/// initialization fails.
 pub fn init() {
     try_init().expect("logger failed to initialize");
 }
 
 /// Attempts to initialize the global logger.
-pub fn try_init() -> Result<(), SetLoggerError> {
+///
+/// If the logger was installed using [`bootstrap`], any records buffered since then are written according to the
+/// current configuration.
+pub fn try_init() -> Result<(), Error> {
+    if INSTANCE.bootstrapping.load(Ordering::SeqCst) {
+        finish_bootstrap();
+        return Ok(());
+    }
+
+    timing::start();
+    host::init();
+    run::init();
     update_max_level();
-    set_logger(&INSTANCE)
+    set_logger(&INSTANCE)?;
+    Ok(())
+}
+
+/// Install the global logger in a buffering mode, so that records can be logged before the logger is configured.
+///
+/// This is useful when the logger configuration itself depends on work that might produce log records, such as
+/// parsing command line arguments or configuration files. Records logged after calling this function are buffered
+/// without being filtered or displayed. Once the logger is configured, call [`init`] to write the buffered records
+/// according to the final configuration and switch to normal operation.
+///
+/// Only the first 1024 records are buffered; any further records are dropped until [`init`] is called.
+///
+/// Returns an error if a global logger is already installed.
+pub fn bootstrap() -> Result<(), Error> {
+    timing::start();
+    bootstrap::start();
+    INSTANCE.bootstrapping.store(true, Ordering::SeqCst);
+
+    if let Err(e) = set_logger(&INSTANCE) {
+        INSTANCE.bootstrapping.store(false, Ordering::SeqCst);
+        bootstrap::finish();
+        return Err(e.into());
+    }
+
+    update_max_level();
+    Ok(())
+}
+
+fn finish_bootstrap() {
+    let buffered = bootstrap::finish();
+    INSTANCE.bootstrapping.store(false, Ordering::SeqCst);
+    update_max_level();
+
+    if let Some((entries, dropped)) = buffered {
+        for entry in entries {
+            if entry.level() <= target_level(entry.target()) {
+                INSTANCE.process(entry);
+            }
+        }
+
+        if dropped > 0 {
+            self_log(
+                Level::Warn,
+                format_args!("dropped {} records logged before initialization", dropped),
+            );
+        }
+    }
 }
 
 /// Check if quiet mode is enabled.
@@ -77,12 +662,31 @@ pub fn quiet() -> bool {
 
 /// Turn quiet mode on or off.
 ///
-/// When quiet mode is enabled, all logging output is discarded.
+/// When quiet mode is enabled, all records less severe than the quiet level are discarded, regardless of the
+/// verbosity. By default only errors are still displayed, so that failures are never silent. Use [`set_quiet_level`] to
+/// change this.
 ///
 /// This function may be called at any time.
 pub fn set_quiet(enabled: bool) {
     INSTANCE.quiet.store(enabled, Ordering::SeqCst);
     update_max_level();
+    change::notify(Change::Quiet);
+}
+
+/// Get the maximum level that is displayed while quiet mode is enabled.
+pub fn quiet_level() -> LevelFilter {
+    level_filter_from_usize(INSTANCE.quiet_level.load(Ordering::SeqCst))
+}
+
+/// Set the maximum level that is displayed while quiet mode is enabled.
+///
+/// The default is `LevelFilter::Error`. Setting this to `LevelFilter::Off` causes quiet mode to discard all output.
+///
+/// This function may be called at any time.
+pub fn set_quiet_level(level: LevelFilter) {
+    INSTANCE.quiet_level.store(level as usize, Ordering::SeqCst);
+    update_max_level();
+    change::notify(Change::Quiet);
 }
 
 /// Get the current logger verbosity level.
@@ -99,17 +703,908 @@ pub fn verbosity() -> usize {
 pub fn set_verbosity(verbosity: usize) {
     INSTANCE.verbosity.store(verbosity, Ordering::SeqCst);
     update_max_level();
+    change::notify(Change::Verbosity);
 }
 
-fn update_max_level() {
-    set_max_level(if quiet() {
+/// Get the current color setting.
+#[cfg(feature = "color")]
+pub fn color() -> ColorChoice {
+    ColorChoice::from_u8(INSTANCE.color.load(Ordering::SeqCst))
+}
+
+/// Set whether console output is colored.
+///
+/// When set to [`ColorChoice::Auto`], color support is detected separately for stdout and stderr, so redirecting one
+/// of them to a file does not affect the other.
+///
+/// On Windows consoles where escape sequences cannot be enabled, such as the legacy console host of older versions
+/// of Windows, colors are applied using the console API instead, so levels are still colored.
+///
+/// This function may be called at any time.
+#[cfg(feature = "color")]
+pub fn set_color(choice: ColorChoice) {
+    INSTANCE.color.store(choice.to_u8(), Ordering::SeqCst);
+}
+
+/// Get the most severe level that is routed to stdout, if any.
+pub fn stdout_level() -> Option<Level> {
+    level_from_usize(INSTANCE.stdout_level.load(Ordering::SeqCst))
+}
+
+/// Route less severe records to stdout instead of stderr.
+///
+/// Records at the given level or any more verbose level are written to stdout, while more severe records continue to
+/// be written to stderr. For example, passing `Some(Level::Info)` writes info, debug and trace records to stdout, and
+/// warnings and errors to stderr. Passing `None` writes all records to stderr, which is the default.
+///
+/// This function may be called at any time.
+pub fn set_stdout_level(level: Option<Level>) {
+    INSTANCE
+        .stdout_level
+        .store(level.map_or(0, |level| level as usize), Ordering::SeqCst);
+}
+
+/// Check if timestamps are enabled.
+#[cfg(feature = "timestamps")]
+pub fn timestamps() -> bool {
+    INSTANCE.timestamps.load(Ordering::SeqCst)
+}
+
+/// Turn timestamps on or off.
+///
+/// When enabled, each line is prefixed with the time the record was logged. Timestamps are disabled by default.
+///
+/// This function may be called at any time.
+#[cfg(feature = "timestamps")]
+pub fn set_timestamps(enabled: bool) {
+    INSTANCE.timestamps.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the minimum verbosity at which timestamps and other metadata are displayed.
+pub fn metadata_verbosity() -> usize {
+    INSTANCE.metadata_verbosity.load(Ordering::SeqCst)
+}
+
+/// Only display timestamps and other metadata, such as [host fields](set_host_fields), at and above the given
+/// verbosity.
+///
+/// This keeps output at the default verbosity minimal, while verbose output is fully annotated. This applies to the
+/// console and sinks alike, except for the `ts` field of the [JSON format](Format::Json), which is always written. The
+/// default is `0`, so that metadata is displayed at every verbosity once enabled.
+///
+/// This function may be called at any time.
+pub fn set_metadata_verbosity(verbosity: usize) {
+    INSTANCE
+        .metadata_verbosity
+        .store(verbosity, Ordering::SeqCst);
+}
+
+/// Get the current timestamp format.
+#[cfg(feature = "timestamps")]
+pub fn timestamp_format() -> TimestampFormat {
+    TimestampFormat::from_u8(INSTANCE.timestamp_format.load(Ordering::SeqCst))
+}
+
+/// Set the format used for timestamps.
+///
+/// This function may be called at any time.
+#[cfg(feature = "timestamps")]
+pub fn set_timestamp_format(format: TimestampFormat) {
+    INSTANCE
+        .timestamp_format
+        .store(format.to_u8(), Ordering::SeqCst);
+}
+
+/// Get the current timestamp precision.
+#[cfg(feature = "timestamps")]
+pub fn timestamp_precision() -> Precision {
+    Precision::from_u8(INSTANCE.timestamp_precision.load(Ordering::SeqCst))
+}
+
+/// Set the precision of the fractional seconds included in timestamps. Timestamps of the [JSON format](Format::Json)
+/// always include nanoseconds.
+///
+/// This function may be called at any time.
+#[cfg(feature = "timestamps")]
+pub fn set_timestamp_precision(precision: Precision) {
+    INSTANCE
+        .timestamp_precision
+        .store(precision.to_u8(), Ordering::SeqCst);
+}
+
+/// Get the current timezone of timestamps.
+#[cfg(feature = "timestamps")]
+pub fn timestamp_timezone() -> Timezone {
+    Timezone::from_u8(INSTANCE.timestamp_timezone.load(Ordering::SeqCst))
+}
+
+/// Set the timezone that timestamps are displayed in. Timestamps are in UTC by default, and those of the
+/// [JSON format](Format::Json) always are.
+///
+/// Sinks use the same timezone unless [configured otherwise](SinkHandle::set_timestamps).
+///
+/// This function may be called at any time.
+#[cfg(feature = "timestamps")]
+pub fn set_timestamp_timezone(timezone: Timezone) {
+    INSTANCE
+        .timestamp_timezone
+        .store(timezone.to_u8(), Ordering::SeqCst);
+}
+
+/// Get the custom line format, if one has been set.
+pub fn line_format() -> Option<String> {
+    format::template()
+        .as_ref()
+        .map(|template| template.source().to_owned())
+}
+
+/// Set a custom format for displayed lines, or `None` to use the default format.
+///
+/// The format is a string containing any of the following placeholders, which are replaced with the corresponding part
+/// of each record:
+///
+/// - `{ts}`: The time the record was logged, using the current timestamp format and precision.
+/// - `{level}`: The level of the record.
+/// - `{target}`: The target of the record.
+/// - `{thread}`: The name of the thread that logged the record.
+/// - `{seq}`: The sequence number of the record.
+/// - `{msg}`: The message.
+/// - `{kv}`: The key-value pairs attached to the record, separated by spaces.
+/// - `{code}`: The [code](coded!) of the record, if it has one.
+///
+/// Each placeholder may be padded to a minimum width and aligned to the left, right or center, using the same syntax
+/// as [`format!`], such as `{level:>5}`. Literal braces are written as `{{` and `}}`.
+///
+/// The format is parsed once when it is set, and is applied to both the console and sinks. Machine mode and error
+/// banners take precedence over a custom format.
+///
+/// ```
+/// clogger::set_line_format(Some("{ts} {level:>5} [{target}] {msg}")).unwrap();
+/// ```
+///
+/// Returns an error listing every placeholder that is not recognized or has an invalid width, such as `{lvl}` or
+/// `{msg:>x}`, in which case the current format is kept.
+///
+/// This function may be called at any time.
+pub fn set_line_format(template: Option<&str>) -> Result<(), Error> {
+    format::set_template(template.map(format::Template::parse).transpose()?);
+    Ok(())
+}
+
+/// Check if monotonic trace timing is enabled.
+pub fn trace_timing() -> bool {
+    INSTANCE.trace_timing.load(Ordering::SeqCst)
+}
+
+/// Turn monotonic trace timing on or off.
+///
+/// When enabled and the verbosity is high enough to show trace records, each line is prefixed with the number of
+/// nanoseconds elapsed since the logger was initialized, followed by the number of nanoseconds since the previous
+/// record. This is useful for lightweight latency analysis of the phases of a program without using a profiler.
+///
+/// This function may be called at any time.
+pub fn set_trace_timing(enabled: bool) {
+    INSTANCE.trace_timing.store(enabled, Ordering::SeqCst);
+}
+
+/// Check if the terminal bell is rung for error records.
+pub fn bell() -> bool {
+    INSTANCE.bell.load(Ordering::SeqCst)
+}
+
+/// Turn ringing the terminal bell for error records on or off.
+///
+/// This is useful for long-running programs, so that the user is alerted to failures when they have switched to a
+/// different window. The bell is only rung if stderr is a terminal.
+///
+/// This function may be called at any time.
+pub fn set_bell(enabled: bool) {
+    INSTANCE.bell.store(enabled, Ordering::SeqCst);
+}
+
+/// Check if entire lines are colored for errors and warnings.
+pub fn whole_line_color() -> bool {
+    INSTANCE.whole_line_color.load(Ordering::SeqCst)
+}
+
+/// Turn coloring entire lines for errors and warnings on or off.
+///
+/// By default, only the level name is colored. Coloring the entire line makes errors and warnings stand out in
+/// fast-scrolling verbose output. Lines are only colored if colored output is enabled.
+///
+/// This function may be called at any time.
+pub fn set_whole_line_color(enabled: bool) {
+    INSTANCE.whole_line_color.store(enabled, Ordering::SeqCst);
+}
+
+/// Check if desktop notifications are shown for error records.
+#[cfg(feature = "notify")]
+pub fn notifications() -> bool {
+    INSTANCE.notify.load(Ordering::SeqCst)
+}
+
+/// Turn showing desktop notifications for error records on or off.
+///
+/// Notifications are shown using `notify-send` on Linux and other Unix-like systems, and `osascript` on macOS. Nothing
+/// happens on other platforms, or if the notification command cannot be run. At most one notification is shown every
+/// 10 seconds, so that a burst of errors does not cause a storm of notifications.
+///
+/// This function may be called at any time.
+#[cfg(feature = "notify")]
+pub fn set_notifications(enabled: bool) {
+    INSTANCE.notify.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the current output format.
+pub fn output_format() -> Format {
+    Format::from_u8(INSTANCE.format.load(Ordering::SeqCst))
+}
+
+/// Set the overall format of output lines.
+///
+/// The [test format](Format::Test) makes it practical to snapshot test the complete diagnostic output of a program,
+/// such as with [insta](https://insta.rs):
+///
+/// ```
+/// clogger::set_output_format(clogger::Format::Test);
+/// ```
+///
+/// This function may be called at any time.
+pub fn set_output_format(format: Format) {
+    INSTANCE.format.store(format.to_u8(), Ordering::SeqCst);
+}
+
+/// Check if machine mode is enabled.
+pub fn machine_mode() -> bool {
+    output_format() == Format::Machine
+}
+
+/// Turn machine mode on or off.
+///
+/// Machine mode is intended for output that is parsed by scripts or other programs. When enabled, color, banners,
+/// timestamps and timing prefixes are all disabled, and every record is written as a single line in the following
+/// format, which will not change between releases:
+///
+/// ```text
+/// <level>\t<target>\t<message>
+/// ```
+///
+/// The level is one of `error`, `warn`, `info`, `debug` or `trace`. Backslashes, tabs, carriage returns and newlines in
+/// the message are escaped as `\\`, `\t`, `\r` and `\n` respectively. [Progress](progress) is written as lines that
+/// start with `progress` instead of a level.
+///
+/// This function may be called at any time.
+pub fn set_machine_mode(enabled: bool) {
+    if enabled {
+        set_output_format(Format::Machine);
+    } else if machine_mode() {
+        set_output_format(Format::Text);
+    }
+}
+
+/// Check if asynchronous mode is enabled.
+#[cfg(feature = "async")]
+pub fn async_mode() -> bool {
+    INSTANCE.async_mode.load(Ordering::SeqCst)
+}
+
+/// Turn asynchronous mode on or off.
+///
+/// When enabled, records are written to the console and sinks by a background thread, so that logging does not block
+/// the calling thread on slow output. The message, timestamp, thread and timing of each record are still captured when
+/// it is logged, so the output is the same as it would be without asynchronous mode.
+///
+/// Records that are still queued when the program exits are lost, so call [`log::logger().flush()`](Log::flush) before
+/// exiting to wait for them to be written. Turning asynchronous mode off also waits for queued records to be written.
+///
+/// This function may be called at any time.
+#[cfg(feature = "async")]
+pub fn set_async_mode(enabled: bool) {
+    INSTANCE.async_mode.store(enabled, Ordering::SeqCst);
+
+    if !enabled {
+        queue::flush();
+    }
+}
+
+/// Check if console output is buffered.
+pub fn buffered_output() -> bool {
+    INSTANCE.buffered_output.load(Ordering::SeqCst)
+}
+
+/// Turn buffering of console output on or off.
+///
+/// When enabled, info, debug and trace records are collected in a buffer and written to the console in batches of up
+/// to 64 KiB, which is much faster under heavy trace output. Warnings and errors are never held back: they are written
+/// immediately, along with everything buffered before them, so that critical diagnostics appear promptly and in order.
+/// Other output such as [`println!`](::println) and the [footer](finish) also writes the buffer first.
+///
+/// Buffered records that have not been written when the program exits are lost, so call
+/// [`log::logger().flush()`](Log::flush), [`finish`] or [`exit`] before exiting. Turning buffering off also writes the
+/// buffer.
+///
+/// This function may be called at any time.
+pub fn set_buffered_output(enabled: bool) {
+    INSTANCE.buffered_output.store(enabled, Ordering::SeqCst);
+
+    if !enabled {
+        Stream::Stdout.flush();
+        Stream::Stderr.flush();
+    }
+}
+
+/// Check if ordered mode is enabled.
+pub fn ordered() -> bool {
+    INSTANCE.ordered.load(Ordering::SeqCst)
+}
+
+/// Turn ordered mode on or off.
+///
+/// Every record is assigned a sequence number when it is logged, available using [`Entry::sequence`] and the `{seq}`
+/// placeholder of [custom line formats](set_line_format). When ordered mode is enabled, records are guaranteed to be
+/// written to the console and every sink in the order of their sequence numbers, even when logged concurrently from
+/// multiple threads or in [asynchronous mode](set_async_mode). This is important when logs are used as an audit trail,
+/// at the cost of serializing logging across threads.
+///
+/// This function may be called at any time.
+pub fn set_ordered(enabled: bool) {
+    INSTANCE.ordered.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the maximum level of diagnostics reported by the logger about itself.
+pub fn self_log_level() -> LevelFilter {
+    level_filter_from_usize(INSTANCE.self_log_level.load(Ordering::SeqCst))
+}
+
+/// Set the maximum level of diagnostics reported by the logger about itself.
+///
+/// The logger reports its own problems, such as sinks that fail to write records, as records with the target
+/// [`SELF_TARGET`]. These records are displayed according to this level instead of the current verbosity, and are not
+/// subject to any filters. The default is `LevelFilter::Warn`.
+///
+/// This function may be called at any time.
+pub fn set_self_log_level(level: LevelFilter) {
+    INSTANCE
+        .self_log_level
+        .store(level as usize, Ordering::SeqCst);
+}
+
+/// Get the current sanitization mode for message content.
+pub fn sanitize() -> Sanitize {
+    Sanitize::from_u8(INSTANCE.sanitize.load(Ordering::SeqCst))
+}
+
+/// Set how control sequences embedded in messages are handled.
+///
+/// By default, control characters are escaped so that untrusted content included in a message cannot manipulate the
+/// terminal. Filters are always applied to the original message.
+///
+/// This function may be called at any time.
+pub fn set_sanitize(mode: Sanitize) {
+    INSTANCE.sanitize.store(mode.to_u8(), Ordering::SeqCst);
+}
+
+/// Check if error banners are enabled.
+pub fn error_banners() -> bool {
+    INSTANCE.banners.load(Ordering::SeqCst)
+}
+
+/// Turn error banners on or off.
+///
+/// When enabled, error records are rendered as a colored, word-wrapped box instead of a single line. This is intended
+/// for fatal errors that are shown to the user before exiting. If the `kv` feature is enabled and an error value is
+/// attached to the record using the `:err` capture modifier, such as `error!(e:err; "failed to read config")`, the
+/// chain of underlying causes is listed below the message.
+///
+/// This function may be called at any time.
+pub fn set_error_banners(enabled: bool) {
+    INSTANCE.banners.store(enabled, Ordering::SeqCst);
+}
+
+/// Only show records from targets matching the given prefix at the default verbosity.
+///
+/// This is intended for suppressing noise from third-party crates. Once at least one prefix is registered, records
+/// from any other target are treated as if the verbosity were one level lower, so dependencies stay silent by default
+/// and only start to appear as the verbosity is increased.
+///
+/// A prefix matches a target if it is equal to it or is a parent module of it; `myapp` matches `myapp` and
+/// `myapp::net`, but not `myapplication`. This function may be called multiple times to register several crates.
+///
+/// Returns a handle that can be used to remove the prefix again.
+///
+/// This function may be called at any time.
+pub fn only_show_targets_matching(prefix: &str) -> FilterHandle {
+    filter::Filters::add(Filter::AppTarget(prefix.to_owned()))
+}
+
+/// Set the maximum log level for a specific target.
+///
+/// Targets are hierarchical: a level set for `myapp::net` also applies to `myapp::net::http`, unless a different level
+/// is set for `myapp::net::http` itself. A level set for a target takes precedence over the level implied by the
+/// current verbosity. If multiple levels are set for the same target, the most recently added one wins.
+///
+/// Returns a handle that can be used to remove the level again.
+///
+/// This function may be called at any time.
+pub fn set_target_level(target: &str, level: LevelFilter) -> FilterHandle {
+    filter::Filters::add(Filter::TargetLevel(target.to_owned(), level))
+}
+
+/// Set the maximum log levels of several targets at once, using a comma-separated list of `target=level`
+/// directives, such as `myapp=debug,hyper=warn`.
+///
+/// This is convenient for accepting target levels from a command line option or environment variable. Each
+/// directive is applied as if by [`set_target_level`].
+///
+/// ```
+/// clogger::set_target_levels("myapp::net=trace, hyper=warn").unwrap();
+///
+/// let error = clogger::set_target_levels("hyper=loud").unwrap_err();
+/// assert_eq!(error.to_string(), "invalid filter directive `hyper=loud`: unknown level `loud`");
+/// ```
+///
+/// Returns handles that can be used to remove the levels again, or an error describing the first invalid directive,
+/// in which case none of the levels are set.
+///
+/// This function may be called at any time.
+pub fn set_target_levels(directives: &str) -> Result<Vec<FilterHandle>, Error> {
+    let levels = directives
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(parse_directive)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(levels
+        .into_iter()
+        .map(|(target, level)| set_target_level(target, level))
+        .collect())
+}
+
+fn parse_directive(directive: &str) -> Result<(&str, LevelFilter), Error> {
+    let invalid = |reason: String| Error::Filter {
+        directive: directive.to_owned(),
+        reason,
+    };
+
+    let (target, level) = directive
+        .split_once('=')
+        .ok_or_else(|| invalid("expected <target>=<level>".to_owned()))?;
+    let target = target.trim();
+
+    if target.is_empty() {
+        return Err(invalid("missing target".to_owned()));
+    }
+
+    let level = level
+        .trim()
+        .parse()
+        .map_err(|_| invalid(format!("unknown level `{}`", level.trim())))?;
+
+    Ok((target, level))
+}
+
+/// Display a target using a friendlier name, such as `db` for `myapp::backend::postgres_pool`.
+///
+/// The alias is used in place of the target in the `{target}` placeholder of a [line format](set_line_format), but
+/// not in the stable [machine-readable format](set_machine_mode). Children of the target are displayed using the
+/// alias in place of the aliased part, such as `db::conn` for `myapp::backend::postgres_pool::conn`. The alias can
+/// also be used in place of the target when configuring targets, such as with [`set_target_level`].
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::LevelFilter;
+///
+/// clogger::set_target_alias("myapp::backend::postgres_pool", "db");
+/// clogger::set_target_level("db", LevelFilter::Debug);
+/// # }
+/// ```
+///
+/// Returns a handle that can be used to remove the alias again.
+///
+/// This function may be called at any time.
+pub fn set_target_alias(target: &str, alias: &str) -> FilterHandle {
+    filter::Filters::add(Filter::Alias(target.to_owned(), alias.to_owned()))
+}
+
+/// Only keep some of the records logged for a specific target.
+///
+/// This is useful for tools that log a record for every item in a loop over millions of items. Sampling applies to the
+/// target and all of its children, like [`set_target_level`], and is applied before records are formatted. Every 10
+/// seconds, a record is written to the target reporting how many of its records were sampled out, if any.
+///
+/// ```
+/// use clogger::Sampling;
+///
+/// // Only show every hundredth record for each scanned file.
+/// clogger::set_target_sampling("mytool::scan", Sampling::OneIn(100));
+/// ```
+///
+/// Returns a handle that can be used to remove the sampling again.
+///
+/// This function may be called at any time.
+pub fn set_target_sampling(target: &str, sampling: Sampling) -> FilterHandle {
+    filter::Filters::add(Filter::Sample(
+        target.to_owned(),
+        std::sync::Arc::new(sample::Sampler::new(sampling)),
+    ))
+}
+
+/// Discard all records whose rendered message matches the given regular expression.
+///
+/// This is useful for silencing known-benign noise, such as a dependency that repeatedly warns about a condition that
+/// your application already handles.
+///
+/// Returns a handle that can be used to remove the filter again, or an error if the pattern is not a valid regular
+/// expression.
+///
+/// This function may be called at any time.
+#[cfg(feature = "regex")]
+pub fn suppress_matching(pattern: &str) -> Result<FilterHandle, Error> {
+    let regex = regex::Regex::new(pattern)?;
+    Ok(filter::Filters::add(Filter::Exclude(regex)))
+}
+
+/// Only show records whose rendered message matches the given regular expression.
+///
+/// If called multiple times, a record is shown if its message matches any of the patterns. Suppressed patterns are
+/// still applied to messages that match.
+///
+/// Returns a handle that can be used to remove the filter again, or an error if the pattern is not a valid regular
+/// expression.
+///
+/// This function may be called at any time.
+#[cfg(feature = "regex")]
+pub fn show_only_matching(pattern: &str) -> Result<FilterHandle, Error> {
+    let regex = regex::Regex::new(pattern)?;
+    Ok(filter::Filters::add(Filter::Include(regex)))
+}
+
+/// Only show records tagged with the given category, such as `perf`.
+///
+/// Tags are attached to records using [`tagged!`] or a `tag` key-value pair, and are filtered independently of
+/// levels and targets: a record must still be enabled for its level and target to be shown. If called multiple times,
+/// a record is shown if it has any of the tags. Untagged records are not shown while this filter is registered.
+///
+/// Returns a handle that can be used to remove the filter again.
+///
+/// This function may be called at any time.
+pub fn show_only_tag(tag: &str) -> FilterHandle {
+    filter::Filters::add(Filter::IncludeTag(tag.to_owned()))
+}
+
+/// Discard all records tagged with the given category, regardless of their level and target.
+///
+/// Suppressed tags take precedence over [shown tags](show_only_tag), so a record tagged both `net` and `perf` is
+/// discarded if `perf` is suppressed.
+///
+/// Returns a handle that can be used to remove the filter again.
+///
+/// This function may be called at any time.
+pub fn suppress_tag(tag: &str) -> FilterHandle {
+    filter::Filters::add(Filter::ExcludeTag(tag.to_owned()))
+}
+
+/// Color the level names of records tagged with the given category, instead of using the color of their level.
+///
+/// ```
+/// use clogger::style::Color;
+///
+/// clogger::set_tag_color("security", Color::Red);
+/// ```
+///
+/// If a record has several colored tags, the most recently colored one is used. Returns a handle that can be used to
+/// remove the color again.
+///
+/// This function may be called at any time.
+pub fn set_tag_color(tag: &str, color: style::Color) -> FilterHandle {
+    filter::Filters::add(Filter::TagColor(tag.to_owned(), color))
+}
+
+/// Discard all records with the given [code](coded!), like `--allow W0007` in a linter.
+///
+/// ```
+/// clogger::suppress_code("W0007");
+/// ```
+///
+/// The number of records discarded for each code is reported once the program [finishes](finish) or [exits](exit),
+/// such as `suppressed 12 diagnostics by code: 9 W0007, 3 W0012`.
+///
+/// Returns a handle that can be used to remove the filter again.
+///
+/// This function may be called at any time.
+pub fn suppress_code(code: &str) -> FilterHandle {
+    filter::Filters::add(Filter::Code(code.to_owned(), CodeRule::Suppress))
+}
+
+/// Display all records with the given [code](coded!) at a different level, such as to downgrade a warning to an info
+/// record.
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use log::Level;
+///
+/// clogger::set_code_level("W0007", Level::Info);
+/// # }
+/// ```
+///
+/// Unlike [escalation](escalate_matching), the level may be lowered as well as raised. A record must still be enabled
+/// for its original level, and is discarded and counted like a [suppressed code](suppress_code) if its new level is
+/// not enabled for its target. If several rules are
+/// registered for the same code, the most recently registered one applies.
+///
+/// Returns a handle that can be used to remove the rule again.
+///
+/// This function may be called at any time.
+pub fn set_code_level(code: &str, level: Level) -> FilterHandle {
+    filter::Filters::add(Filter::Code(code.to_owned(), CodeRule::Level(level)))
+}
+
+/// Raise the level of all records whose rendered message matches the given regular expression.
+///
+/// Records are only escalated to a more severe level, never lowered. Whether a record is displayed is still decided
+/// using its original level.
+///
+/// Returns a handle that can be used to remove the rule again, or an error if the pattern is not a valid regular
+/// expression.
+///
+/// This function may be called at any time.
+#[cfg(feature = "regex")]
+pub fn escalate_matching(pattern: &str, level: Level) -> Result<FilterHandle, Error> {
+    let regex = regex::Regex::new(pattern)?;
+    Ok(filter::Filters::add(Filter::Escalate(
+        escalate::Rule::Matching(regex, level),
+    )))
+}
+
+/// Raise the level of records once the same message from the same target has been logged more than the given number
+/// of times within a window of time.
+///
+/// For example, to turn repeated reconnect warnings into an error:
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use std::time::Duration;
+/// use log::Level;
+///
+/// clogger::escalate_repeated(5, Duration::from_secs(60), Level::Error);
+/// # }
+/// ```
+///
+/// Records are only escalated to a more severe level, never lowered. Whether a record is displayed is still decided
+/// using its original level.
+///
+/// Returns a handle that can be used to remove the rule again.
+///
+/// This function may be called at any time.
+pub fn escalate_repeated(times: usize, window: std::time::Duration, level: Level) -> FilterHandle {
+    filter::Filters::add(Filter::Escalate(escalate::Rule::Repeated(
+        std::sync::Arc::new(escalate::Repeats::new(times, window)),
+        level,
+    )))
+}
+
+/// Check if dry-run mode is enabled.
+pub fn dry_run() -> bool {
+    INSTANCE.dry_run.load(Ordering::SeqCst)
+}
+
+/// Turn dry-run mode on or off.
+///
+/// When enabled, records logged using [`action!`] are prefixed with `[dry-run]`, or its [translation](Translation), so that programs implementing a
+/// `--dry-run` flag present the actions they would have taken consistently.
+///
+/// This function may be called at any time.
+pub fn set_dry_run(enabled: bool) {
+    INSTANCE.dry_run.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the level that actions are logged at in dry-run mode, if it has been changed.
+pub fn dry_run_level() -> Option<Level> {
+    level_from_usize(INSTANCE.dry_run_level.load(Ordering::SeqCst))
+}
+
+/// Set the level that actions logged using [`action!`] are logged at in dry-run mode.
+///
+/// Actions are logged at the info level by default. Passing `None` restores the default.
+///
+/// This function may be called at any time.
+pub fn set_dry_run_level(level: Option<Level>) {
+    INSTANCE
+        .dry_run_level
+        .store(level.map_or(0, |level| level as usize), Ordering::SeqCst);
+}
+
+/// Log an action that the program is taking, or would take in dry-run mode.
+///
+/// Actions are logged at the info level. When [dry-run mode](set_dry_run) is enabled, the message is prefixed with
+/// `[dry-run]` and logged at the [dry-run level](set_dry_run_level) instead.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// let path = "build/";
+///
+/// clogger::set_dry_run(true);
+/// action!("deleting {}", path);
+/// action!(target: "cleanup", "deleting {}", path);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! action {
+    (target: $target:expr, $($arg:tt)+) => {
+        $crate::__action($target, format_args!($($arg)+))
+    };
+    ($($arg:tt)+) => {
+        $crate::__action(module_path!(), format_args!($($arg)+))
+    };
+}
+
+#[doc(hidden)]
+pub fn __action(target: &str, args: fmt::Arguments) {
+    if dry_run() {
+        let level = dry_run_level().unwrap_or(Level::Info);
+        let prefix = translation::current().dry_run_text().to_owned();
+        log!(target: target, level, "{} {}", prefix, args);
+    } else {
+        log!(target: target, Level::Info, "{}", args);
+    }
+}
+
+/// Initialize the global logger, only showing records from the calling crate at the default verbosity.
+///
+/// This is shorthand for calling [`only_show_targets_matching`] with the name of the current crate, followed by
+/// [`init`].
+#[macro_export]
+macro_rules! init_for_crate {
+    () => {{
+        $crate::only_show_targets_matching(module_path!().split("::").next().unwrap());
+        $crate::init();
+    }};
+}
+
+/// Report a problem with the logger itself.
+pub(crate) fn self_log(level: Level, args: fmt::Arguments) {
+    if level <= self_log_level() {
+        self_report(level, SELF_TARGET, args);
+    }
+}
+
+/// Write a record generated by the logger, such as a summary of suppressed records, regardless of the verbosity.
+///
+/// Like problems with the logger itself, these records are not subject to any filters.
+pub(crate) fn self_report(level: Level, target: &str, args: fmt::Arguments) {
+    let record = Record::builder()
+        .level(level)
+        .target(target)
+        .args(args)
+        .build();
+
+    INSTANCE.emit(&Entry::new(
+        &record,
+        args.to_string(),
+        clock::now(),
+        Vec::new(),
+    ));
+}
+
+/// Get the maximum level that should be displayed for the given target.
+fn target_level(target: &str) -> LevelFilter {
+    let verbosity = verbosity();
+    let matched = filter::read().targets().lookup(target);
+
+    let level = if let Some(level) = matched.level {
+        level
+    } else if matched.app {
+        verbosity_level(verbosity)
+    } else {
+        verbosity
+            .checked_sub(1)
+            .map_or(LevelFilter::Off, verbosity_level)
+    };
+    let level = level.max(boost::level());
+
+    if quiet() {
+        level.min(quiet_level())
+    } else {
+        level
+    }
+}
+
+fn verbosity_level(verbosity: usize) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+fn level_from_usize(value: usize) -> Option<Level> {
+    Level::iter().nth(value.checked_sub(1)?)
+}
+
+fn level_filter_from_usize(value: usize) -> LevelFilter {
+    LevelFilter::iter().nth(value).unwrap_or(LevelFilter::Trace)
+}
+
+/// The most verbose level allowed by the `max_level_*` and `release_max_level_*` features of this crate, in the same
+/// order of precedence as `log`.
+const FEATURE_MAX_LEVEL: LevelFilter = {
+    if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_off"
+    )) {
+        LevelFilter::Off
+    } else if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_error"
+    )) {
+        LevelFilter::Error
+    } else if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_warn"
+    )) {
+        LevelFilter::Warn
+    } else if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_info"
+    )) {
+        LevelFilter::Info
+    } else if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_debug"
+    )) {
+        LevelFilter::Debug
+    } else if cfg!(all(
+        not(debug_assertions),
+        feature = "release_max_level_trace"
+    )) {
+        LevelFilter::Trace
+    } else if cfg!(feature = "max_level_off") {
         LevelFilter::Off
+    } else if cfg!(feature = "max_level_error") {
+        LevelFilter::Error
+    } else if cfg!(feature = "max_level_warn") {
+        LevelFilter::Warn
+    } else if cfg!(feature = "max_level_info") {
+        LevelFilter::Info
+    } else if cfg!(feature = "max_level_debug") {
+        LevelFilter::Debug
     } else {
-        match verbosity() {
-            0 => LevelFilter::Warn,
-            1 => LevelFilter::Info,
-            2 => LevelFilter::Debug,
-            _ => LevelFilter::Trace,
-        }
-    });
+        LevelFilter::Trace
+    }
+};
+
+// The features are forwarded to `log`, whose macros are what compile records out, so its static level must be at least
+// as restrictive. It may be more restrictive if another crate enables one of its features directly.
+const _: () = assert!(
+    STATIC_MAX_LEVEL as usize <= FEATURE_MAX_LEVEL as usize,
+    "the max_level features of clogger must enable the same features of log"
+);
+
+fn update_max_level() {
+    let level = verbosity_level(verbosity())
+        .max(filter::read().targets().max_level())
+        .max(boost::level());
+
+    let level = if INSTANCE.bootstrapping.load(Ordering::SeqCst) {
+        // Capture everything until the final configuration is known.
+        LevelFilter::Trace
+    } else if history::recording() {
+        LevelFilter::Trace
+    } else if quiet() {
+        level.min(quiet_level())
+    } else {
+        level
+    };
+
+    // Records more verbose than the static level are compiled out, so there is no point in checking for them.
+    set_max_level(level.min(STATIC_MAX_LEVEL));
 }
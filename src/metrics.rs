@@ -0,0 +1,276 @@
+//! Durations of phases and groups, collected for a timing breakdown.
+
+use clock;
+use format::Format;
+use kv;
+use log::Level;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use table::Table;
+use timing;
+
+static REPORT: AtomicBool = AtomicBool::new(false);
+
+/// The timings of everything that was started so far, keyed by the names of the phases and groups they were nested in,
+/// in the order they were first started.
+static TIMINGS: Mutex<Vec<(Vec<String>, Timing)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// The names of the phases and groups that are currently running on the current thread.
+    static RUNNING: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn timings() -> std::sync::MutexGuard<'static, Vec<(Vec<String>, Timing)>> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The total time spent in a [phase](::phase!) or [group](::group!), returned as part of [`Metrics`].
+///
+/// Phases and groups with the same name that are nested in the same phases and groups are combined, so a phase that
+/// runs once for each file is reported once, with the number of times it ran. Phases and groups are only nested in
+/// those running on the same thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timing {
+    name: String,
+    depth: usize,
+    count: u64,
+    failures: u64,
+    total: Duration,
+}
+
+impl Timing {
+    /// Get the name of the phase, or the heading of the group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of phases and groups this one was nested in.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the number of times it finished.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Get the number of times it [failed](::Phase::fail).
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    /// Get the total time it took, across every time it ran.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+}
+
+/// A report of how long each [phase](::phase!) and [group](::group!) took, returned by [`metrics`].
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    timings: Vec<Timing>,
+    elapsed: Duration,
+}
+
+impl Metrics {
+    /// Get the timings of every phase and group that has finished, with each one directly followed by those nested in
+    /// it.
+    pub fn timings(&self) -> &[Timing] {
+        &self.timings
+    }
+
+    /// Get the time elapsed since the program started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Build a table of the timings, with nested phases and groups indented below their parents and the share of the
+    /// elapsed time that each one took.
+    ///
+    /// In the [test format](::Format::Test), durations are displayed as `[duration]` and shares as `[share]`.
+    pub fn table(&self) -> Table {
+        let test = super::output_format() == Format::Test;
+        let mut table = Table::new()
+            .header(["PHASE", "RUNS", "TIME", "SHARE"])
+            .right_align(1)
+            .right_align(2)
+            .right_align(3);
+
+        for timing in &self.timings {
+            let mut name = format!("{}{}", "  ".repeat(timing.depth), timing.name);
+            if timing.failures > 0 {
+                name.push_str(&format!(" ({} failed)", timing.failures));
+            }
+
+            let (time, share) = if test {
+                ("[duration]".to_owned(), "[share]".to_owned())
+            } else {
+                let share = match self.elapsed.as_secs_f64() {
+                    elapsed if elapsed > 0.0 => timing.total.as_secs_f64() / elapsed * 100.0,
+                    _ => 0.0,
+                };
+                (
+                    kv::humanize_seconds(timing.total.as_secs_f64()),
+                    format!("{:.0}%", share),
+                )
+            };
+
+            table = table.row([name, timing.count.to_string(), time, share]);
+        }
+
+        table
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.table().lines() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get how long each [phase](::phase!) and [group](::group!) has taken so far, such as to display a timing breakdown:
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// {
+///     let _build = phase!("build");
+///     let _compile = phase!("compile");
+/// }
+///
+/// // PHASE      RUNS  TIME  SHARE
+/// // build         1  3.2s    80%
+/// //   compile     1  2.9s    73%
+/// print!("{}", clogger::metrics());
+/// # }
+/// ```
+///
+/// The table can also be logged using [`Metrics::table`], or written automatically when the program
+/// [finishes](::finish) using [`set_report_metrics`]. Phases and groups that are still running are not included.
+pub fn metrics() -> Metrics {
+    let timings = timings()
+        .iter()
+        .map(|(_, timing)| timing)
+        .filter(|timing| timing.count > 0)
+        .cloned()
+        .collect();
+
+    Metrics {
+        timings,
+        elapsed: timing::elapsed(),
+    }
+}
+
+/// Check if the [timing breakdown](metrics) is written when the program finishes.
+pub fn report_metrics() -> bool {
+    REPORT.load(Ordering::SeqCst)
+}
+
+/// Write the [timing breakdown](metrics) as info records when the program [finishes](::finish) or [exits](::exit),
+/// before the footer, similar to `cargo build --timings`. The default is off.
+///
+/// The breakdown is written regardless of the verbosity, unless there are no timings.
+///
+/// This function may be called at any time.
+pub fn set_report_metrics(enabled: bool) {
+    REPORT.store(enabled, Ordering::SeqCst);
+}
+
+/// A phase or group being timed, returned by [`start`].
+pub(crate) struct Scope {
+    path: Vec<String>,
+    start: Duration,
+}
+
+impl Scope {
+    /// Get the time elapsed since the phase or group started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        clock::monotonic().saturating_sub(self.start)
+    }
+}
+
+/// Start timing a phase or group, nested in those that are currently running on the current thread.
+pub(crate) fn start(name: &str) -> Scope {
+    let path = RUNNING.with(|running| {
+        let mut running = running.borrow_mut();
+        running.push(name.to_owned());
+        running.clone()
+    });
+
+    let mut timings = timings();
+    if !timings.iter().any(|(p, _)| *p == path) {
+        let timing = Timing {
+            name: name.to_owned(),
+            depth: path.len() - 1,
+            count: 0,
+            failures: 0,
+            total: Duration::ZERO,
+        };
+        timings.push((path.clone(), timing));
+    }
+
+    Scope {
+        path,
+        start: clock::monotonic(),
+    }
+}
+
+/// Stop timing a phase or group, adding the time it took to its total.
+pub(crate) fn end(scope: &Scope, failed: bool) {
+    let elapsed = scope.elapsed();
+    RUNNING.with(|running| running.borrow_mut().truncate(scope.path.len() - 1));
+
+    if let Some((_, timing)) = timings().iter_mut().find(|(p, _)| *p == scope.path) {
+        timing.count += 1;
+        timing.total += elapsed;
+        if failed {
+            timing.failures += 1;
+        }
+    }
+}
+
+/// Write the timing breakdown, if it is [enabled](set_report_metrics) and there are any timings.
+pub(crate) fn report() {
+    if !report_metrics() {
+        return;
+    }
+
+    let metrics = metrics();
+    if metrics.timings.is_empty() {
+        return;
+    }
+
+    for line in metrics.table().lines() {
+        super::self_report(Level::Info, super::SELF_TARGET, format_args!("{}", line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn scopes_are_nested_per_thread() {
+        let outer = start("metrics-test-outer");
+        let inner = start("metrics-test-inner");
+        assert_eq!(inner.path, ["metrics-test-outer", "metrics-test-inner"]);
+
+        let worker = thread::spawn(|| start("metrics-test-worker").path)
+            .join()
+            .unwrap();
+        assert_eq!(worker, ["metrics-test-worker"]);
+
+        end(&inner, false);
+        let next = start("metrics-test-next");
+        assert_eq!(next.path, ["metrics-test-outer", "metrics-test-next"]);
+        end(&next, false);
+        end(&outer, false);
+    }
+}
@@ -0,0 +1,43 @@
+//! Sequencing of records.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// The sequence number of the next record.
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+/// Held while an ordered record is being sequenced and written.
+static LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Set while the current thread holds the lock, so that records logged while writing a record do not deadlock.
+    static HELD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Assign the next sequence number.
+pub(crate) fn next() -> u64 {
+    NEXT.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Guarantees that records are written in sequence order for as long as it is held.
+pub(crate) struct Guard {
+    _lock: MutexGuard<'static, ()>,
+}
+
+/// Acquire the ordering lock, unless the current thread already holds it.
+pub(crate) fn lock() -> Option<Guard> {
+    if HELD.with(|held| held.replace(true)) {
+        return None;
+    }
+
+    Some(Guard {
+        _lock: LOCK.lock().unwrap_or_else(|e| e.into_inner()),
+    })
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        HELD.with(|held| held.set(false));
+    }
+}
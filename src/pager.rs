@@ -0,0 +1,92 @@
+//! Paging of console output.
+
+use log::Level;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static BUFFER: Mutex<String> = Mutex::new(String::new());
+
+/// Hold back a line of console output to be shown in the pager, returning `false` if it should be written now
+/// instead. Errors are always written immediately.
+pub(crate) fn buffer(level: Level, line: &str) -> bool {
+    if level == Level::Error || !ACTIVE.load(Ordering::SeqCst) {
+        return false;
+    }
+
+    let mut buffer = BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+    buffer.push_str(line);
+    buffer.push('\n');
+    true
+}
+
+/// Check if console output is being held back to be shown in a pager.
+pub fn pager() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Turn showing console output in a pager on or off.
+///
+/// While enabled, records other than errors are held back instead of being written to the console, and are shown in
+/// the pager named by the `PAGER` environment variable, or `less`, once the program [finishes](::finish) or
+/// [exits](::exit), or this is turned off again. Errors are still written immediately, so that they are not missed.
+/// Like git, `LESS` is set to `FRX` for the pager if it is not set already, so that short output is written to the
+/// terminal as usual and colors are kept. Records are written to [sinks](::add_sink) as they are logged regardless.
+///
+/// Has no effect unless stderr is a terminal, so that redirected output is written as usual.
+///
+/// This function may be called at any time.
+pub fn set_pager(enabled: bool) {
+    if enabled {
+        ACTIVE.store(io::stderr().is_terminal(), Ordering::SeqCst);
+    } else if ACTIVE.swap(false, Ordering::SeqCst) {
+        show();
+    }
+}
+
+/// Show all held back output in the pager and stop holding back output.
+pub(crate) fn show() {
+    ACTIVE.store(false, Ordering::SeqCst);
+
+    let output = std::mem::take(&mut *BUFFER.lock().unwrap_or_else(|e| e.into_inner()));
+    if output.is_empty() {
+        return;
+    }
+
+    if let Err(e) = page(&output) {
+        let _ = io::stderr().write_all(output.as_bytes());
+        super::self_log(Level::Warn, format_args!("failed to run pager: {}", e));
+    }
+}
+
+fn page(output: &str) -> io::Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+
+    let mut words = pager.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or("less"));
+    // The output would otherwise have been written to stderr, which is the stream known to be a terminal.
+    command
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::from(io::stderr()));
+
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let mut child = command.spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything, which closes the pipe.
+        let _ = stdin.write_all(output.as_bytes());
+    }
+
+    child.wait()?;
+    Ok(())
+}
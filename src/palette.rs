@@ -0,0 +1,134 @@
+//! Color palettes for the colors used by the logger itself.
+
+use log::Level;
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+use style::Color;
+
+/// The current palette: 0 if not yet read from the environment, otherwise one more than its index.
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// A set of colors used for level names, diffs, [attention blocks](::set_attention) and the [final summary](::finish).
+///
+/// Unless one is set using [`set_palette`], the palette is chosen using the `CLOGGER_PALETTE` environment variable,
+/// which may be set to `default`, `colorblind` or `light`, so that users can pick a palette that works for them
+/// without the application having to offer an option for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Palette {
+    /// Red, magenta, yellow, cyan and blue for error to trace. This is the default.
+    Default,
+
+    /// Colors that remain distinguishable with the common forms of color blindness, based on the Okabe-Ito palette.
+    /// Red and green are never used to tell things apart; additions and removals in diffs are blue and orange.
+    ColorBlind,
+
+    /// Darker colors that remain readable on terminals with a light background, avoiding yellow and cyan.
+    Light,
+}
+
+impl Palette {
+    /// Get the palette named by the `CLOGGER_PALETTE` environment variable, or the default palette if it is not set or
+    /// not recognized.
+    pub fn from_env() -> Self {
+        match env::var("CLOGGER_PALETTE") {
+            Ok(ref name) if name.eq_ignore_ascii_case("colorblind") => Palette::ColorBlind,
+            Ok(ref name) if name.eq_ignore_ascii_case("light") => Palette::Light,
+            _ => Palette::Default,
+        }
+    }
+
+    /// Get the color of a level name.
+    pub(crate) fn level(self, level: Level) -> Color {
+        match (self, level) {
+            (Palette::Default, Level::Error) => Color::Red,
+            (Palette::Default, Level::Warn) => Color::Magenta,
+            (Palette::Default, Level::Info) => Color::Yellow,
+            (Palette::Default, Level::Debug) => Color::Cyan,
+            (Palette::Default, Level::Trace) => Color::Blue,
+            (Palette::ColorBlind, Level::Error) => Color::Fixed(166),
+            (Palette::ColorBlind, Level::Warn) => Color::Fixed(214),
+            (Palette::ColorBlind, Level::Info) => Color::Fixed(39),
+            (Palette::ColorBlind, Level::Debug) => Color::Fixed(175),
+            (Palette::ColorBlind, Level::Trace) => Color::Fixed(245),
+            (Palette::Light, Level::Error) => Color::Red,
+            (Palette::Light, Level::Warn) => Color::Magenta,
+            (Palette::Light, Level::Info) => Color::Blue,
+            (Palette::Light, Level::Debug) => Color::Fixed(30),
+            (Palette::Light, Level::Trace) => Color::Fixed(240),
+        }
+    }
+
+    /// Get the color of something that was added or succeeded.
+    pub(crate) fn positive(self) -> Color {
+        match self {
+            Palette::Default => Color::Green,
+            Palette::ColorBlind => Color::Fixed(33),
+            Palette::Light => Color::Fixed(28),
+        }
+    }
+
+    /// Get the color of something that was removed or failed.
+    pub(crate) fn negative(self) -> Color {
+        match self {
+            Palette::Default | Palette::Light => Color::Red,
+            Palette::ColorBlind => Color::Fixed(208),
+        }
+    }
+
+    /// Get the color of headings and other markers, such as the hunk headers of diffs.
+    pub(crate) fn accent(self) -> Color {
+        match self {
+            Palette::Default => Color::Cyan,
+            Palette::ColorBlind => Color::Fixed(175),
+            Palette::Light => Color::Blue,
+        }
+    }
+
+    /// Get the foreground and background colors of [attention blocks](::set_attention), which should stand out from
+    /// everything else on the terminal.
+    pub(crate) fn attention(self) -> (Color, Color) {
+        match self {
+            Palette::Default | Palette::Light => (Color::White, Color::Red),
+            Palette::ColorBlind => (Color::Black, Color::Fixed(208)),
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Palette::Default),
+            2 => Some(Palette::ColorBlind),
+            3 => Some(Palette::Light),
+            _ => None,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Palette::Default => 1,
+            Palette::ColorBlind => 2,
+            Palette::Light => 3,
+        }
+    }
+}
+
+/// Get the current palette.
+pub fn palette() -> Palette {
+    Palette::from_u8(CURRENT.load(Ordering::SeqCst)).unwrap_or_else(|| {
+        let palette = Palette::from_env();
+        let _ = CURRENT.compare_exchange(0, palette.to_u8(), Ordering::SeqCst, Ordering::SeqCst);
+        palette
+    })
+}
+
+/// Set the palette of colors used by the logger, overriding the `CLOGGER_PALETTE` environment variable.
+///
+/// ```
+/// use clogger::Palette;
+///
+/// clogger::set_palette(Palette::ColorBlind);
+/// ```
+///
+/// This function may be called at any time.
+pub fn set_palette(palette: Palette) {
+    CURRENT.store(palette.to_u8(), Ordering::SeqCst);
+}
@@ -0,0 +1,106 @@
+//! Primary program output written alongside log records.
+
+use clock;
+use console::Stream;
+use format::{self, Format};
+use log::{Level, Record};
+#[cfg(feature = "async")]
+use queue;
+use record::Entry;
+use sink;
+use std::borrow::Cow;
+use std::fmt;
+use style::{self, Style};
+use test;
+
+/// Print a line of primary program output to standard output.
+///
+/// Unlike a log record, the line is printed regardless of the verbosity and filters, but is still handled consistently
+/// with log records:
+///
+/// - In [quiet mode](::set_quiet), nothing is printed.
+/// - Values styled using [`style`](::style()) are only colored if colored output is enabled for standard output.
+/// - The line is written to all [sinks](::add_sink) as an info record.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # fn main() {
+/// clogger::println!("{} files copied", 42);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::__print(module_path!(), false, format_args!(""))
+    };
+    ($($arg:tt)+) => {
+        $crate::__print(module_path!(), false, format_args!($($arg)+))
+    };
+}
+
+/// Print a line of primary program output to standard error.
+///
+/// This behaves the same as [`println!`](::println), except that in [machine mode](::set_machine_mode) and the
+/// [JSON format](::Format::Json) the line is written in that format as an info record, so that standard error remains
+/// parseable.
+#[macro_export]
+macro_rules! eprintln {
+    () => {
+        $crate::__print(module_path!(), true, format_args!(""))
+    };
+    ($($arg:tt)+) => {
+        $crate::__print(module_path!(), true, format_args!($($arg)+))
+    };
+}
+
+#[doc(hidden)]
+pub fn __print(target: &str, stderr: bool, args: fmt::Arguments) {
+    // Keep the output ordered with any records that are still being written asynchronously.
+    #[cfg(feature = "async")]
+    queue::flush();
+
+    let record = Record::builder()
+        .level(Level::Info)
+        .target(target)
+        .args(args)
+        .build();
+    let entry = Entry::new(&record, style::with_markup(&args), clock::now(), Vec::new());
+    let format = super::output_format();
+
+    if !super::quiet() {
+        let stream = if stderr {
+            Stream::Stderr
+        } else {
+            Stream::Stdout
+        };
+
+        if stderr && format == Format::Machine {
+            stream.write_line(&format::machine(Level::Info, target, entry.message()));
+        } else if stderr && format.is_json() {
+            let options = super::options(&entry, format);
+            stream.write_line(&format::line(&entry, &options, Style::new()));
+        } else {
+            stream.write_line(&message(&entry, super::colored(stream, format)));
+        }
+    }
+
+    test::record(&entry);
+    let options = super::options(&entry, format);
+    sink::dispatch(
+        &entry,
+        &options,
+        &format::line(&entry, &options, Style::new()),
+    );
+}
+
+#[cfg_attr(not(feature = "color"), allow(unused_variables))]
+fn message(entry: &Entry, colored: bool) -> Cow<'_, str> {
+    #[cfg(feature = "color")]
+    {
+        if colored {
+            return style::render_markup(entry.styled_message());
+        }
+    }
+
+    Cow::Borrowed(entry.message())
+}
@@ -0,0 +1,223 @@
+//! Progress of long-running operations.
+
+use console::Stream;
+use format::{self, Format};
+#[cfg(feature = "json")]
+use json;
+use pager;
+use status;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The width of the bar drawn on the terminal, in columns.
+const BAR_WIDTH: usize = 30;
+
+/// Whether a progress bar is currently drawn on the terminal.
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+/// The ID of the progress bar currently drawn on the terminal and its text.
+static BAR: Mutex<Option<(u64, String)>> = Mutex::new(None);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A handle for reporting the progress of an operation, returned by [`progress`].
+///
+/// The handle can be shared between threads that work on the operation together.
+#[must_use = "the progress bar is removed as soon as it is dropped"]
+pub struct Progress {
+    id: u64,
+    name: String,
+    total: u64,
+    current: AtomicU64,
+    /// The percentage that was last reported, or `u64::MAX` if none was.
+    reported: AtomicU64,
+}
+
+impl Progress {
+    /// Get the amount of work done so far.
+    pub fn current(&self) -> u64 {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    /// Get the total amount of work.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Get the percentage of the work done so far, from 0 to 100.
+    pub fn percent(&self) -> u64 {
+        percent(self.current(), self.total)
+    }
+
+    /// Set the amount of work done so far.
+    pub fn set(&self, current: u64) {
+        self.current.store(current, Ordering::SeqCst);
+        self.report();
+    }
+
+    /// Add to the amount of work done so far.
+    pub fn inc(&self, delta: u64) {
+        self.current.fetch_add(delta, Ordering::SeqCst);
+        self.report();
+    }
+
+    /// Mark all of the work as done and remove the progress bar. This is the same as dropping the handle after
+    /// setting the amount of work done to the total.
+    pub fn finish(self) {
+        self.set(self.total);
+    }
+
+    /// Report the progress if the percentage has changed since it was last reported.
+    fn report(&self) {
+        let current = self.current();
+        let percent = percent(current, self.total);
+
+        if self.reported.swap(percent, Ordering::SeqCst) == percent || super::quiet() {
+            return;
+        }
+
+        status::set_progress(self.id, Some(percent));
+
+        match super::output_format() {
+            Format::Machine => {
+                Stream::Stderr.write_line(&machine(&self.name, current, self.total, percent))
+            }
+            #[cfg(feature = "json")]
+            Format::Json => {
+                Stream::Stderr.write_line(&json::progress(&self.name, current, self.total, percent))
+            }
+            Format::Test => {}
+            _ if io::stderr().is_terminal() && !pager::pager() => {
+                let bar = bar(&self.name, current, self.total, percent);
+                let mut drawn = BAR.lock().unwrap_or_else(|e| e.into_inner());
+
+                Stream::Stderr.write_str(&format!("\r\x1b[2K{}", bar));
+                *drawn = Some((self.id, bar));
+                DRAWN.store(true, Ordering::SeqCst);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        status::set_progress(self.id, None);
+
+        if !DRAWN.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let mut drawn = BAR.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((id, _)) = *drawn {
+            if id == self.id {
+                Stream::Stderr.write_str("\r\x1b[2K");
+                *drawn = None;
+                DRAWN.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("name", &self.name)
+            .field("current", &self.current())
+            .field("total", &self.total)
+            .finish()
+    }
+}
+
+/// Start reporting the progress of an operation with the given name and total amount of work.
+///
+/// How progress is reported depends on the output format:
+///
+/// - When stderr is a terminal, a progress bar is drawn on the last line and redrawn as progress is made. Records
+///   written in the meantime are written above it. Only the most recently updated progress bar is displayed.
+/// - In [machine mode](::set_machine_mode), each change of the percentage is written to stderr as a line in the
+///   following format, instead of a progress bar:
+///
+///   ```text
+///   progress\t<name>\t<current>\t<total>\t<percent>
+///   ```
+///
+/// - In the [JSON format](::Format::Json), each change of the percentage is written to stderr as a `progress` event:
+///
+///   ```text
+///   {"schema":1,"ts":"2024-05-01T12:00:00Z","event":"progress","name":"download","current":4,"total":10,"percent":40}
+///   ```
+///
+/// This allows tools that wrap a program to render their own progress UI. Nothing is written otherwise, or in
+/// [quiet mode](::set_quiet), and progress is never written to [sinks](::add_sink).
+///
+/// ```
+/// let files = ["a.txt", "b.txt", "c.txt"];
+/// let progress = clogger::progress("copying", files.len() as u64);
+///
+/// for file in &files {
+///     // ...
+///     progress.inc(1);
+/// }
+///
+/// progress.finish();
+/// ```
+pub fn progress<N: fmt::Display>(name: N, total: u64) -> Progress {
+    Progress {
+        id: NEXT_ID.fetch_add(1, Ordering::SeqCst),
+        name: name.to_string(),
+        total,
+        current: AtomicU64::new(0),
+        reported: AtomicU64::new(u64::MAX),
+    }
+}
+
+/// Run a function that writes to the console, removing any progress bar while it runs and drawing it again after.
+pub(crate) fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    if !DRAWN.load(Ordering::Relaxed) {
+        return f();
+    }
+
+    let drawn = BAR.lock().unwrap_or_else(|e| e.into_inner());
+
+    match *drawn {
+        Some((_, ref bar)) => {
+            Stream::Stderr.write_str("\r\x1b[2K");
+            let value = f();
+            Stream::Stderr.write_str(bar);
+            value
+        }
+        None => f(),
+    }
+}
+
+fn percent(current: u64, total: u64) -> u64 {
+    match total {
+        0 => 100,
+        _ => (current.min(total) as u128 * 100 / total as u128) as u64,
+    }
+}
+
+fn machine(name: &str, current: u64, total: u64, percent: u64) -> String {
+    let mut line = String::from("progress\t");
+    format::escape(name, &mut line);
+    line.push_str(&format!("\t{}\t{}\t{}", current, total, percent));
+    line
+}
+
+fn bar(name: &str, current: u64, total: u64, percent: u64) -> String {
+    let filled = percent as usize * BAR_WIDTH / 100;
+
+    format!(
+        "{} [{}{}] {:>3}% ({}/{})",
+        name,
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        percent,
+        current,
+        total
+    )
+}
@@ -0,0 +1,95 @@
+//! Background writing of records for asynchronous mode.
+
+use log::Level;
+use record::Entry;
+use std::cell::Cell;
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+static SENDER: Mutex<Option<Sender<Message>>> = Mutex::new(None);
+
+thread_local! {
+    /// Set on the background thread that writes queued records.
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+enum Message {
+    Entry(Box<Entry>),
+    Flush(Sender<()>),
+}
+
+/// Queue an entry to be written by the background thread, starting it if necessary.
+///
+/// Returns the entry if the background thread could not be started, so that the caller can write it directly.
+pub(crate) fn send(entry: Entry) -> Option<Entry> {
+    let mut sender = SENDER.lock().unwrap_or_else(|e| e.into_inner());
+
+    if sender.is_none() {
+        match spawn() {
+            Ok(tx) => *sender = Some(tx),
+            Err(e) => {
+                drop(sender);
+                super::self_log(
+                    Level::Warn,
+                    format_args!("failed to start background thread: {}", e),
+                );
+                return Some(entry);
+            }
+        }
+    }
+
+    let result = match *sender {
+        Some(ref tx) => tx.send(Message::Entry(Box::new(entry))),
+        None => return Some(entry),
+    };
+
+    if let Err(mpsc::SendError(Message::Entry(entry))) = result {
+        // The background thread has exited, so start a new one next time.
+        *sender = None;
+        return Some(*entry);
+    }
+
+    None
+}
+
+/// Wait for all queued entries to be written.
+pub(crate) fn flush() {
+    if WORKER.with(Cell::get) {
+        return;
+    }
+
+    let (ack_tx, ack_rx) = mpsc::channel();
+
+    let sent = SENDER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|tx| tx.send(Message::Flush(ack_tx)).is_ok());
+
+    if sent {
+        let _ = ack_rx.recv();
+    }
+}
+
+fn spawn() -> io::Result<Sender<Message>> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::Builder::new()
+        .name("clogger".to_owned())
+        .spawn(move || {
+            WORKER.with(|worker| worker.set(true));
+
+            for message in rx {
+                match message {
+                    Message::Entry(entry) => super::INSTANCE.process(*entry),
+                    Message::Flush(ack) => {
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        })?;
+
+    Ok(tx)
+}
@@ -0,0 +1,47 @@
+//! Lines that were formatted outside of the logger.
+
+use log::{Level, Record};
+use sanitize::Sanitize;
+
+/// Write a line that was already formatted by the caller, as a record with the given level and target.
+///
+/// This is for integrators that format records themselves, or forward the output of another program or logging
+/// library, but want to reuse the routing of this logger without formatting the line again. The line is treated like
+/// any other record for filtering by level and target, [quiet mode](::set_quiet), [sampling](::set_target_sampling),
+/// the [burst limit](::set_burst_limit) and the [pager](::set_pager), and is displayed on the same stream. It is then
+/// written to the console and every [sink](::add_sink) as it is, regardless of the [output format](::set_output_format),
+/// so files are rotated as usual but without timestamps or level names being added:
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use clogger::Sanitize;
+/// use log::Level;
+///
+/// clogger::write_formatted(
+///     Level::Warn,
+///     "child",
+///     b"2024-05-01 12:00:00 \x1b[33m[WARN]\x1b[0m disk almost full\n",
+///     Sanitize::Off,
+/// );
+/// # }
+/// ```
+///
+/// The line is [sanitized](::set_sanitize) using the given mode instead of the global one, so that color codes added
+/// by the caller's own formatter can be kept using [`Sanitize::Off`], while lines forwarded from an untrusted source
+/// can still be escaped. A trailing line terminator is removed, and invalid UTF-8 is replaced. Sinks can tell such
+/// records apart using [`Entry::is_preformatted`](::Entry::is_preformatted).
+pub fn write_formatted(level: Level, target: &str, line: &[u8], sanitize: Sanitize) {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end_matches(['\n', '\r']);
+
+    super::INSTANCE.handle(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{}", line))
+            .build(),
+        Some((line, sanitize)),
+    );
+}
@@ -0,0 +1,233 @@
+//! Owned log records.
+
+use catalog;
+use group;
+use hexdump;
+use kv;
+use log::{Level, Record};
+use order;
+use sanitize::Sanitize;
+use sink;
+use std::thread::{self, ThreadId};
+use std::time::SystemTime;
+use style;
+use tag;
+#[cfg(feature = "task")]
+use task;
+use timing;
+
+/// A log record that has passed filtering, along with its rendered message.
+///
+/// Unlike [`log::Record`], an entry owns all of its data, so it can be kept around or passed to sinks after the
+/// logging call has returned.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    sequence: u64,
+    level: Level,
+    target: String,
+    message: String,
+    styled: Option<String>,
+    time: SystemTime,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    causes: Vec<String>,
+    fields: Vec<(String, String)>,
+    tags: Vec<String>,
+    code: Option<String>,
+    thread: Option<String>,
+    thread_id: ThreadId,
+    timing: Option<(u64, u64)>,
+    depth: usize,
+    scopes: Vec<u64>,
+    preformatted: Option<Sanitize>,
+}
+
+impl Entry {
+    /// Create an entry for a record.
+    ///
+    /// This must be called on the thread that logged the record, since the current thread and monotonic timing are
+    /// captured here rather than when the entry is written.
+    pub(crate) fn new(
+        record: &Record,
+        message: String,
+        time: SystemTime,
+        causes: Vec<String>,
+    ) -> Self {
+        let (message, styled) = match style::strip_markup(&message) {
+            Some(plain) => (plain, Some(message)),
+            None => (message, None),
+        };
+
+        let mut fields = kv::fields(record);
+        #[cfg(feature = "task")]
+        task::extend(&mut fields);
+        let tags = tag::collect(&mut fields);
+        let code = catalog::collect(&mut fields);
+        hexdump::extend(&mut fields);
+
+        Entry {
+            sequence: order::next(),
+            level: record.level(),
+            target: record.target().to_owned(),
+            message,
+            styled,
+            time,
+            module_path: record.module_path().map(ToOwned::to_owned),
+            file: record.file().map(ToOwned::to_owned),
+            line: record.line(),
+            causes,
+            fields,
+            tags,
+            code,
+            thread: thread::current().name().map(ToOwned::to_owned),
+            thread_id: thread::current().id(),
+            timing: if super::trace_timing() {
+                Some(timing::monotonic())
+            } else {
+                None
+            },
+            depth: group::depth(),
+            scopes: sink::scopes(),
+            preformatted: None,
+        }
+    }
+
+    /// Apply a sanitization mode to the message and causes.
+    pub(crate) fn sanitize(&mut self, mode: Sanitize) {
+        self.message = mode.apply(&self.message).into_owned();
+
+        if let Some(ref mut styled) = self.styled {
+            *styled = mode.apply(styled).into_owned();
+        }
+
+        for cause in &mut self.causes {
+            *cause = mode.apply(cause).into_owned();
+        }
+
+        for (key, value) in &mut self.fields {
+            *key = mode.apply(key).into_owned();
+            *value = mode.apply(value).into_owned();
+        }
+    }
+
+    /// Get the sequence number of the record, which is assigned in the order that records are logged.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Get the level of the record.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Add a key-value pair to the record.
+    pub(crate) fn push_field(&mut self, key: &str, value: &str) {
+        self.fields.push((key.to_owned(), value.to_owned()));
+    }
+
+    /// Replace the message with a line that was already formatted by the caller, which is written as it is after
+    /// being sanitized using the given mode.
+    pub(crate) fn set_preformatted(&mut self, line: &str, mode: Sanitize) {
+        self.message = line.to_owned();
+        self.styled = None;
+        self.preformatted = Some(mode);
+    }
+
+    /// Check if the record was written using [`write_formatted`](::write_formatted), in which case its message is the
+    /// complete line as it was formatted by the caller, and is written to the console and sinks as it is.
+    pub fn is_preformatted(&self) -> bool {
+        self.preformatted.is_some()
+    }
+
+    /// Get the sanitization mode given for a line that was already formatted by the caller, if any.
+    pub(crate) fn preformatted_sanitize(&self) -> Option<Sanitize> {
+        self.preformatted
+    }
+
+    /// Change the level of the record, such as when it is escalated.
+    pub(crate) fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+
+    /// Get the target of the record.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Get the rendered message, after sanitization has been applied.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the message including any markup for styled values.
+    #[cfg(feature = "color")]
+    pub(crate) fn styled_message(&self) -> &str {
+        self.styled.as_deref().unwrap_or(&self.message)
+    }
+
+    /// Get the time at which the record was logged.
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Get the module path of the code that logged the record, if known.
+    pub fn module_path(&self) -> Option<&str> {
+        self.module_path.as_deref()
+    }
+
+    /// Get the source file of the code that logged the record, if known.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Get the line number of the code that logged the record, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Get the chain of error messages attached to the record using the `:err` capture modifier, if any.
+    pub fn causes(&self) -> &[String] {
+        &self.causes
+    }
+
+    /// Get the key-value pairs attached to the record, other than errors, with their values rendered as text.
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// Get the category tags of the record, from its `tag` field and any enclosing [`tagged!`](::tagged!).
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Get the [code](::coded!) of the record, from its `code` field or its message template, if it has one.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Get the name of the thread that logged the record, if it has one.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    /// Get the ID of the thread that logged the record.
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    /// Get the monotonic timing of the record, if trace timing was enabled when it was logged.
+    pub(crate) fn timing(&self) -> Option<(u64, u64)> {
+        self.timing
+    }
+
+    /// Get the number of groups that were open when the record was logged.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the IDs of the [scoped sinks](::with_sink) that were attached to the thread when the record was logged.
+    pub(crate) fn scopes(&self) -> &[u64] {
+        &self.scopes
+    }
+}
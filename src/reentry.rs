@@ -0,0 +1,73 @@
+//! Detection of records logged while the logger is already handling a record on the same thread.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// What happens to records that are logged while the logger is already handling a record on the same thread.
+///
+/// This happens when code called by the logger logs itself, such as the `Display` implementation of a value in a
+/// message, or a [sink](::Sink) or [hook](::on_change) that logs. Records nested more than one level deep are always
+/// discarded, so that such code cannot recurse indefinitely.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reentrancy {
+    /// Write nested records with a `reentrant=true` field, so that they can be told apart. This is the default.
+    Mark,
+
+    /// Discard nested records.
+    Suppress,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+thread_local! {
+    /// The number of records the logger is handling on the current thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A guard for a record being handled, returned by [`enter`].
+pub(crate) struct Guard {
+    depth: usize,
+}
+
+impl Guard {
+    /// Get the number of records that were already being handled on this thread when this one was logged.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(self.depth));
+    }
+}
+
+/// Start handling a record on the current thread.
+pub(crate) fn enter() -> Guard {
+    let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    Guard { depth }
+}
+
+/// Get what happens to records that are logged while the logger is already handling a record on the same thread.
+pub fn reentrancy() -> Reentrancy {
+    match MODE.load(Ordering::SeqCst) {
+        1 => Reentrancy::Suppress,
+        _ => Reentrancy::Mark,
+    }
+}
+
+/// Set what happens to records that are logged while the logger is already handling a record on the same thread.
+///
+/// Nested records never wait for locks held by the record they are nested in, such as in
+/// [ordered mode](::set_ordered), so they cannot deadlock. Records that are discarded are included in the summary of
+/// dropped records written by [`finish`](::finish).
+///
+/// This function may be called at any time.
+pub fn set_reentrancy(reentrancy: Reentrancy) {
+    let mode = match reentrancy {
+        Reentrancy::Mark => 0,
+        Reentrancy::Suppress => 1,
+    };
+
+    MODE.store(mode, Ordering::SeqCst);
+}
@@ -0,0 +1,46 @@
+//! A short ID identifying the current run of the program.
+
+use sample;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static FIELDS: OnceLock<[(String, String); 1]> = OnceLock::new();
+
+/// Get the ID of the current run of the program, if run IDs are enabled.
+pub fn run_id() -> Option<&'static str> {
+    if ENABLED.load(Ordering::SeqCst) {
+        Some(&init()[0].1)
+    } else {
+        None
+    }
+}
+
+/// Include a short randomly generated ID for the current run of the program, such as `4f9a2c1e`, as the `run` field of
+/// every record in the [key-value](::Format::KeyValue) and [JSON](::Format::Json) formats, and in the footer written
+/// by [`finish`](::finish).
+///
+/// A user pasting a snippet of output into a bug report can then be matched to the full log file of that run. The ID
+/// is generated once, when the logger is initialized, and is available from [`run_id`] to include elsewhere.
+///
+/// This function may be called at any time.
+pub fn set_run_id(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Get the run ID field, if it is enabled.
+pub(crate) fn fields() -> &'static [(String, String)] {
+    if ENABLED.load(Ordering::SeqCst) {
+        init()
+    } else {
+        &[]
+    }
+}
+
+/// Generate the run ID, if it has not been generated yet.
+pub(crate) fn init() -> &'static [(String, String); 1] {
+    FIELDS.get_or_init(|| {
+        let id = (sample::random() * f64::from(u32::MAX)) as u32;
+        [("run".to_owned(), format!("{:08x}", id))]
+    })
+}
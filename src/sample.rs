@@ -0,0 +1,141 @@
+//! Sampling of records from high-volume targets.
+
+use clock;
+use drops::{self, Cause};
+use filter;
+use log::Record;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use translation::{self, Message};
+
+/// How often the number of records that were sampled out is reported for each sampled target.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Which records from a target are kept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sampling {
+    /// Keep the first of every `n` records, starting with the first record.
+    OneIn(u64),
+
+    /// Keep each record with the given probability, between `0.0` and `1.0`.
+    Probability(f64),
+}
+
+/// The state of a sampling rule, which is shared by every target it applies to.
+pub(crate) struct Sampler {
+    sampling: Sampling,
+    count: AtomicU64,
+    window_seen: AtomicU64,
+    window_dropped: AtomicU64,
+    last_report: AtomicU64,
+}
+
+impl Sampler {
+    pub(crate) fn new(sampling: Sampling) -> Self {
+        Sampler {
+            sampling,
+            count: AtomicU64::new(0),
+            window_seen: AtomicU64::new(0),
+            window_dropped: AtomicU64::new(0),
+            last_report: AtomicU64::new(clock::monotonic().as_millis() as u64),
+        }
+    }
+
+    /// Decide whether the next record should be kept.
+    fn keep(&self) -> bool {
+        let keep = match self.sampling {
+            Sampling::OneIn(n) => self
+                .count
+                .fetch_add(1, Ordering::Relaxed)
+                .is_multiple_of(n.max(1)),
+            Sampling::Probability(p) => random() < p,
+        };
+
+        self.window_seen.fetch_add(1, Ordering::Relaxed);
+        if !keep {
+            self.window_dropped.fetch_add(1, Ordering::Relaxed);
+            drops::count(Cause::Sampling);
+        }
+
+        keep
+    }
+
+    /// Get the number of records that were sampled out and seen in the last interval, if a report is due.
+    fn report(&self) -> Option<(u64, u64)> {
+        let now = clock::monotonic().as_millis() as u64;
+        let last = self.last_report.load(Ordering::Relaxed);
+
+        if now.saturating_sub(last) < REPORT_INTERVAL.as_millis() as u64
+            || self
+                .last_report
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return None;
+        }
+
+        let dropped = self.window_dropped.swap(0, Ordering::Relaxed);
+        let seen = self.window_seen.swap(0, Ordering::Relaxed);
+
+        if dropped > 0 {
+            Some((dropped, seen))
+        } else {
+            None
+        }
+    }
+}
+
+/// Decide whether an enabled record should be kept under the sampling rule for its target, if there is one.
+///
+/// When a report is due, the number of records from the target that were sampled out is written first, at the level
+/// of the record.
+pub(crate) fn keep(record: &Record) -> bool {
+    let filters = filter::read();
+    let sampler = match filters.targets().sampler(record.target()) {
+        Some(sampler) => sampler,
+        None => return true,
+    };
+
+    let keep = sampler.keep();
+    let report = sampler.report();
+    drop(filters);
+
+    if let Some((dropped, seen)) = report {
+        let message = translation::current().format(
+            Message::SampleSummary,
+            &[
+                ("dropped", &dropped),
+                ("seen", &seen),
+                ("seconds", &REPORT_INTERVAL.as_secs()),
+            ],
+        );
+        super::self_report(record.level(), record.target(), format_args!("{}", message));
+    }
+
+    keep
+}
+
+/// Generate a pseudo-random number between `0.0` and `1.0`.
+///
+/// This only needs to be good enough to sample records evenly, so a SplitMix64 generator is used instead of adding a
+/// dependency.
+pub(crate) fn random() -> f64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+    static SEED: OnceLock<u64> = OnceLock::new();
+
+    let seed = *SEED.get_or_init(|| {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        time.as_nanos() as u64 ^ (u64::from(process::id()) << 32)
+    });
+
+    let mut z = seed.wrapping_add(STATE.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,152 @@
+//! Sanitization of control sequences in untrusted message content.
+
+use std::borrow::Cow;
+
+/// How terminal control sequences embedded in log messages are handled.
+///
+/// Messages often include content that comes from outside of the application, such as file names or server
+/// responses. If that content contains ANSI escape sequences or other control characters, printing it verbatim could
+/// change the terminal title, move the cursor, or spoof other output.
+///
+/// Newlines and tabs are always preserved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sanitize {
+    /// Print messages verbatim.
+    Off,
+
+    /// Remove escape sequences and control characters entirely.
+    Strip,
+
+    /// Replace control characters with a visible escape, such as `\u{1b}`. This is the default.
+    Escape,
+}
+
+impl Sanitize {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Sanitize::Off,
+            1 => Sanitize::Strip,
+            _ => Sanitize::Escape,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Sanitize::Off => 0,
+            Sanitize::Strip => 1,
+            Sanitize::Escape => 2,
+        }
+    }
+
+    /// Apply this sanitization mode to a message.
+    pub(crate) fn apply(self, message: &str) -> Cow<'_, str> {
+        if self == Sanitize::Off || !message.chars().any(is_unsafe) {
+            return Cow::Borrowed(message);
+        }
+
+        let mut output = String::with_capacity(message.len());
+        let mut chars = message.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if !is_unsafe(c) {
+                output.push(c);
+            } else if self == Sanitize::Escape {
+                output.extend(c.escape_default());
+            } else if c == '\x1b' {
+                // Skip the rest of the escape sequence along with the introducer.
+                match chars.next() {
+                    Some('[') => skip_csi(&mut chars),
+                    Some(']') | Some('P') | Some('_') | Some('^') => skip_string(&mut chars),
+                    _ => {}
+                }
+            } else if c == '\u{9b}' {
+                skip_csi(&mut chars);
+            } else if c == '\u{9d}' || c == '\u{90}' {
+                skip_string(&mut chars);
+            }
+        }
+
+        Cow::Owned(output)
+    }
+}
+
+fn is_unsafe(c: char) -> bool {
+    c.is_control() && c != '\n' && c != '\t'
+}
+
+/// Skip the parameters and final byte of a control sequence.
+fn skip_csi(chars: &mut impl Iterator<Item = char>) {
+    for c in chars {
+        if ('\x40'..='\x7e').contains(&c) {
+            break;
+        }
+    }
+}
+
+/// Skip a control string up to and including its terminator.
+fn skip_string(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>) {
+    while let Some(c) = chars.next() {
+        match c {
+            '\x07' | '\u{9c}' => break,
+            '\x1b' if chars.peek() == Some(&'\\') => {
+                chars.next();
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_messages_are_borrowed() {
+        for mode in [Sanitize::Off, Sanitize::Strip, Sanitize::Escape] {
+            assert!(matches!(
+                mode.apply("line one\n\tline two"),
+                Cow::Borrowed("line one\n\tline two")
+            ));
+        }
+    }
+
+    #[test]
+    fn off_keeps_control_sequences() {
+        assert_eq!(
+            Sanitize::Off.apply("\x1b[31mred\x1b[0m"),
+            "\x1b[31mred\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn escape_makes_control_characters_visible() {
+        assert_eq!(
+            Sanitize::Escape.apply("\x1b[31mred\x1b[0m\r\x07"),
+            "\\u{1b}[31mred\\u{1b}[0m\\r\\u{7}"
+        );
+    }
+
+    #[test]
+    fn strip_removes_csi_sequences() {
+        assert_eq!(Sanitize::Strip.apply("\x1b[1;31mred\x1b[0m"), "red");
+        assert_eq!(Sanitize::Strip.apply("\u{9b}2Jcleared"), "cleared");
+    }
+
+    #[test]
+    fn strip_removes_control_strings() {
+        // Terminated by a bell, and by a string terminator.
+        assert_eq!(Sanitize::Strip.apply("\x1b]2;title\x07text"), "text");
+        assert_eq!(Sanitize::Strip.apply("\x1b]8;;http://x\x1b\\link"), "link");
+        assert_eq!(Sanitize::Strip.apply("\x1bPdata\u{9c}text"), "text");
+    }
+
+    #[test]
+    fn strip_removes_other_control_characters() {
+        assert_eq!(Sanitize::Strip.apply("a\rb\x08c\x7f"), "abc");
+        assert_eq!(
+            Sanitize::Strip.apply("keep\nnewlines\tand tabs"),
+            "keep\nnewlines\tand tabs"
+        );
+    }
+}
@@ -0,0 +1,922 @@
+//! Additional destinations for log records.
+
+use drops::{self, Cause};
+#[cfg(feature = "regex")]
+use error::Error;
+#[cfg(feature = "regex")]
+use filter::FilterHandle;
+use filter::MessageFilters;
+#[cfg(feature = "timestamps")]
+use format;
+use format::Options;
+use log::Level;
+use record::Entry;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "timestamps")]
+use style::Style;
+#[cfg(feature = "timestamps")]
+use timestamp::SinkTimestamps;
+
+static SINKS: RwLock<Vec<Arc<Registered>>> = RwLock::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+/// The number of registered [scoped sinks](with_sink), so that records only copy the scopes of their thread if there
+/// is any sink they are needed for.
+static SCOPED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "regex")]
+static NEXT_FILTER_ID: AtomicU64 = AtomicU64::new(0);
+static ERROR_HANDLER: RwLock<Option<ErrorHandler>> = RwLock::new(None);
+
+type ErrorHandler = Box<dyn Fn(SinkHandle, &io::Error) + Send + Sync>;
+
+thread_local! {
+    /// Set while records are being written to sinks on the current thread.
+    static DISPATCHING: Cell<bool> = const { Cell::new(false) };
+
+    /// The IDs of the scoped sinks attached to the current thread, innermost last.
+    static SCOPES: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+fn read() -> RwLockReadGuard<'static, Vec<Arc<Registered>>> {
+    SINKS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write() -> RwLockWriteGuard<'static, Vec<Arc<Registered>>> {
+    SINKS.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Get the registered sinks, so that they can be written to without holding the lock.
+///
+/// Writing to a sink can take a long time, such as when retrying after a failure, which must not keep sinks from
+/// being added, removed or configured in the meantime.
+fn snapshot() -> Vec<Arc<Registered>> {
+    read().clone()
+}
+
+/// Get the sink with the given ID, if it is still registered.
+fn get(id: u64) -> Option<Arc<Registered>> {
+    read().iter().find(|sink| sink.id == id).cloned()
+}
+
+/// A destination that log records are written to in addition to the console.
+///
+/// Any `Mutex` wrapping a writer implements this trait, so a file can be used as a sink directly:
+///
+/// ```no_run
+/// use std::fs::File;
+/// use std::sync::Mutex;
+///
+/// let file = File::create("app.log").unwrap();
+/// clogger::add_sink(Mutex::new(file));
+/// ```
+pub trait Sink: Send + Sync + 'static {
+    /// Write a single record.
+    ///
+    /// The record is also provided already formatted as a single line of plain text, without a trailing newline. If the
+    /// message contains newlines, the line does too; it should be written using a single call to the underlying
+    /// writer, so that it cannot be interleaved with output written by other threads.
+    fn write(&self, entry: &Entry, line: &str) -> io::Result<()>;
+
+    /// Flush any output buffered by this sink.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write + Send + 'static> Sink for Mutex<W> {
+    fn write(&self, _: &Entry, line: &str) -> io::Result<()> {
+        let mut writer = self.lock().unwrap_or_else(|e| e.into_inner());
+        writer.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
+impl Sink for Box<dyn Sink> {
+    fn write(&self, entry: &Entry, line: &str) -> io::Result<()> {
+        (**self).write(entry, line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: Sink> Sink for Arc<S> {
+    fn write(&self, entry: &Entry, line: &str) -> io::Result<()> {
+        (**self).write(entry, line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// What to do when a sink fails to write a record.
+///
+/// Regardless of the policy, logging never panics because of a failing sink. Every failure is reported to the handler
+/// set with [`set_error_handler`], if any.
+pub enum FailurePolicy {
+    /// Drop the record and keep using the sink for future records. This is the default.
+    Ignore,
+
+    /// Try writing the record again, up to the given number of additional attempts. The delay between attempts starts
+    /// at the given backoff and doubles after each failed attempt. If all attempts fail, the record is dropped.
+    ///
+    /// Unless the sink has a [queue](SinkHandle::set_queue_capacity), retries happen on the thread that logged the
+    /// record, so long delays will block that thread. Other threads and sinks are not held up.
+    Retry {
+        /// The maximum number of additional attempts.
+        attempts: u32,
+
+        /// The delay before the first retry.
+        backoff: Duration,
+    },
+
+    /// Stop writing to the sink after the first failure.
+    Disable,
+
+    /// Write the record to another sink instead.
+    Fallback(Box<dyn Sink>),
+}
+
+/// A handle to a registered sink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SinkHandle {
+    id: u64,
+}
+
+impl SinkHandle {
+    /// Set the policy for handling failures of this sink.
+    pub fn set_failure_policy(&self, policy: FailurePolicy) {
+        self.with(|sink| sink.policy = Arc::new(policy));
+    }
+
+    /// Discard records written to this sink whose rendered message matches the given regular expression.
+    ///
+    /// Returns a handle that can be used to disable or remove the filter again, or an error if the pattern is not a
+    /// valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn suppress_matching(&self, pattern: &str) -> Result<FilterHandle, Error> {
+        self.add_filter(pattern, false)
+    }
+
+    /// Only write records to this sink whose rendered message matches the given regular expression.
+    ///
+    /// Returns a handle that can be used to disable or remove the filter again, or an error if the pattern is not a
+    /// valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn show_only_matching(&self, pattern: &str) -> Result<FilterHandle, Error> {
+        self.add_filter(pattern, true)
+    }
+
+    #[cfg(feature = "regex")]
+    fn add_filter(&self, pattern: &str, include: bool) -> Result<FilterHandle, Error> {
+        let regex = Regex::new(pattern)?;
+        let id = NEXT_FILTER_ID.fetch_add(1, Ordering::SeqCst);
+
+        self.with(|sink| {
+            sink.message_filters.push(MessageFilter {
+                id,
+                enabled: true,
+                include,
+                regex,
+            });
+            sink.compile_filters();
+        });
+
+        Ok(FilterHandle::for_sink(self.id, id))
+    }
+
+    /// Write records to this sink on its own background thread, using a queue that holds up to the given number of
+    /// records, or `None` to write records on the thread that logged them. This is the default.
+    ///
+    /// This keeps a slow sink, such as one writing over the network, from stalling the console and other sinks. When
+    /// the queue is full, further records are dropped for this sink only, and counted by [`SinkHandle::dropped`].
+    /// Failures are handled by the [failure policy](SinkHandle::set_failure_policy) as usual, but on the background
+    /// thread, so retries do not block the application either. If the thread cannot be started, a warning is logged and
+    /// records are written on the thread that logged them instead.
+    ///
+    /// If the sink already has a queue, the records in it are written before this function returns, and before any
+    /// record is written using the new settings.
+    pub fn set_queue_capacity(&self, capacity: Option<usize>) {
+        let (start, started) = mpsc::channel();
+        let (queue, error) = match capacity.map(|capacity| Queue::spawn(self.id, capacity, started))
+        {
+            Some(Ok(queue)) => (Some(queue), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, None),
+        };
+
+        // Without a new queue, records are written by the threads that log them as soon as the old queue is replaced,
+        // so it is drained while it is still in place.
+        if queue.is_none() {
+            self.drain();
+        }
+
+        let mut old = None;
+        self.with(|sink| old = std::mem::replace(&mut sink.queue, queue));
+
+        // The new thread only starts writing once the old one has written everything that was queued for it, so that
+        // the sink is never written to by both at once and records stay in order.
+        if let Some(old) = old {
+            old.stop();
+        }
+        let _ = start.send(());
+
+        // The failure is logged after the settings have been changed, since logging writes to this sink as well.
+        if let Some(e) = error {
+            super::self_log(
+                Level::Warn,
+                format_args!(
+                    "failed to start background thread for sink {}: {}",
+                    self.id, e
+                ),
+            );
+        }
+    }
+
+    /// Set whether and how timestamps are included in the lines written to this sink, independently of the console.
+    ///
+    /// This makes it possible to keep the console short while files and network sinks have complete timestamps that
+    /// can be correlated across machines:
+    ///
+    /// ```no_run
+    /// use clogger::{FileSink, Precision, SinkTimestamps, TimestampFormat, Timezone};
+    ///
+    /// clogger::set_timestamps(true);
+    /// clogger::set_timestamp_format(TimestampFormat::Compact);
+    /// clogger::set_timestamp_timezone(Timezone::Local);
+    ///
+    /// let sink = clogger::add_sink(FileSink::open("app.log").unwrap());
+    /// sink.set_timestamps(SinkTimestamps::On(
+    ///     TimestampFormat::Rfc3339,
+    ///     Precision::Millis,
+    ///     Timezone::Utc,
+    /// ));
+    /// ```
+    ///
+    /// In the [JSON format](::Format::Json), the `ts` field is always written with full precision in UTC unless
+    /// timestamps are set explicitly for the sink, in which case the given precision and timezone apply to it, or it is
+    /// left out if they are turned off. Unlike timestamps in the text formats, it is written regardless of the
+    /// [metadata verbosity](::set_metadata_verbosity).
+    #[cfg(feature = "timestamps")]
+    pub fn set_timestamps(&self, timestamps: SinkTimestamps) {
+        self.with(|sink| sink.timestamps = timestamps);
+    }
+
+    /// Get the number of records in the queue of this sink that have not been written yet, if it has a queue.
+    pub fn queue_depth(&self) -> usize {
+        get(self.id)
+            .and_then(|sink| {
+                sink.settings()
+                    .queue
+                    .as_ref()
+                    .map(|queue| queue.depth.load(Ordering::SeqCst))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Get the number of records that were dropped for this sink because its queue was full.
+    pub fn dropped(&self) -> u64 {
+        get(self.id).map_or(0, |sink| sink.dropped.load(Ordering::SeqCst))
+    }
+
+    /// Check if this sink has been disabled because of a failure.
+    ///
+    /// Returns `true` if the sink has been removed.
+    pub fn is_disabled(&self) -> bool {
+        get(self.id).is_none_or(|sink| sink.disabled.load(Ordering::SeqCst))
+    }
+
+    /// Enable the sink again after it has been disabled because of a failure.
+    pub fn reenable(&self) {
+        if let Some(sink) = get(self.id) {
+            sink.disabled.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Remove the sink, flushing any output it has buffered.
+    pub fn remove(self) {
+        self.drain();
+
+        let mut sinks = write();
+
+        if let Some(index) = sinks.iter().position(|sink| sink.id == self.id) {
+            let sink = sinks.remove(index);
+            drop(sinks);
+            if sink.scoped {
+                SCOPED.fetch_sub(1, Ordering::SeqCst);
+            }
+            let _ = sink.sink.flush();
+        }
+    }
+
+    /// Wait until all records in the queue of the sink have been written, if it has one.
+    fn drain(&self) {
+        if let Some(queue) = get(self.id).and_then(|sink| {
+            sink.settings()
+                .queue
+                .as_ref()
+                .map(|queue| queue.sender.clone())
+        }) {
+            drain(&queue);
+        }
+    }
+
+    fn with(&self, f: impl FnOnce(&mut Settings)) {
+        if let Some(sink) = get(self.id) {
+            f(&mut sink.settings.write().unwrap_or_else(|e| e.into_inner()));
+        }
+    }
+}
+
+struct Registered {
+    id: u64,
+    sink: Box<dyn Sink>,
+    disabled: AtomicBool,
+    dropped: AtomicU64,
+    /// Set for sinks attached by [`with_sink`], which only receive records logged within the scope.
+    scoped: bool,
+    settings: RwLock<Settings>,
+}
+
+/// The settings of a sink that can be changed through its handle.
+struct Settings {
+    /// Shared so that a failure can be handled without holding the lock, since retrying takes a while.
+    policy: Arc<FailurePolicy>,
+    /// The message filters of the sink, compiled from those that are enabled.
+    filters: MessageFilters,
+    #[cfg(feature = "regex")]
+    message_filters: Vec<MessageFilter>,
+    queue: Option<Queue>,
+    #[cfg(feature = "timestamps")]
+    timestamps: SinkTimestamps,
+}
+
+/// A message filter of a sink, which can be changed using a [`FilterHandle`].
+#[cfg(feature = "regex")]
+struct MessageFilter {
+    id: u64,
+    enabled: bool,
+    include: bool,
+    regex: Regex,
+}
+
+/// The queue of a sink that is written to on its own background thread.
+struct Queue {
+    sender: SyncSender<Job>,
+    depth: Arc<AtomicUsize>,
+    worker: thread::JoinHandle<()>,
+}
+
+enum Job {
+    Write(Box<Entry>, String),
+    Flush(Sender<()>),
+}
+
+impl Queue {
+    /// Start a background thread for the sink with the given ID, which starts writing records once it receives a
+    /// message from the given channel, or the channel is closed.
+    fn spawn(id: u64, capacity: usize, started: Receiver<()>) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let depth = Arc::new(AtomicUsize::new(0));
+
+        let worker_depth = depth.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("clogger-sink-{}", id))
+            .spawn(move || {
+                // Records logged while writing, such as by the failure policy, are only written to the console.
+                DISPATCHING.with(|dispatching| dispatching.set(true));
+                let _ = started.recv();
+
+                for job in receiver {
+                    match job {
+                        Job::Write(entry, line) => {
+                            if let Some(sink) = get(id) {
+                                if !sink.disabled.load(Ordering::SeqCst) {
+                                    sink.write(&entry, &line);
+                                }
+                            }
+                            worker_depth.fetch_sub(1, Ordering::SeqCst);
+                        }
+                        Job::Flush(ack) => {
+                            let _ = ack.send(());
+                        }
+                    }
+                }
+            });
+
+        spawned.map(|worker| Queue {
+            sender,
+            depth,
+            worker,
+        })
+    }
+
+    /// Close the queue, and wait until the records in it have been written.
+    fn stop(self) {
+        drop(self.sender);
+
+        // A sink that changes its own queue while writing a record cannot wait for itself.
+        if self.worker.thread().id() != thread::current().id() {
+            let _ = self.worker.join();
+        }
+    }
+}
+
+/// Wait until all records in a queue have been written.
+fn drain(sender: &SyncSender<Job>) {
+    let (ack_tx, ack_rx) = mpsc::channel();
+
+    if sender.send(Job::Flush(ack_tx)).is_ok() {
+        let _ = ack_rx.recv();
+    }
+}
+
+/// Add a sink that all displayed records are written to, in addition to the console.
+///
+/// Returns a handle that can be used to configure or remove the sink.
+///
+/// This function may be called at any time.
+pub fn add_sink<S: Sink>(sink: S) -> SinkHandle {
+    register(Box::new(sink), false)
+}
+
+fn register(sink: Box<dyn Sink>, scoped: bool) -> SinkHandle {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    if scoped {
+        SCOPED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    write().push(Arc::new(Registered {
+        id,
+        sink,
+        disabled: AtomicBool::new(false),
+        dropped: AtomicU64::new(0),
+        scoped,
+        settings: RwLock::new(Settings {
+            policy: Arc::new(FailurePolicy::Ignore),
+            filters: MessageFilters::new(),
+            #[cfg(feature = "regex")]
+            message_filters: Vec::new(),
+            queue: None,
+            #[cfg(feature = "timestamps")]
+            timestamps: SinkTimestamps::Console,
+        }),
+    }));
+
+    SinkHandle { id }
+}
+
+/// Add a sink only while a function runs, removing it again once the function returns or panics.
+///
+/// This is useful for capturing the diagnostics of a single operation, such as to embed them in an error report.
+/// Since a sink can be shared using an `Arc`, its output can be read back afterwards:
+///
+/// ```
+/// # #[macro_use] extern crate log;
+/// # extern crate clogger;
+/// # fn main() {
+/// use std::sync::{Arc, Mutex};
+///
+/// let output = Arc::new(Mutex::new(Vec::new()));
+///
+/// clogger::with_sink(output.clone(), || {
+///     warn!("config file not found, using defaults");
+/// });
+///
+/// let report = String::from_utf8_lossy(&output.lock().unwrap()).into_owned();
+/// # }
+/// ```
+///
+/// The sink only receives records logged by the current thread while the function runs. Records logged by other
+/// threads in the meantime are not written to it, including threads spawned by the function, unless they run inside
+/// the [scope](sink_scope) of the function:
+///
+/// ```
+/// # #[macro_use] extern crate log;
+/// # extern crate clogger;
+/// # fn main() {
+/// # clogger::init();
+/// use std::sync::{Arc, Mutex};
+/// use std::thread;
+///
+/// let output = Arc::new(Mutex::new(Vec::new()));
+///
+/// clogger::with_sink(output.clone(), || {
+///     let scope = clogger::sink_scope();
+///
+///     thread::scope(|s| {
+///         s.spawn(|| scope.run(|| warn!("retrying download")));
+///         s.spawn(|| warn!("cache is stale"));
+///     });
+/// });
+///
+/// let report = String::from_utf8_lossy(&output.lock().unwrap()).into_owned();
+/// assert!(report.contains("retrying download"));
+/// assert!(!report.contains("cache is stale"));
+/// # }
+/// ```
+///
+/// Any records still queued for the sink are written and the sink is flushed before this function returns.
+pub fn with_sink<S: Sink, T, F: FnOnce() -> T>(sink: S, f: F) -> T {
+    struct Remove(SinkHandle, usize);
+
+    impl Drop for Remove {
+        fn drop(&mut self) {
+            SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.1));
+            self.0.remove();
+        }
+    }
+
+    let handle = register(Box::new(sink), true);
+    let _remove = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let len = scopes.len();
+        scopes.push(handle.id);
+        Remove(handle, len)
+    });
+
+    f()
+}
+
+/// The [scoped sinks](with_sink) attached to a thread, which can be attached to other threads as well, returned by
+/// [`sink_scope`].
+#[derive(Clone, Debug, Default)]
+pub struct SinkScope {
+    ids: Vec<u64>,
+}
+
+impl SinkScope {
+    /// Run a function with the scoped sinks attached to the current thread, so that they receive the records it logs
+    /// for as long as they are attached to the thread that created the scope.
+    pub fn run<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        struct Reset(usize);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.0));
+            }
+        }
+
+        let _reset = SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            let len = scopes.len();
+            scopes.extend(self.ids.iter().copied());
+            Reset(len)
+        });
+
+        f()
+    }
+}
+
+/// Get the [scoped sinks](with_sink) attached to the current thread, such as to carry them into threads spawned by
+/// the function passed to [`with_sink`] using [`SinkScope::run`].
+///
+/// The scope can be sent to other threads. It is empty outside of [`with_sink`].
+pub fn sink_scope() -> SinkScope {
+    SinkScope {
+        ids: SCOPES.with(|scopes| scopes.borrow().clone()),
+    }
+}
+
+/// Get the IDs of the scoped sinks attached to the current thread, for a record logged on it.
+///
+/// This is empty without allocating unless scoped sinks are registered.
+pub(crate) fn scopes() -> Vec<u64> {
+    if SCOPED.load(Ordering::SeqCst) == 0 {
+        return Vec::new();
+    }
+
+    SCOPES.with(|scopes| scopes.borrow().clone())
+}
+
+/// Set a function to be called whenever a sink fails to write a record.
+///
+/// The handler is called with the sink that failed and the error it returned. Any records logged by the handler itself
+/// are only written to the console. The handler must not add, remove or configure sinks.
+///
+/// This function may be called at any time.
+pub fn set_error_handler<F>(handler: F)
+where
+    F: Fn(SinkHandle, &io::Error) + Send + Sync + 'static,
+{
+    *ERROR_HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(handler));
+}
+
+/// Write an entry to all registered sinks.
+///
+/// The line is rendered again using the given options for sinks with their own timestamp settings. Records logged
+/// while already writing to sinks, such as by an error handler, are not written to sinks again.
+#[cfg_attr(not(feature = "timestamps"), allow(unused_variables))]
+pub(crate) fn dispatch(entry: &Entry, options: &Options, line: &str) {
+    if DISPATCHING.with(|dispatching| dispatching.replace(true)) {
+        return;
+    }
+
+    for sink in snapshot() {
+        if sink.disabled.load(Ordering::SeqCst)
+            || (sink.scoped && !entry.scopes().contains(&sink.id))
+        {
+            continue;
+        }
+
+        let settings = sink.settings();
+        if !settings.filters.allows(entry.message()) {
+            continue;
+        }
+
+        #[cfg(feature = "timestamps")]
+        let own_line = settings.line(entry, options);
+        #[cfg(feature = "timestamps")]
+        let line = own_line.as_deref().unwrap_or(line);
+
+        if let Some(ref queue) = settings.queue {
+            queue.depth.fetch_add(1, Ordering::SeqCst);
+
+            let job = Job::Write(Box::new(entry.clone()), line.to_owned());
+            if queue.sender.try_send(job).is_err() {
+                queue.depth.fetch_sub(1, Ordering::SeqCst);
+                sink.dropped.fetch_add(1, Ordering::SeqCst);
+                drops::count(Cause::SinkQueue);
+            }
+            continue;
+        }
+
+        drop(settings);
+        sink.write(entry, line);
+    }
+
+    DISPATCHING.with(|dispatching| dispatching.set(false));
+}
+
+/// Check if a message filter of a sink is enabled.
+#[cfg(feature = "regex")]
+pub(crate) fn filter_enabled(sink: u64, id: u64) -> bool {
+    get(sink).is_some_and(|sink| {
+        sink.settings()
+            .message_filters
+            .iter()
+            .any(|filter| filter.id == id && filter.enabled)
+    })
+}
+
+/// Enable or disable a message filter of a sink, or remove it with `None`.
+#[cfg(feature = "regex")]
+pub(crate) fn set_filter_enabled(sink: u64, id: u64, enabled: Option<bool>) {
+    SinkHandle { id: sink }.with(|sink| {
+        match enabled {
+            Some(enabled) => {
+                if let Some(filter) = sink
+                    .message_filters
+                    .iter_mut()
+                    .find(|filter| filter.id == id)
+                {
+                    filter.enabled = enabled;
+                }
+            }
+            None => sink.message_filters.retain(|filter| filter.id != id),
+        }
+        sink.compile_filters();
+    });
+}
+
+/// Flush all registered sinks, after waiting for their queues to be written.
+pub(crate) fn flush() {
+    let sinks = snapshot();
+    let queues = sinks
+        .iter()
+        .filter_map(|sink| {
+            sink.settings()
+                .queue
+                .as_ref()
+                .map(|queue| queue.sender.clone())
+        })
+        .collect::<Vec<_>>();
+
+    for queue in queues {
+        drain(&queue);
+    }
+
+    for sink in sinks {
+        if let Err(e) = sink.sink.flush() {
+            report(SinkHandle { id: sink.id }, &e);
+            super::self_log(
+                Level::Warn,
+                format_args!("sink {} failed to flush: {}", sink.id, e),
+            );
+        }
+    }
+}
+
+impl Settings {
+    /// Rebuild the message filters from those that are enabled.
+    #[cfg(feature = "regex")]
+    fn compile_filters(&mut self) {
+        self.filters = MessageFilters::new();
+
+        for filter in self.message_filters.iter().filter(|filter| filter.enabled) {
+            if filter.include {
+                self.filters.include(filter.regex.clone());
+            } else {
+                self.filters.exclude(filter.regex.clone());
+            }
+        }
+    }
+
+    /// Render an entry using the timestamp settings of the sink, if it has its own.
+    #[cfg(feature = "timestamps")]
+    fn line(&self, entry: &Entry, options: &Options) -> Option<String> {
+        if entry.is_preformatted() {
+            return None;
+        }
+
+        let mut options = options.clone();
+
+        match self.timestamps {
+            SinkTimestamps::Console => return None,
+            SinkTimestamps::Off => {
+                options.timestamp = None;
+                #[cfg(feature = "json")]
+                {
+                    options.json_timestamp = None;
+                }
+            }
+            SinkTimestamps::On(format, precision, timezone) => {
+                if options.metadata {
+                    options.timestamp = Some((format, precision, timezone));
+                }
+                #[cfg(feature = "json")]
+                {
+                    options.json_timestamp = Some((precision, timezone));
+                }
+            }
+        }
+
+        Some(format::line(entry, &options, Style::new()))
+    }
+}
+
+impl Registered {
+    fn settings(&self) -> RwLockReadGuard<'_, Settings> {
+        self.settings.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self, entry: &Entry, line: &str) {
+        if let Err(e) = try_write(&*self.sink, entry, line) {
+            self.handle_failure(entry, line, e);
+        }
+    }
+
+    fn handle_failure(&self, entry: &Entry, line: &str, error: io::Error) {
+        let handle = SinkHandle { id: self.id };
+        report(handle, &error);
+
+        let policy = self.settings().policy.clone();
+        match *policy {
+            FailurePolicy::Ignore => {
+                super::self_log(
+                    Level::Warn,
+                    format_args!("sink {} failed to write record: {}", self.id, error),
+                );
+            }
+            FailurePolicy::Retry { attempts, backoff } => {
+                let mut delay = backoff;
+
+                for _ in 0..attempts {
+                    thread::sleep(delay);
+                    delay *= 2;
+
+                    match try_write(&*self.sink, entry, line) {
+                        Ok(()) => return,
+                        Err(e) => report(handle, &e),
+                    }
+                }
+
+                super::self_log(
+                    Level::Warn,
+                    format_args!(
+                        "sink {} failed to write record after {} retries: {}",
+                        self.id, attempts, error
+                    ),
+                );
+            }
+            FailurePolicy::Disable => {
+                self.disabled.store(true, Ordering::SeqCst);
+                super::self_log(
+                    Level::Warn,
+                    format_args!("sink {} disabled after failure: {}", self.id, error),
+                );
+            }
+            FailurePolicy::Fallback(ref fallback) => {
+                super::self_log(
+                    Level::Debug,
+                    format_args!(
+                        "sink {} failed, writing record to fallback: {}",
+                        self.id, error
+                    ),
+                );
+
+                if let Err(e) = try_write(fallback, entry, line) {
+                    report(handle, &e);
+                    super::self_log(
+                        Level::Warn,
+                        format_args!(
+                            "fallback for sink {} failed to write record: {}",
+                            self.id, e
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Write to a sink, treating a panic as an error.
+fn try_write(sink: &dyn Sink, entry: &Entry, line: &str) -> io::Result<()> {
+    panic::catch_unwind(AssertUnwindSafe(|| sink.write(entry, line)))
+        .unwrap_or_else(|_| Err(io::Error::other("sink panicked")))
+}
+
+fn report(handle: SinkHandle, error: &io::Error) {
+    if let Some(ref handler) = *ERROR_HANDLER.read().unwrap_or_else(|e| e.into_inner()) {
+        handler(handle, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock;
+    use log::Record;
+
+    /// A slow sink that records the lines written to it, and whether it was ever written to by two threads at once.
+    #[derive(Default)]
+    struct Slow {
+        lines: Mutex<Vec<String>>,
+        writing: AtomicUsize,
+        overlapped: AtomicBool,
+    }
+
+    impl Sink for Arc<Slow> {
+        fn write(&self, _: &Entry, line: &str) -> io::Result<()> {
+            if self.writing.fetch_add(1, Ordering::SeqCst) > 0 {
+                self.overlapped.store(true, Ordering::SeqCst);
+            }
+            thread::sleep(Duration::from_millis(1));
+            self.lines.lock().unwrap().push(line.to_owned());
+            self.writing.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn write(line: &str) {
+        let entry = Entry::new(
+            &Record::builder().args(format_args!("{}", line)).build(),
+            line.to_owned(),
+            clock::now(),
+            Vec::new(),
+        );
+        let options = super::super::options(&entry, super::super::output_format());
+        dispatch(&entry, &options, line);
+    }
+
+    #[test]
+    fn replacing_a_queue_keeps_records_in_order() {
+        let slow = Arc::new(Slow::default());
+        let handle = add_sink(slow.clone());
+        let expected = (0..60)
+            .map(|i| format!("queued record {}", i))
+            .collect::<Vec<_>>();
+
+        handle.set_queue_capacity(Some(100));
+        for (i, line) in expected.iter().enumerate() {
+            write(line);
+            match i {
+                19 => handle.set_queue_capacity(Some(50)),
+                39 => handle.set_queue_capacity(None),
+                _ => {}
+            }
+        }
+        handle.remove();
+
+        let lines = slow.lines.lock().unwrap().clone();
+        let lines = lines
+            .into_iter()
+            .filter(|line| line.starts_with("queued record"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, expected);
+        assert!(!slow.overlapped.load(Ordering::SeqCst));
+    }
+}
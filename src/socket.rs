@@ -0,0 +1,125 @@
+//! Streaming records to other processes over local sockets.
+
+use error::Error;
+use record::Entry;
+use sink::Sink;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+
+/// How long writing a record to an attached client may block before the client is disconnected.
+#[cfg(unix)]
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A sink that streams formatted records to other processes over a Unix domain socket or Windows named pipe.
+///
+/// This allows companion tools, such as a `mytool logs` subcommand or a dashboard, to attach to a running instance and
+/// follow its output live.
+///
+/// ```no_run
+/// use clogger::SocketSink;
+///
+/// clogger::add_sink(SocketSink::bind("/run/mytool/logs.sock").unwrap());
+/// ```
+pub struct SocketSink {
+    path: PathBuf,
+    target: Target,
+}
+
+enum Target {
+    #[cfg(unix)]
+    Clients(Arc<Mutex<Vec<UnixStream>>>),
+    Stream(Mutex<Box<dyn Write + Send>>),
+}
+
+impl SocketSink {
+    /// Listen on a Unix domain socket at the given path, streaming records to every client that connects.
+    ///
+    /// Any existing file at the path is removed first. Clients only receive records logged after they connect. A
+    /// client that disconnects or does not keep up is dropped, so that a stalled client can never block logging.
+    #[cfg(unix)]
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = clients.clone();
+        thread::Builder::new()
+            .name("clogger-socket".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok() {
+                        accepted
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push(stream);
+                    }
+                }
+            })?;
+
+        Ok(SocketSink {
+            path,
+            target: Target::Clients(clients),
+        })
+    }
+
+    /// Connect to a Unix domain socket or Windows named pipe that another process is listening on, such as
+    /// `\\.\pipe\mytool-logs`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+
+        #[cfg(unix)]
+        let stream: Box<dyn Write + Send> = Box::new(UnixStream::connect(&path)?);
+        #[cfg(not(unix))]
+        let stream: Box<dyn Write + Send> =
+            Box::new(std::fs::OpenOptions::new().write(true).open(&path)?);
+
+        Ok(SocketSink {
+            path,
+            target: Target::Stream(Mutex::new(stream)),
+        })
+    }
+
+    /// Get the path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Sink for SocketSink {
+    fn write(&self, _: &Entry, line: &str) -> io::Result<()> {
+        let line = format!("{}\n", line);
+
+        match self.target {
+            #[cfg(unix)]
+            Target::Clients(ref clients) => {
+                clients
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+                Ok(())
+            }
+            Target::Stream(ref stream) => stream
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .write_all(line.as_bytes()),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.target {
+            #[cfg(unix)]
+            Target::Clients(_) => Ok(()),
+            Target::Stream(ref stream) => stream.lock().unwrap_or_else(|e| e.into_inner()).flush(),
+        }
+    }
+}
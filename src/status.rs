@@ -0,0 +1,252 @@
+//! The status of the program shown by the terminal itself, in its title and taskbar progress indicator.
+//!
+//! Terminals are updated using escape sequences written to stderr: `CSI 22 t` and `CSI 23 t` to save and restore the
+//! title, `OSC 2` to set it, and `OSC 9;4` to set the progress indicator supported by Windows Terminal and ConEmu.
+//! Terminals that do not support a sequence ignore it.
+
+use console::Stream;
+use format::Format;
+use log::Level;
+use pager;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static TITLE: AtomicBool = AtomicBool::new(false);
+static PROGRESS: AtomicBool = AtomicBool::new(false);
+
+static STATE: Mutex<State> = Mutex::new(State::new());
+
+struct State {
+    /// The IDs and names of the phases currently running, innermost last.
+    phases: Vec<(u64, String)>,
+
+    /// The ID and percentage of the most recently updated progress bar.
+    progress: Option<(u64, u64)>,
+
+    next_phase: u64,
+    errors: u64,
+
+    /// The title that was last written, if any.
+    title: Option<String>,
+
+    /// The progress sequence that was last written, if any.
+    indicator: Option<String>,
+}
+
+impl State {
+    const fn new() -> Self {
+        State {
+            phases: Vec::new(),
+            progress: None,
+            next_phase: 0,
+            errors: 0,
+            title: None,
+            indicator: None,
+        }
+    }
+
+    /// Write the title and progress indicator if they have changed since they were last written.
+    fn update(&mut self) {
+        if !active() {
+            return;
+        }
+
+        if TITLE.load(Ordering::SeqCst) {
+            let title = self.title();
+
+            if self.title.as_ref() != Some(&title) {
+                // Save the original title the first time, so that it can be restored.
+                if self.title.is_none() {
+                    Stream::Stderr.write_str("\x1b[22;0t");
+                }
+
+                Stream::Stderr.write_str(&format!("\x1b]2;{}\x07", title));
+                self.title = Some(title);
+            }
+        }
+
+        if PROGRESS.load(Ordering::SeqCst) {
+            let indicator = self.indicator();
+
+            if self.indicator != indicator {
+                if let Some(ref indicator) = indicator {
+                    Stream::Stderr.write_str(indicator);
+                } else {
+                    Stream::Stderr.write_str(REMOVE);
+                }
+                self.indicator = indicator;
+            }
+        }
+    }
+
+    /// Build the title, such as `mytool: download (40%, 2 errors)`.
+    fn title(&self) -> String {
+        let mut title = std::env::args_os()
+            .next()
+            .and_then(|arg| {
+                Path::new(&arg)
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default();
+
+        if let Some((_, ref phase)) = self.phases.last() {
+            if !title.is_empty() {
+                title.push_str(": ");
+            }
+            title.push_str(&sanitize(phase));
+        }
+
+        let mut details = Vec::new();
+        if let Some((_, percent)) = self.progress {
+            details.push(format!("{}%", percent));
+        }
+        match self.errors {
+            0 => {}
+            1 => details.push("1 error".to_owned()),
+            errors => details.push(format!("{} errors", errors)),
+        }
+
+        if !details.is_empty() {
+            if !title.is_empty() {
+                title.push(' ');
+            }
+            title.push_str(&format!("({})", details.join(", ")));
+        }
+
+        title
+    }
+
+    /// Build the progress sequence, or `None` if there is nothing to indicate.
+    ///
+    /// The indicator is shown as an error once an error has been logged, and as indeterminate while a phase is running
+    /// without a progress bar.
+    fn indicator(&self) -> Option<String> {
+        let state = if self.errors > 0 { 2 } else { 1 };
+
+        match self.progress {
+            Some((_, percent)) => Some(format!("\x1b]9;4;{};{}\x07", state, percent)),
+            None if !self.phases.is_empty() => Some("\x1b]9;4;3;0\x07".to_owned()),
+            None if self.errors > 0 => Some("\x1b]9;4;2;100\x07".to_owned()),
+            None => None,
+        }
+    }
+}
+
+/// The sequence that removes the progress indicator.
+const REMOVE: &str = "\x1b]9;4;0;0\x07";
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Check if the status should be written, which is only the case for the human-readable format on a terminal.
+fn active() -> bool {
+    (TITLE.load(Ordering::Relaxed) || PROGRESS.load(Ordering::Relaxed))
+        && super::output_format() == Format::Text
+        && !pager::pager()
+        && io::stderr().is_terminal()
+}
+
+/// Remove control characters from text included in the title, which would end the sequence early.
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// Check if the [status of the program is shown in the terminal title](set_terminal_title).
+pub fn terminal_title() -> bool {
+    TITLE.load(Ordering::SeqCst)
+}
+
+/// Show the status of the program in the terminal title, so that long runs can be followed at a glance while the
+/// terminal is in the background.
+///
+/// The title consists of the program name, the innermost running [phase](::phase!), the percentage of the most
+/// recently updated [progress bar](::progress) and the number of errors logged so far, such as
+/// `mytool: download (40%, 2 errors)`. The original title is restored when the program [finishes](::finish) or
+/// [exits](::exit), on terminals that support it.
+///
+/// The title is only changed if stderr is a terminal and records are written in the [text format](::Format::Text). The
+/// default is off.
+///
+/// This function may be called at any time.
+pub fn set_terminal_title(enabled: bool) {
+    TITLE.store(enabled, Ordering::SeqCst);
+    state().update();
+}
+
+/// Check if the [status of the program is shown in the progress indicator of the terminal](set_terminal_progress).
+pub fn terminal_progress() -> bool {
+    PROGRESS.load(Ordering::SeqCst)
+}
+
+/// Show the status of the program in the progress indicator of terminals that support the `OSC 9;4` sequence, such
+/// as Windows Terminal and ConEmu, which display it in the tab and taskbar.
+///
+/// The indicator shows the percentage of the most recently updated [progress bar](::progress), or that work is in
+/// progress while a [phase](::phase!) is running without one. Once an error has been logged, the indicator is shown as
+/// failed. It is removed when the program [finishes](::finish) or [exits](::exit).
+///
+/// The indicator is only changed if stderr is a terminal and records are written in the [text format](::Format::Text).
+/// The default is off.
+///
+/// This function may be called at any time.
+pub fn set_terminal_progress(enabled: bool) {
+    PROGRESS.store(enabled, Ordering::SeqCst);
+    state().update();
+}
+
+/// Note that a phase has started, returning an ID for it.
+pub(crate) fn enter_phase(name: &str) -> u64 {
+    let mut state = state();
+    let id = state.next_phase;
+
+    state.next_phase += 1;
+    state.phases.push((id, name.to_owned()));
+    state.update();
+    id
+}
+
+/// Note that a phase has ended.
+pub(crate) fn leave_phase(id: u64) {
+    let mut state = state();
+    state.phases.retain(|&(phase, _)| phase != id);
+    state.update();
+}
+
+/// Note the percentage of a progress bar, or that it was removed.
+pub(crate) fn set_progress(id: u64, percent: Option<u64>) {
+    let mut state = state();
+
+    match percent {
+        Some(percent) => state.progress = Some((id, percent)),
+        None if state.progress.is_some_and(|(current, _)| current == id) => state.progress = None,
+        None => return,
+    }
+
+    state.update();
+}
+
+/// Note that a record was written at the given level.
+pub(crate) fn observe(level: Level) {
+    if level == Level::Error {
+        let mut state = state();
+        state.errors += 1;
+        state.update();
+    }
+}
+
+/// Restore the title and remove the progress indicator, if they were changed.
+pub(crate) fn reset() {
+    let mut state = state();
+
+    if state.title.take().is_some() {
+        Stream::Stderr.write_str("\x1b[23;0t");
+    }
+
+    if state.indicator.take().is_some() {
+        Stream::Stderr.write_str(REMOVE);
+    }
+}
@@ -0,0 +1,483 @@
+//! Styling primitives for terminal output.
+//!
+//! These are the same primitives used by the logger to color its own output, so that custom formats can be styled
+//! consistently. Styles are only rendered as escape sequences when the `color` feature is enabled; otherwise text is
+//! always displayed plain.
+//!
+//! ```
+//! use clogger::style::{Color, Style};
+//!
+//! let style = Style::new().fg(Color::Green).bold();
+//! println!("{}", style.paint("done"));
+//! ```
+//!
+//! Values styled using [`style`](::style()) that are included in a log message are colored on the console only if
+//! colored output is enabled, and are written as plain text to sinks and other uncolored outputs.
+
+#![cfg_attr(not(feature = "color"), allow(dead_code))]
+
+#[cfg(feature = "color")]
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+
+/// Marks the start of an encoded style in a message, followed by the style and [`MARK_END`].
+const MARK_START: char = '\u{e000}';
+const MARK_END: char = '\u{e001}';
+/// Marks the end of styled text in a message.
+const MARK_RESET: char = '\u{e002}';
+
+thread_local! {
+    /// Set while a log message is being rendered on the current thread.
+    static MARKUP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A terminal color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// Black.
+    Black,
+
+    /// Red.
+    Red,
+
+    /// Green.
+    Green,
+
+    /// Yellow.
+    Yellow,
+
+    /// Blue.
+    Blue,
+
+    /// Magenta.
+    Magenta,
+
+    /// Cyan.
+    Cyan,
+
+    /// White.
+    White,
+
+    /// A color from the 256-color palette.
+    Fixed(u8),
+
+    /// A 24-bit color.
+    Rgb(u8, u8, u8),
+}
+
+/// A combination of colors and text attributes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    /// Create a plain style.
+    pub fn new() -> Self {
+        Style::default()
+    }
+
+    /// Set the foreground color.
+    pub fn fg(self, color: Color) -> Self {
+        Style {
+            fg: Some(color),
+            ..self
+        }
+    }
+
+    /// Set the background color.
+    pub fn bg(self, color: Color) -> Self {
+        Style {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    /// Make the text bold.
+    pub fn bold(self) -> Self {
+        Style { bold: true, ..self }
+    }
+
+    /// Make the text dimmed.
+    pub fn dimmed(self) -> Self {
+        Style {
+            dimmed: true,
+            ..self
+        }
+    }
+
+    /// Make the text italic.
+    pub fn italic(self) -> Self {
+        Style {
+            italic: true,
+            ..self
+        }
+    }
+
+    /// Underline the text.
+    pub fn underline(self) -> Self {
+        Style {
+            underline: true,
+            ..self
+        }
+    }
+
+    /// Check if this style has no colors or attributes.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// Wrap a value so that it is displayed using this style.
+    pub fn paint<T: fmt::Display>(self, value: T) -> Painted<T> {
+        Painted { style: self, value }
+    }
+
+    /// Encode the style as a short string of printable characters.
+    fn encode(&self, output: &mut String) {
+        use std::fmt::Write;
+
+        for color in [self.fg, self.bg] {
+            match color {
+                Some(Color::Fixed(n)) => {
+                    let _ = write!(output, "x{}", n);
+                }
+                Some(Color::Rgb(r, g, b)) => {
+                    let _ = write!(output, "#{:02x}{:02x}{:02x}", r, g, b);
+                }
+                Some(color) => output.push(match color {
+                    Color::Black => '0',
+                    Color::Red => '1',
+                    Color::Green => '2',
+                    Color::Yellow => '3',
+                    Color::Blue => '4',
+                    Color::Magenta => '5',
+                    Color::Cyan => '6',
+                    _ => '7',
+                }),
+                None => {}
+            }
+            output.push(';');
+        }
+
+        for (enabled, flag) in [
+            (self.bold, 'b'),
+            (self.dimmed, 'd'),
+            (self.italic, 'i'),
+            (self.underline, 'u'),
+        ] {
+            if enabled {
+                output.push(flag);
+            }
+        }
+    }
+
+    /// Decode a style encoded using [`Style::encode`].
+    #[cfg(feature = "color")]
+    fn decode(encoded: &str) -> Self {
+        fn color(encoded: &str) -> Option<Color> {
+            Some(match encoded {
+                "0" => Color::Black,
+                "1" => Color::Red,
+                "2" => Color::Green,
+                "3" => Color::Yellow,
+                "4" => Color::Blue,
+                "5" => Color::Magenta,
+                "6" => Color::Cyan,
+                "7" => Color::White,
+                _ if encoded.starts_with('x') => Color::Fixed(encoded[1..].parse().ok()?),
+                _ if encoded.starts_with('#') && encoded.len() == 7 => {
+                    let channel = |i: usize| u8::from_str_radix(&encoded[i..i + 2], 16).ok();
+                    Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+                }
+                _ => return None,
+            })
+        }
+
+        let mut parts = encoded.splitn(3, ';');
+        let fg = parts.next().and_then(color);
+        let bg = parts.next().and_then(color);
+        let flags = parts.next().unwrap_or("");
+
+        Style {
+            fg,
+            bg,
+            bold: flags.contains('b'),
+            dimmed: flags.contains('d'),
+            italic: flags.contains('i'),
+            underline: flags.contains('u'),
+        }
+    }
+
+    #[cfg(feature = "color")]
+    fn to_anstyle(self) -> anstyle::Style {
+        fn color(color: Color) -> anstyle::Color {
+            use anstyle::AnsiColor;
+
+            match color {
+                Color::Black => AnsiColor::Black.into(),
+                Color::Red => AnsiColor::Red.into(),
+                Color::Green => AnsiColor::Green.into(),
+                Color::Yellow => AnsiColor::Yellow.into(),
+                Color::Blue => AnsiColor::Blue.into(),
+                Color::Magenta => AnsiColor::Magenta.into(),
+                Color::Cyan => AnsiColor::Cyan.into(),
+                Color::White => AnsiColor::White.into(),
+                Color::Fixed(n) => anstyle::Ansi256Color(n).into(),
+                Color::Rgb(r, g, b) => anstyle::RgbColor(r, g, b).into(),
+            }
+        }
+
+        let mut style = anstyle::Style::new()
+            .fg_color(self.fg.map(color))
+            .bg_color(self.bg.map(color));
+
+        if self.bold {
+            style = style.bold();
+        }
+        if self.dimmed {
+            style = style.dimmed();
+        }
+        if self.italic {
+            style = style.italic();
+        }
+        if self.underline {
+            style = style.underline();
+        }
+
+        style
+    }
+}
+
+/// Style a value for display, such as in a log message.
+///
+/// ```
+/// # #[macro_use] extern crate log;
+/// # extern crate clogger;
+/// # fn main() {
+/// info!("deployed to {}", clogger::style("production").bold().red());
+/// # }
+/// ```
+pub fn style<T: fmt::Display>(value: T) -> Painted<T> {
+    Style::new().paint(value)
+}
+
+/// A value displayed using a style.
+#[derive(Clone, Copy, Debug)]
+pub struct Painted<T> {
+    #[cfg_attr(not(feature = "color"), allow(dead_code))]
+    style: Style,
+    value: T,
+}
+
+impl<T> Painted<T> {
+    /// Set the foreground color.
+    pub fn fg(self, color: Color) -> Self {
+        self.map(|style| style.fg(color))
+    }
+
+    /// Set the background color.
+    pub fn bg(self, color: Color) -> Self {
+        self.map(|style| style.bg(color))
+    }
+
+    /// Make the text bold.
+    pub fn bold(self) -> Self {
+        self.map(Style::bold)
+    }
+
+    /// Make the text dimmed.
+    pub fn dimmed(self) -> Self {
+        self.map(Style::dimmed)
+    }
+
+    /// Make the text italic.
+    pub fn italic(self) -> Self {
+        self.map(Style::italic)
+    }
+
+    /// Underline the text.
+    pub fn underline(self) -> Self {
+        self.map(Style::underline)
+    }
+
+    /// Color the text black.
+    pub fn black(self) -> Self {
+        self.fg(Color::Black)
+    }
+
+    /// Color the text red.
+    pub fn red(self) -> Self {
+        self.fg(Color::Red)
+    }
+
+    /// Color the text green.
+    pub fn green(self) -> Self {
+        self.fg(Color::Green)
+    }
+
+    /// Color the text yellow.
+    pub fn yellow(self) -> Self {
+        self.fg(Color::Yellow)
+    }
+
+    /// Color the text blue.
+    pub fn blue(self) -> Self {
+        self.fg(Color::Blue)
+    }
+
+    /// Color the text magenta.
+    pub fn magenta(self) -> Self {
+        self.fg(Color::Magenta)
+    }
+
+    /// Color the text cyan.
+    pub fn cyan(self) -> Self {
+        self.fg(Color::Cyan)
+    }
+
+    /// Color the text white.
+    pub fn white(self) -> Self {
+        self.fg(Color::White)
+    }
+
+    fn map(self, f: impl FnOnce(Style) -> Style) -> Self {
+        Painted {
+            style: f(self.style),
+            value: self.value,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    #[cfg(feature = "color")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.style.is_plain() {
+            return self.value.fmt(f);
+        }
+
+        // Inside of a log message, the style is encoded so that it can be applied or removed for each output.
+        if MARKUP.with(Cell::get) {
+            let mut mark = String::new();
+            mark.push(MARK_START);
+            self.style.encode(&mut mark);
+            mark.push(MARK_END);
+
+            return write!(f, "{}{}{}", mark, self.value, MARK_RESET);
+        }
+
+        let style = self.style.to_anstyle();
+        write!(
+            f,
+            "{}{}{}",
+            style.render(),
+            self.value,
+            style.render_reset()
+        )
+    }
+
+    #[cfg(not(feature = "color"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// Apply a style to an entire rendered line, including after any escape sequences in the line that reset the style.
+#[cfg(feature = "color")]
+pub(crate) fn paint_line(line: &str, style: Style) -> String {
+    if style.is_plain() {
+        return line.to_owned();
+    }
+
+    let style = style.to_anstyle();
+    let start = style.render().to_string();
+    let reset = style.render_reset().to_string();
+
+    format!(
+        "{}{}{}",
+        start,
+        line.replace(&reset, &format!("{}{}", reset, start)),
+        reset
+    )
+}
+
+#[cfg(not(feature = "color"))]
+pub(crate) fn paint_line(line: &str, _: Style) -> String {
+    line.to_owned()
+}
+
+/// Render a log message, encoding any styled values in it as markup.
+pub(crate) fn with_markup(args: &fmt::Arguments) -> String {
+    let previous = MARKUP.with(|markup| markup.replace(true));
+    let message = args.to_string();
+    MARKUP.with(|markup| markup.set(previous));
+    message
+}
+
+/// Remove markup from a message, returning the plain message, or `None` if the message does not contain any markup.
+pub(crate) fn strip_markup(message: &str) -> Option<String> {
+    if !message.contains(MARK_START) {
+        return None;
+    }
+
+    let mut output = String::with_capacity(message.len());
+    let mut in_mark = false;
+
+    for c in message.chars() {
+        match c {
+            MARK_START => in_mark = true,
+            MARK_END => in_mark = false,
+            MARK_RESET => {}
+            _ if !in_mark => output.push(c),
+            _ => {}
+        }
+    }
+
+    Some(output)
+}
+
+/// Replace markup in a message with escape sequences.
+#[cfg(feature = "color")]
+pub(crate) fn render_markup(message: &str) -> Cow<'_, str> {
+    if !message.contains(MARK_START) {
+        return Cow::Borrowed(message);
+    }
+
+    let mut output = String::with_capacity(message.len() + 16);
+    let mut stack = Vec::new();
+    let mut rest = message;
+
+    while let Some(index) = rest.find([MARK_START, MARK_RESET]) {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if rest.starts_with(MARK_RESET) {
+            rest = &rest[MARK_RESET.len_utf8()..];
+
+            if let Some(style) = stack.pop() {
+                output.push_str(&anstyle::Style::render_reset(style).to_string());
+            }
+
+            // Restore the style of any enclosing styled text.
+            if let Some(&style) = stack.last() {
+                output.push_str(&anstyle::Style::render(style).to_string());
+            }
+        } else {
+            rest = &rest[MARK_START.len_utf8()..];
+            let end = rest.find(MARK_END).unwrap_or(rest.len());
+            let style = Style::decode(&rest[..end]).to_anstyle();
+            rest = rest[end..].strip_prefix(MARK_END).unwrap_or("");
+
+            output.push_str(&style.render().to_string());
+            stack.push(style);
+        }
+    }
+
+    output.push_str(rest);
+    Cow::Owned(output)
+}
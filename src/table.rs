@@ -0,0 +1,116 @@
+//! Aligned tabular output.
+
+use log::{log, log_enabled, Level};
+use std::fmt;
+use width::{self, Align};
+
+/// A batch of rows that are logged with their columns aligned.
+///
+/// Column widths are computed across all rows in the table, so every row should be added before the table is logged.
+/// Each row is logged as a separate record, so the table is subject to the same filtering as any other record.
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use clogger::Table;
+/// use log::Level;
+///
+/// Table::new()
+///     .header(["NAME", "SIZE", "STATUS"])
+///     .row(["foo.txt", "120", "ok"])
+///     .row(["bar.bin", "48000", "failed"])
+///     .right_align(1)
+///     .log(module_path!(), Level::Info);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    right_aligned: Vec<usize>,
+}
+
+impl Table {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    /// Set the header row, which is logged before all other rows.
+    pub fn header<I>(mut self, cells: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: fmt::Display,
+    {
+        self.header = Some(cells.into_iter().map(|cell| cell.to_string()).collect());
+        self
+    }
+
+    /// Add a row.
+    pub fn row<I>(mut self, cells: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: fmt::Display,
+    {
+        self.rows
+            .push(cells.into_iter().map(|cell| cell.to_string()).collect());
+        self
+    }
+
+    /// Align the cells in the given column, starting from zero, to the right. Columns are aligned to the left by
+    /// default.
+    pub fn right_align(mut self, column: usize) -> Self {
+        self.right_aligned.push(column);
+        self
+    }
+
+    /// Log every row of the table with the given target and level.
+    pub fn log(&self, target: &str, level: Level) {
+        if !log_enabled!(target: target, level) {
+            return;
+        }
+
+        for line in self.lines() {
+            log!(target: target, level, "{}", line);
+        }
+    }
+
+    /// Render every row of the table as a line.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let rows = self.header.iter().chain(&self.rows);
+        let mut widths = Vec::new();
+
+        for row in rows.clone() {
+            if widths.len() < row.len() {
+                widths.resize(row.len(), 0);
+            }
+
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(width::width(cell));
+            }
+        }
+
+        rows.map(|row| self.render(row, &widths)).collect()
+    }
+
+    fn render(&self, row: &[String], widths: &[usize]) -> String {
+        let mut line = String::new();
+
+        for (column, (cell, &width)) in row.iter().zip(widths).enumerate() {
+            if column > 0 {
+                line.push_str("  ");
+            }
+
+            let align = if self.right_aligned.contains(&column) {
+                Align::Right
+            } else {
+                Align::Left
+            };
+            line.push_str(&width::pad(cell, align, width));
+        }
+
+        line.truncate(line.trim_end().len());
+        line
+    }
+}
@@ -0,0 +1,91 @@
+//! Category tags attached to records.
+//!
+//! A record is tagged either by a `tag` key-value pair, whose value may list several tags separated by commas, or by
+//! logging it inside [`tagged!`]. Tags are filtered and colored independently of levels and targets.
+
+use std::cell::RefCell;
+
+/// The key of the key-value pair that tags are read from.
+pub(crate) const KEY: &str = "tag";
+
+thread_local! {
+    static CURRENT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Tag all records logged by an expression with one or more categories, such as `perf`, `security` or `net`.
+///
+/// The tags are added to the `tag` key-value pair of each record, which is also how records can be tagged directly
+/// with the `kv` feature:
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # #[macro_use] extern crate log;
+/// # fn main() {
+/// // Displayed as "info: cache warmed in 12ms tag=perf".
+/// tagged!("perf" => info!("cache warmed in {}ms", 12));
+///
+/// tagged!("net", "security" => {
+///     warn!("certificate expires in 3 days");
+///     debug!("handshake complete");
+/// });
+///
+/// // The same as the first record.
+/// info!(tag = "perf"; "cache warmed in {}ms", 12);
+/// # }
+/// ```
+///
+/// Tagged records can be filtered using [`show_only_tag`](::show_only_tag) and [`suppress_tag`](::suppress_tag), and
+/// colored using [`set_tag_color`](::set_tag_color). The value of the expression is returned.
+#[macro_export]
+macro_rules! tagged {
+    ($($tag:expr),+ => $body:expr) => {
+        $crate::__tagged(&[$($tag),+], || $body)
+    };
+}
+
+#[doc(hidden)]
+pub fn __tagged<T>(tags: &[&str], f: impl FnOnce() -> T) -> T {
+    struct Reset(usize);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT.with(|current| current.borrow_mut().truncate(self.0));
+        }
+    }
+
+    let _reset = CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let len = current.len();
+        current.extend(tags.iter().map(|tag| (*tag).to_owned()));
+        Reset(len)
+    });
+
+    f()
+}
+
+/// Collect the tags of a record being logged on the current thread, adding those set by [`tagged!`] to the `tag`
+/// field so that they are displayed along with the other fields.
+pub(crate) fn collect(fields: &mut Vec<(String, String)>) -> Vec<String> {
+    let scoped = CURRENT.with(|current| current.borrow().clone());
+
+    if !scoped.is_empty() {
+        match fields.iter_mut().find(|(key, _)| key == KEY) {
+            Some((_, value)) => {
+                for tag in &scoped {
+                    value.push(',');
+                    value.push_str(tag);
+                }
+            }
+            None => fields.push((KEY.to_owned(), scoped.join(","))),
+        }
+    }
+
+    fields
+        .iter()
+        .filter(|(key, _)| key == KEY)
+        .flat_map(|(_, value)| value.split(','))
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
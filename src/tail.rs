@@ -0,0 +1,301 @@
+//! Reading log files written by a [`FileSink`](::FileSink) back to the console.
+
+use console::Stream;
+use format::{self, Format};
+use log::Level;
+use palette;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use style::{self, Style};
+use translation;
+
+/// How often a followed file is checked for new records.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+/// Write the records in a log file written by a [`FileSink`](::FileSink) to standard output, styled the same way as
+/// records written to the console.
+///
+/// Files rotated from the given file are read first, oldest first, including compressed files if the corresponding
+/// feature is enabled. If `follow` is true, the file is then watched for new records, like `tail -f`, and read again
+/// from the start once it is rotated. In that case this function only returns if reading fails.
+///
+/// This makes it easy for a tool to offer a subcommand that shows its own logs:
+///
+/// ```no_run
+/// let dir = clogger::log_dir("mytool").unwrap();
+///
+/// if let Err(e) = clogger::tail(dir.join("mytool.log"), true) {
+///     eprintln!("failed to read logs: {}", e);
+/// }
+/// ```
+///
+/// Each line is written as it appears in the file, with the level name colored using the current
+/// [palette](::set_palette) and, if enabled, the [whole line colored](::set_whole_line_color) for warnings and errors.
+/// Lines in the [JSON](::Format::Json) and [machine-readable](::set_machine_mode) formats are colored by their level
+/// as a whole.
+pub fn tail<P: AsRef<Path>>(path: P, follow: bool) -> io::Result<()> {
+    let path = path.as_ref();
+    let colored = super::colored(Stream::Stdout, Format::Text);
+
+    for rotated in rotated(path)? {
+        match open(&rotated) {
+            Ok(Some(reader)) => {
+                for line in BufReader::new(reader).lines() {
+                    write(&line?, colored);
+                }
+            }
+            Ok(None) => super::self_log(
+                Level::Warn,
+                format_args!(
+                    "skipping compressed log file {} because decompression is not enabled",
+                    rotated.display()
+                ),
+            ),
+            Err(e) => super::self_log(
+                Level::Warn,
+                format_args!("failed to read log file {}: {}", rotated.display(), e),
+            ),
+        }
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+
+    loop {
+        // A line is only complete once its terminator has been written.
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                break;
+            }
+
+            write(line.trim_end_matches(['\n', '\r']), colored);
+            line.clear();
+        }
+
+        if !follow {
+            if !line.is_empty() {
+                write(&line, colored);
+            }
+            return Ok(());
+        }
+
+        Stream::Stdout.flush();
+        thread::sleep(POLL_INTERVAL);
+
+        if rotated_since(path, reader.get_mut())? {
+            if !line.is_empty() {
+                write(&line, colored);
+                line.clear();
+            }
+            reader = BufReader::new(File::open(path)?);
+        }
+    }
+}
+
+/// Check if a followed file has been replaced or truncated since it was opened.
+fn rotated_since(path: &Path, file: &mut File) -> io::Result<bool> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        // Briefly missing while it is being rotated.
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    #[cfg(unix)]
+    {
+        let current = file.metadata()?;
+        if metadata.dev() != current.dev() || metadata.ino() != current.ino() {
+            return Ok(true);
+        }
+    }
+
+    Ok(metadata.len() < file.stream_position()?)
+}
+
+/// Get the files rotated from a log file, oldest first.
+fn rotated(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.starts_with(&prefix) => name.to_owned(),
+            _ => continue,
+        };
+
+        // Rotated files are named after the time they were rotated, so they sort in order once any compression
+        // extension is removed.
+        let key = name
+            .trim_end_matches(".gz")
+            .trim_end_matches(".zst")
+            .to_owned();
+        files.push((key, path));
+    }
+
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Open a rotated file, decompressing it if needed, or return `None` if it is compressed using a method that is not
+/// enabled.
+fn open(path: &Path) -> io::Result<Option<Box<dyn Read>>> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    match extension {
+        #[cfg(feature = "gzip")]
+        Some("gz") => Ok(Some(Box::new(flate2::read::GzDecoder::new(File::open(
+            path,
+        )?)))),
+        #[cfg(not(feature = "gzip"))]
+        Some("gz") => Ok(None),
+        #[cfg(feature = "zstd")]
+        Some("zst") => Ok(Some(Box::new(zstd::Decoder::new(File::open(path)?)?))),
+        #[cfg(not(feature = "zstd"))]
+        Some("zst") => Ok(None),
+        _ => Ok(Some(Box::new(File::open(path)?))),
+    }
+}
+
+/// Write a line of a log file to standard output, styled by its level.
+fn write(line: &str, colored: bool) {
+    match level(line) {
+        Some((level, start, end)) if colored => {
+            let style = Style::new().fg(palette::palette().level(level));
+
+            if level <= Level::Warn && super::whole_line_color() || start == end {
+                Stream::Stdout.write_line(&style::paint_line(line, style));
+            } else {
+                Stream::Stdout.write_line(&format!(
+                    "{}{}{}",
+                    &line[..start],
+                    style.paint(&line[start..end]),
+                    &line[end..]
+                ));
+            }
+        }
+        _ => Stream::Stdout.write_line(line),
+    }
+}
+
+/// Find the level of a line, along with the range of the level name to style, which is empty if the whole line
+/// should be styled instead.
+fn level(line: &str) -> Option<(Level, usize, usize)> {
+    if line.trim_start().starts_with('{') {
+        let start = line.find("\"level\":\"")? + 9;
+        let name = &line[start..start + line[start..].find('"')?];
+        let level = LEVELS
+            .iter()
+            .copied()
+            .find(|&level| format::level_name(level) == name)?;
+
+        return Some((level, 0, 0));
+    }
+
+    // The machine-readable format starts with the level, followed by a tab.
+    if let Some((name, _)) = line.split_once('\t') {
+        if let Some(level) = LEVELS
+            .iter()
+            .copied()
+            .find(|&level| format::level_name(level) == name)
+        {
+            return Some((level, 0, 0));
+        }
+    }
+
+    // Otherwise, the level is the earliest level name followed by a colon, after any timestamp and timing prefix.
+    let translation = translation::current();
+
+    LEVELS
+        .iter()
+        .copied()
+        .filter_map(|level| {
+            let name = translation.level_name(level);
+            let mut offset = 0;
+
+            while let Some(found) = line[offset..].find(name) {
+                let start = offset + found;
+                let end = start + name.len();
+
+                // The name may be followed by the code of the record, such as `error[E0042]:`.
+                let label = match line[end..]
+                    .strip_prefix('[')
+                    .and_then(|rest| rest.find("]:"))
+                {
+                    Some(close) => end + close + 2,
+                    None => end,
+                };
+
+                if line[label..].starts_with(':') && (start == 0 || line[..start].ends_with(' ')) {
+                    return Some((level, start, label));
+                }
+
+                offset = end;
+            }
+
+            None
+        })
+        .min_by_key(|&(_, start, _)| start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_of_text_line() {
+        assert_eq!(level("warn: disk almost full"), Some((Level::Warn, 0, 4)));
+        assert_eq!(
+            level("2024-05-01T12:00:00Z error: failed"),
+            Some((Level::Error, 21, 26))
+        );
+    }
+
+    #[test]
+    fn level_of_text_line_with_code() {
+        let line = "error[E0042]: config file not found";
+        assert_eq!(level(line), Some((Level::Error, 0, 12)));
+        assert_eq!(&line[0..12], "error[E0042]");
+    }
+
+    #[test]
+    fn level_ignores_names_inside_words_and_messages() {
+        assert_eq!(level("mywarn: not a level"), None);
+        assert_eq!(
+            level("info: the warn: label is ignored"),
+            Some((Level::Info, 0, 4))
+        );
+        assert_eq!(level("error[unclosed: text"), None);
+    }
+
+    #[test]
+    fn level_of_structured_lines() {
+        assert_eq!(
+            level(r#"{"schema":1,"level":"debug","target":"x","msg":"m"}"#),
+            Some((Level::Debug, 0, 0))
+        );
+        assert_eq!(level("trace\tmyapp\tmessage"), Some((Level::Trace, 0, 0)));
+        assert_eq!(level(r#"{"level":"loud"}"#), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Shortening of displayed targets.
+
+use filter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use width;
+
+static TRIM_PREFIX: RwLock<Option<String>> = RwLock::new(None);
+
+/// The maximum number of columns of a displayed target, or 0 if unlimited.
+static MAX_WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Get the name to display for a target, after applying aliases, prefix trimming and the maximum width.
+pub(crate) fn display(target: &str) -> String {
+    let filters = filter::read();
+    let target = filters.aliases().display(target);
+
+    let trimmed = match *TRIM_PREFIX.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref prefix) => target
+            .strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.strip_prefix("::"))
+            .unwrap_or(&target)
+            .to_owned(),
+        None => target.into_owned(),
+    };
+
+    match MAX_WIDTH.load(Ordering::SeqCst) {
+        0 => trimmed,
+        width => ellipsize(trimmed, width),
+    }
+}
+
+/// Shorten a string to the given number of columns by replacing its middle with an ellipsis, so that both the crate
+/// and the innermost module remain visible.
+fn ellipsize(s: String, columns: usize) -> String {
+    if width::width(&s) <= columns {
+        return s;
+    }
+
+    let kept = columns.saturating_sub(1);
+    let head = width::truncate(&s, kept / 2);
+    let tail = width::truncate_start(&s[head.len()..], kept - width::width(head));
+
+    format!("{}…{}", head, tail)
+}
+
+/// Get the prefix that is removed from displayed targets, if any.
+pub fn target_trim_prefix() -> Option<String> {
+    TRIM_PREFIX
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Remove a common prefix from displayed targets, such as the name of the application's crate, or `None` to display
+/// targets in full.
+///
+/// A target is only trimmed if the prefix is one of its parent modules, so with the prefix `myapp`, `myapp::sub::mod`
+/// is displayed as `sub::mod`, while `myapp` itself and `myapplication` are displayed unchanged. The prefix is removed
+/// after any [aliases](::set_target_alias) are applied, and only affects the `{target}` placeholder of a
+/// [line format](::set_line_format).
+///
+/// This function may be called at any time.
+pub fn set_target_trim_prefix(prefix: Option<&str>) {
+    *TRIM_PREFIX.write().unwrap_or_else(|e| e.into_inner()) = prefix.map(ToOwned::to_owned);
+}
+
+/// Get the maximum number of columns of displayed targets, if limited.
+pub fn target_max_width() -> Option<usize> {
+    match MAX_WIDTH.load(Ordering::SeqCst) {
+        0 => None,
+        width => Some(width),
+    }
+}
+
+/// Shorten displayed targets that are wider than the given number of columns, or `None` to display targets in
+/// full.
+///
+/// Long targets are shortened by replacing their middle with an ellipsis, so that `myapp::backend::postgres::pool` is
+/// displayed as `myapp::ba…gres::pool` at a width of 20. This keeps padded line formats such as `{target:<20}` aligned
+/// on narrow terminals. Like [prefix trimming](set_target_trim_prefix), this only affects the `{target}` placeholder
+/// of a [line format](::set_line_format).
+///
+/// ```
+/// clogger::set_target_trim_prefix(Some("myapp"));
+/// clogger::set_target_max_width(Some(20));
+/// clogger::set_line_format(Some("{level:>5} {target:<20} {msg}")).unwrap();
+/// ```
+///
+/// This function may be called at any time.
+pub fn set_target_max_width(width: Option<usize>) {
+    MAX_WIDTH.store(width.unwrap_or(0), Ordering::SeqCst);
+}
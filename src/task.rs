@@ -0,0 +1,92 @@
+//! Key-value pairs attached to asynchronous tasks.
+//!
+//! Async runtimes move tasks between worker threads, so thread-local state is not a reliable way to attach context to
+//! the records a task logs. Instead, the fields of a task are made current on whichever thread polls it, for exactly
+//! as long as it is being polled.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+thread_local! {
+    static CURRENT: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A future with key-value pairs that are attached to every record it logs, returned by [`with_task_context`].
+#[must_use = "futures do nothing unless polled"]
+pub struct TaskContext<F> {
+    future: F,
+    fields: Vec<(String, String)>,
+}
+
+impl<F: Future> Future for TaskContext<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        struct Reset(usize);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT.with(|current| current.borrow_mut().truncate(self.0));
+            }
+        }
+
+        // The future is never moved out of the pinned wrapper, and the fields are not pinned.
+        let TaskContext { future, fields } = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(future) };
+
+        let _reset = CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            let len = current.len();
+            current.extend(fields.iter().cloned());
+            Reset(len)
+        });
+
+        future.poll(cx)
+    }
+}
+
+/// Attach key-value pairs to every record logged by a future, regardless of which thread of an async runtime, such as
+/// tokio, happens to poll it.
+///
+/// The pairs are added to the [fields](::Entry::fields) of each record after the record's own key-value pairs, and so
+/// are displayed along with them. Futures can be nested, in which case the pairs of the outer future come first. Since
+/// a spawned task does not inherit the context of the task that spawned it, each task should be wrapped separately:
+///
+/// ```edition2021
+/// # #[macro_use] extern crate log;
+/// # extern crate clogger;
+/// # fn main() {
+/// async fn download(url: &str) {
+///     // Displayed as "info: connecting job=3 url=https://example.com".
+///     info!("connecting");
+/// }
+///
+/// let url = "https://example.com";
+/// let task = clogger::with_task_context([("job", "3"), ("url", url)], download(url));
+/// // tokio::spawn(task);
+/// # drop(task);
+/// # }
+/// ```
+pub fn with_task_context<F, I, K, V>(fields: I, future: F) -> TaskContext<F>
+where
+    F: Future,
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: fmt::Display,
+{
+    TaskContext {
+        future,
+        fields: fields
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.to_string()))
+            .collect(),
+    }
+}
+
+/// Add the fields of the task being polled on the current thread to the fields of a record, if any.
+pub(crate) fn extend(fields: &mut Vec<(String, String)>) {
+    CURRENT.with(|current| fields.extend(current.borrow().iter().cloned()));
+}
@@ -0,0 +1,504 @@
+//! Assertions about logged records, for testing the logging behavior of an application.
+//!
+//! Start a [`Capture`] at the beginning of a test, and then use [`assert_logged!`](::assert_logged),
+//! [`assert_not_logged!`](::assert_not_logged) and [`assert_logged_in_order!`](::assert_logged_in_order) to check
+//! which records were written while it was active.
+//!
+//! ```
+//! #[macro_use] extern crate clogger;
+//! #[macro_use] extern crate log;
+//!
+//! use clogger::test::Capture;
+//! use log::Level;
+//!
+//! # fn main() {
+//! let _capture = Capture::start();
+//!
+//! warn!("disk full");
+//! error!("failed to save");
+//!
+//! assert_logged!(Level::Warn, contains "disk full");
+//! assert_not_logged!(Level::Info);
+//! assert_logged_in_order!(
+//!     Level::Warn, contains "disk";
+//!     Level::Error, eq "failed to save";
+//! );
+//! # }
+//! ```
+//!
+//! An [`OutputGuard`] can additionally be used to detect output that bypasses the logger entirely.
+//!
+//! Only records that are actually written are captured, so records that are hidden by the current verbosity or
+//! filters cannot be asserted on. Since the configuration of the logger is global, tests that change it may still
+//! affect each other. Messages are matched against the record message without styling.
+
+#[cfg(unix)]
+use console::{self, Stream};
+use log::Level;
+#[cfg(feature = "async")]
+use queue;
+use record::Entry;
+use std::fmt;
+#[cfg(unix)]
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+/// Held by the active capture of all threads, so that such captures do not see each other's records.
+static ALL_THREADS: Mutex<()> = Mutex::new(());
+
+/// Held by the active output guard.
+#[cfg(unix)]
+static OUTPUT: Mutex<()> = Mutex::new(());
+
+/// The number of active captures, so that records are only checked against captures when there are any.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// The ID of the next capture.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The records captured by each active capture, in the order they were started.
+static CAPTURES: Mutex<Vec<Captured>> = Mutex::new(Vec::new());
+
+/// The ID of a capture, the thread it captures or `None` for all threads, and the records it captured so far.
+type Captured = (u64, Option<ThreadId>, Vec<Entry>);
+
+/// A guard that captures written records until it is dropped.
+pub struct Capture {
+    id: u64,
+    _lock: Option<MutexGuard<'static, ()>>,
+}
+
+impl Capture {
+    /// Start capturing records logged by the current thread, installing the logger if it has not been installed yet.
+    ///
+    /// Since each test run by `cargo test` runs on its own thread, tests running in parallel each capture only their
+    /// own records. Records logged by other threads, including threads spawned by the test, are not captured; use
+    /// [`Capture::all_threads`] to capture those.
+    ///
+    /// Captures of the same thread can be nested, in which case records are only captured by the innermost one until
+    /// it is dropped.
+    pub fn start() -> Self {
+        Capture::new(Some(thread::current().id()), None)
+    }
+
+    /// Start capturing records logged by any thread, installing the logger if it has not been installed yet.
+    ///
+    /// Only one capture of all threads may be active at a time. If another one is active, such as in a test running in
+    /// parallel, this blocks until it is dropped.
+    pub fn all_threads() -> Self {
+        let lock = ALL_THREADS.lock().unwrap_or_else(|e| e.into_inner());
+        Capture::new(None, Some(lock))
+    }
+
+    fn new(thread: Option<ThreadId>, lock: Option<MutexGuard<'static, ()>>) -> Self {
+        let _ = super::try_init();
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut captures = CAPTURES.lock().unwrap_or_else(|e| e.into_inner());
+        captures.push((id, thread, Vec::new()));
+        ACTIVE.store(captures.len(), Ordering::SeqCst);
+
+        Capture { id, _lock: lock }
+    }
+
+    /// Get the records captured so far.
+    pub fn records(&self) -> Vec<Entry> {
+        #[cfg(feature = "async")]
+        queue::flush();
+        CAPTURES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|&&(id, _, _)| id == self.id)
+            .map(|(_, _, records)| records.clone())
+            .unwrap_or_default()
+    }
+
+    /// Discard the records captured so far.
+    pub fn clear(&self) {
+        #[cfg(feature = "async")]
+        queue::flush();
+        for (id, _, records) in CAPTURES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter_mut()
+        {
+            if *id == self.id {
+                records.clear();
+            }
+        }
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        #[cfg(feature = "async")]
+        queue::flush();
+
+        let mut captures = CAPTURES.lock().unwrap_or_else(|e| e.into_inner());
+        captures.retain(|&(id, _, _)| id != self.id);
+        ACTIVE.store(captures.len(), Ordering::SeqCst);
+    }
+}
+
+/// Get the records captured so far by the innermost active capture of the current thread, or the innermost active
+/// capture of all threads if the current thread has none.
+pub fn records() -> Vec<Entry> {
+    #[cfg(feature = "async")]
+    queue::flush();
+
+    let captures = CAPTURES.lock().unwrap_or_else(|e| e.into_inner());
+    let current = Some(thread::current().id());
+
+    captures
+        .iter()
+        .rfind(|&&(_, t, _)| t == current)
+        .or_else(|| captures.iter().rfind(|&&(_, t, _)| t.is_none()))
+        .map(|(_, _, records)| records.clone())
+        .unwrap_or_default()
+}
+
+/// Add a written entry to the innermost active capture of the thread that logged it, and to that of all threads.
+pub(crate) fn record(entry: &Entry) {
+    if ACTIVE.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+
+    let mut captures = CAPTURES.lock().unwrap_or_else(|e| e.into_inner());
+
+    for thread in [Some(entry.thread_id()), None].iter() {
+        if let Some((_, _, records)) = captures.iter_mut().rfind(|(_, t, _)| t == thread) {
+            records.push(entry.clone());
+        }
+    }
+}
+
+/// A guard that detects output written directly to stdout or stderr, bypassing the logger, until it is dropped.
+///
+/// While the guard is active, the standard output and error streams of the process are redirected into pipes, and the
+/// logger keeps writing records and [printed lines](::println) to the original streams. Anything else that reaches the
+/// pipes, such as writes using `std::io::stdout()` or by a child process inheriting the streams, did not go through
+/// the logger. When the guard is dropped, the streams are restored and it panics with the stray output, if there was
+/// any. This helps to enforce that all output of an application goes through a single pathway.
+///
+/// ```no_run
+/// use clogger::test::OutputGuard;
+/// use std::io::Write;
+///
+/// let _guard = OutputGuard::start();
+///
+/// clogger::println!("42 files copied");
+///
+/// // Causes the guard to panic when it is dropped.
+/// std::io::stdout().write_all(b"done\n").unwrap();
+/// ```
+///
+/// The test harness captures the output of the `print!` family of macros of the standard library by itself, so such
+/// output is only detected when tests are run using `--nocapture`. Since the standard streams are shared by the whole
+/// process, only one guard may be active at a time. If another one is active, such as in a test running in parallel,
+/// this blocks until it is dropped.
+#[cfg(unix)]
+pub struct OutputGuard {
+    readers: Option<[thread::JoinHandle<Vec<u8>>; 2]>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+#[cfg(unix)]
+impl OutputGuard {
+    /// Start redirecting the standard streams, installing the logger if it has not been installed yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the streams could not be redirected.
+    pub fn start() -> Self {
+        let lock = OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = super::try_init();
+
+        #[cfg(feature = "async")]
+        queue::flush();
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+
+        let stdout = redirect(Stream::Stdout).expect("failed to redirect stdout");
+        let stderr = redirect(Stream::Stderr).expect("failed to redirect stderr");
+
+        OutputGuard {
+            readers: Some([stdout, stderr]),
+            _lock: lock,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for OutputGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "async")]
+        queue::flush();
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+
+        // Restoring the streams closes the write ends of the pipes, which ends the threads reading them.
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            if let Some(original) = console::set_original(stream, None) {
+                console::restore(stream.fd(), &original);
+            }
+        }
+
+        let mut description = String::new();
+        for (name, reader) in ["stdout", "stderr"]
+            .iter()
+            .zip(self.readers.take().into_iter().flatten())
+        {
+            let output = reader.join().unwrap_or_default();
+
+            if !output.is_empty() {
+                description.push_str(&format!("\n  written to {}:", name));
+                for line in String::from_utf8_lossy(&output).lines() {
+                    description.push_str(&format!("\n    {}", line));
+                }
+            }
+        }
+
+        if !description.is_empty() && !thread::panicking() {
+            panic!(
+                "expected all output to go through the logger;{}",
+                description
+            );
+        }
+    }
+}
+
+/// Redirect a standard stream into a pipe, returning a thread that reads everything written to the pipe until the
+/// stream is restored.
+#[cfg(unix)]
+fn redirect(stream: Stream) -> io::Result<thread::JoinHandle<Vec<u8>>> {
+    let (original, mut reader) = console::redirect(stream.fd())?;
+    console::set_original(stream, Some(original));
+
+    thread::Builder::new()
+        .name("clogger-output".into())
+        .spawn(move || {
+            let mut output = Vec::new();
+            let _ = reader.read_to_end(&mut output);
+            output
+        })
+}
+
+/// A pattern that the message of a record is matched against.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub enum Pattern<'a> {
+    Any,
+    Contains(&'a str),
+    Equals(&'a str),
+}
+
+impl<'a> Pattern<'a> {
+    fn matches(&self, level: Level, entry: &Entry) -> bool {
+        entry.level() == level
+            && match *self {
+                Pattern::Any => true,
+                Pattern::Contains(text) => entry.message().contains(text),
+                Pattern::Equals(text) => entry.message() == text,
+            }
+    }
+}
+
+impl<'a> fmt::Display for Pattern<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pattern::Any => Ok(()),
+            Pattern::Contains(text) => write!(f, " containing {:?}", text),
+            Pattern::Equals(text) => write!(f, " equal to {:?}", text),
+        }
+    }
+}
+
+/// List captured records for a failure message.
+fn describe(records: &[Entry]) -> String {
+    if records.is_empty() {
+        return "no records were captured".to_owned();
+    }
+
+    let mut description = String::from("captured records:");
+    for entry in records {
+        description.push_str(&format!(
+            "\n    {}: {}",
+            entry.level().as_str().to_lowercase(),
+            entry.message()
+        ));
+    }
+    description
+}
+
+#[doc(hidden)]
+pub fn __assert_logged(level: Level, pattern: Pattern, expected: bool) {
+    let records = records();
+
+    if records.iter().any(|entry| pattern.matches(level, entry)) != expected {
+        panic!(
+            "expected {} record at level {}{} to be logged; {}",
+            if expected { "a" } else { "no" },
+            level.as_str().to_lowercase(),
+            pattern,
+            describe(&records)
+        );
+    }
+}
+
+#[doc(hidden)]
+pub fn __assert_logged_in_order(expected: &[(Level, Pattern)]) {
+    let records = records();
+    let mut remaining = records.iter();
+
+    for (i, &(level, pattern)) in expected.iter().enumerate() {
+        if !remaining.any(|entry| pattern.matches(level, entry)) {
+            panic!(
+                "expected a record at level {}{} to be logged after {} matching record{}; {}",
+                level.as_str().to_lowercase(),
+                pattern,
+                i,
+                if i == 1 { "" } else { "s" },
+                describe(&records)
+            );
+        }
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __clogger_pattern {
+    () => {
+        $crate::test::Pattern::Any
+    };
+    (contains $text:expr) => {
+        $crate::test::Pattern::Contains(::std::convert::AsRef::<str>::as_ref(&$text))
+    };
+    (eq $text:expr) => {
+        $crate::test::Pattern::Equals(::std::convert::AsRef::<str>::as_ref(&$text))
+    };
+}
+
+/// Assert that a record with the given level was captured, optionally with a message that `contains` or is `eq` to
+/// the given text.
+///
+/// ```
+/// # #[macro_use] extern crate clogger;
+/// # #[macro_use] extern crate log;
+/// # use log::Level;
+/// # fn main() {
+/// let _capture = clogger::test::Capture::start();
+///
+/// warn!("disk full");
+///
+/// assert_logged!(Level::Warn);
+/// assert_logged!(Level::Warn, contains "full");
+/// assert_logged!(Level::Warn, eq "disk full");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_logged {
+    ($level:expr $(, $kind:ident $text:expr)? $(,)?) => {
+        $crate::test::__assert_logged($level, $crate::__clogger_pattern!($($kind $text)?), true)
+    };
+}
+
+/// Assert that no record with the given level was captured, optionally only considering records with a message that
+/// `contains` or is `eq` to the given text.
+#[macro_export]
+macro_rules! assert_not_logged {
+    ($level:expr $(, $kind:ident $text:expr)? $(,)?) => {
+        $crate::test::__assert_logged($level, $crate::__clogger_pattern!($($kind $text)?), false)
+    };
+}
+
+/// Assert that records matching each of the given patterns were captured in order, separated by semicolons.
+///
+/// Other records may be captured before, between or after the matching records.
+#[macro_export]
+macro_rules! assert_logged_in_order {
+    ($($level:expr $(, $kind:ident $text:expr)?);+ $(;)?) => {
+        $crate::test::__assert_logged_in_order(&[
+            $(($level, $crate::__clogger_pattern!($($kind $text)?))),+
+        ])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+
+    fn messages(capture: &Capture) -> Vec<String> {
+        capture
+            .records()
+            .iter()
+            .map(|entry| entry.message().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn captures_are_isolated_per_thread() {
+        // Both threads capture and log at the same time, so that each would see the other's records if captures were
+        // not isolated.
+        let barrier = Barrier::new(2);
+        let captured = thread::scope(|scope| {
+            let threads: Vec<_> = ["first thread", "second thread"]
+                .iter()
+                .map(|&name| {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        let capture = Capture::start();
+                        barrier.wait();
+                        log::warn!("{} checking in", name);
+                        barrier.wait();
+                        messages(&capture)
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(
+            captured,
+            [
+                vec!["first thread checking in".to_owned()],
+                vec!["second thread checking in".to_owned()],
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_captures_resume_the_outer_capture() {
+        let outer = Capture::start();
+        log::warn!("before the inner capture");
+
+        let inner = Capture::start();
+        log::warn!("during the inner capture");
+        assert_eq!(messages(&inner), ["during the inner capture"]);
+        assert_eq!(records().len(), 1);
+        drop(inner);
+
+        log::warn!("after the inner capture");
+        assert_eq!(
+            messages(&outer),
+            ["before the inner capture", "after the inner capture"]
+        );
+    }
+
+    #[test]
+    fn records_of_spawned_threads_are_not_captured() {
+        let capture = Capture::start();
+        thread::spawn(|| log::warn!("from a spawned thread"))
+            .join()
+            .unwrap();
+        log::warn!("from the test");
+
+        assert_eq!(messages(&capture), ["from the test"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! Timestamp formatting.
+#![cfg_attr(not(feature = "timestamps"), allow(dead_code))]
+
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The format used for timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// A full RFC 3339 date and time, such as `2024-05-01T12:00:00Z`. This is the default.
+    Rfc3339,
+
+    /// Only the time of day, such as `12:00:00`.
+    Compact,
+}
+
+/// The precision of the fractional seconds included in timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    /// Whole seconds only. This is the default.
+    Seconds,
+
+    /// Milliseconds.
+    Millis,
+
+    /// Microseconds.
+    Micros,
+
+    /// Nanoseconds.
+    Nanos,
+}
+
+/// The timezone that timestamps are displayed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timezone {
+    /// Coordinated Universal Time. This is the default.
+    Utc,
+
+    /// The local timezone of the system. RFC 3339 timestamps include the offset from UTC, such as
+    /// `2024-05-01T14:00:00+02:00`. Where the local offset cannot be determined, UTC is used instead.
+    Local,
+}
+
+/// How timestamps are included in the lines written to a [sink](::SinkHandle::set_timestamps).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SinkTimestamps {
+    /// Use the same settings as the console. This is the default.
+    Console,
+
+    /// Never include timestamps.
+    Off,
+
+    /// Always include timestamps, using the given settings.
+    On(TimestampFormat, Precision, Timezone),
+}
+
+impl TimestampFormat {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => TimestampFormat::Compact,
+            _ => TimestampFormat::Rfc3339,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            TimestampFormat::Rfc3339 => 0,
+            TimestampFormat::Compact => 1,
+        }
+    }
+}
+
+impl Timezone {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Timezone::Local,
+            _ => Timezone::Utc,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Timezone::Utc => 0,
+            Timezone::Local => 1,
+        }
+    }
+}
+
+impl Precision {
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Precision::Millis,
+            2 => Precision::Micros,
+            3 => Precision::Nanos,
+            _ => Precision::Seconds,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Precision::Seconds => 0,
+            Precision::Millis => 1,
+            Precision::Micros => 2,
+            Precision::Nanos => 3,
+        }
+    }
+
+    fn digits(self) -> usize {
+        match self {
+            Precision::Seconds => 0,
+            Precision::Millis => 3,
+            Precision::Micros => 6,
+            Precision::Nanos => 9,
+        }
+    }
+}
+
+/// Format a point in time.
+pub(crate) fn format(
+    time: SystemTime,
+    format: TimestampFormat,
+    precision: Precision,
+    timezone: Timezone,
+) -> String {
+    let mut output = String::with_capacity(30);
+    push(&mut output, time, format, precision, timezone);
+    output
+}
+
+/// Append a formatted point in time to a string, without allocating.
+pub(crate) fn push(
+    output: &mut String,
+    time: SystemTime,
+    format: TimestampFormat,
+    precision: Precision,
+    timezone: Timezone,
+) {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let offset = match timezone {
+        Timezone::Utc => 0,
+        Timezone::Local => local_offset(duration.as_secs()),
+    };
+    let secs = duration.as_secs().saturating_add_signed(offset);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+
+    if format == TimestampFormat::Rfc3339 {
+        let _ = write!(output, "{:04}-{:02}-{:02}T", year, month, day);
+    }
+
+    let _ = write!(
+        output,
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    );
+
+    let digits = precision.digits();
+    if digits > 0 {
+        let fraction = duration.subsec_nanos() / 10u32.pow(9 - digits as u32);
+        let _ = write!(output, ".{:0width$}", fraction, width = digits);
+    }
+
+    if format == TimestampFormat::Rfc3339 {
+        if timezone == Timezone::Utc {
+            output.push('Z');
+        } else {
+            let minutes = offset.unsigned_abs() / 60;
+            let sign = if offset < 0 { '-' } else { '+' };
+            let _ = write!(output, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60);
+        }
+    }
+}
+
+/// Get the offset of the local timezone from UTC in seconds at the given number of seconds since the Unix epoch.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+fn local_offset(secs: u64) -> i64 {
+    use std::os::raw::{c_char, c_int, c_long};
+
+    // The layout shared by the platforms that have `tm_gmtoff`.
+    #[repr(C)]
+    struct Tm {
+        tm_sec: c_int,
+        tm_min: c_int,
+        tm_hour: c_int,
+        tm_mday: c_int,
+        tm_mon: c_int,
+        tm_year: c_int,
+        tm_wday: c_int,
+        tm_yday: c_int,
+        tm_isdst: c_int,
+        tm_gmtoff: c_long,
+        tm_zone: *const c_char,
+    }
+
+    extern "C" {
+        fn localtime_r(time: *const c_long, result: *mut Tm) -> *mut Tm;
+    }
+
+    let time = secs as c_long;
+    let mut tm = std::mem::MaybeUninit::<Tm>::uninit();
+
+    if unsafe { localtime_r(&time, tm.as_mut_ptr()) }.is_null() {
+        return 0;
+    }
+
+    // `long` is only 32 bits wide on some platforms.
+    #[allow(clippy::unnecessary_cast)]
+    let offset = unsafe { tm.assume_init() }.tm_gmtoff as i64;
+    offset
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+fn local_offset(_: u64) -> i64 {
+    0
+}
+
+/// Convert a number of days since the Unix epoch into a year, month and day.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
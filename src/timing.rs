@@ -0,0 +1,31 @@
+//! Monotonic timing of records.
+
+use clock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The monotonic time that timings are measured from.
+static START: OnceLock<Duration> = OnceLock::new();
+
+/// The monotonic timing of the most recent record, in nanoseconds.
+static LAST: AtomicU64 = AtomicU64::new(0);
+
+/// Start the monotonic clock, if it has not been started already.
+pub(crate) fn start() {
+    START.get_or_init(clock::monotonic);
+}
+
+/// Get the time elapsed since the clock was started.
+pub(crate) fn elapsed() -> Duration {
+    clock::monotonic().saturating_sub(*START.get_or_init(clock::monotonic))
+}
+
+/// Get the nanoseconds elapsed since the clock was started, and since the previous call to this function.
+pub(crate) fn monotonic() -> (u64, u64) {
+    let start = *START.get_or_init(clock::monotonic);
+    let elapsed = clock::monotonic().saturating_sub(start).as_nanos() as u64;
+    let previous = LAST.swap(elapsed, Ordering::SeqCst);
+
+    (elapsed, elapsed.saturating_sub(previous))
+}
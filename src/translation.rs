@@ -0,0 +1,400 @@
+//! Translations of the text displayed by the logger.
+
+use log::Level;
+use std::borrow::Cow;
+use std::env;
+use std::fmt::{self, Write};
+use std::mem;
+use std::sync::{RwLock, RwLockReadGuard};
+
+static CURRENT: RwLock<Translation> = RwLock::new(Translation::ENGLISH);
+
+pub(crate) fn current() -> RwLockReadGuard<'static, Translation> {
+    CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A sentence written by the logger itself, which can be [translated](Translation::message).
+///
+/// Sentences may contain placeholders in braces, such as `{elapsed}`, which are replaced with values when the sentence
+/// is written. The placeholders of each sentence are listed along with its English text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Message {
+    /// Written when a [phase](::phase!) starts, after its name: `started`.
+    Started,
+    /// Written when a phase or the program [finishes](::finish) successfully: `finished in {elapsed}`.
+    Finished,
+    /// Written when a phase or the program fails: `failed after {elapsed}`.
+    Failed,
+    /// A summary of records suppressed by the [burst limit](::set_burst_limit):
+    /// `suppressed {count} similar {level} messages in the last {seconds}s`.
+    BurstSummary,
+    /// A summary of records of a target that were [sampled](::set_target_sampling) out:
+    /// `sampled out {dropped} of {seen} records in the last {seconds}s`.
+    SampleSummary,
+    /// The summary of dropped records written when the program finishes, if one record was dropped:
+    /// `{count} record was dropped: {causes}`.
+    DroppedOne,
+    /// The summary of dropped records written when the program finishes: `{count} records were dropped: {causes}`.
+    Dropped,
+    /// One of the causes listed in the summary of dropped records: `{count} by sampling`.
+    DroppedBySampling,
+    /// One of the causes listed in the summary of dropped records: `{count} by the burst limit`.
+    DroppedByBurstLimit,
+    /// One of the causes listed in the summary of dropped records: `{count} by full sink queues`.
+    DroppedBySinkQueues,
+    /// One of the causes listed in the summary of dropped records: `{count} as re-entrant records`.
+    DroppedReentrant,
+    /// The summary of diagnostics suppressed by their code, if one was suppressed:
+    /// `suppressed {count} diagnostic by code: {codes}`.
+    SuppressedByCodeOne,
+    /// The summary of diagnostics suppressed by their code: `suppressed {count} diagnostics by code: {codes}`.
+    SuppressedByCode,
+    /// Written before [replaying](::set_replay_on_error) a single hidden record:
+    /// `replaying {count} hidden record leading up to the first error`.
+    ReplayingOne,
+    /// Written before replaying hidden records: `replaying {count} hidden records leading up to the first error`.
+    Replaying,
+}
+
+/// The number of variants of [`Message`].
+const MESSAGES: usize = 15;
+
+/// A table of the text displayed by the logger, such as level names and the sentences it writes itself.
+///
+/// Level names in [machine mode](::set_machine_mode) are never translated, so that the output remains stable.
+///
+/// ```
+/// # extern crate clogger;
+/// # extern crate log;
+/// # fn main() {
+/// use clogger::{Message, Translation};
+/// use log::Level;
+///
+/// clogger::set_translation(
+///     Translation::from_env()
+///         .level(Level::Warn, "careful")
+///         .message(Message::Finished, "all done in {elapsed}"),
+/// );
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Translation {
+    levels: [Cow<'static, str>; 5],
+    caused_by: Cow<'static, str>,
+    dry_run: Cow<'static, str>,
+    messages: [Cow<'static, str>; MESSAGES],
+}
+
+impl Translation {
+    const ENGLISH: Translation = Translation::new(
+        ["error", "warn", "info", "debug", "trace"],
+        "Caused by:",
+        "[dry-run]",
+        [
+            "started",
+            "finished in {elapsed}",
+            "failed after {elapsed}",
+            "suppressed {count} similar {level} messages in the last {seconds}s",
+            "sampled out {dropped} of {seen} records in the last {seconds}s",
+            "{count} record was dropped: {causes}",
+            "{count} records were dropped: {causes}",
+            "{count} by sampling",
+            "{count} by the burst limit",
+            "{count} by full sink queues",
+            "{count} as re-entrant records",
+            "suppressed {count} diagnostic by code: {codes}",
+            "suppressed {count} diagnostics by code: {codes}",
+            "replaying {count} hidden record leading up to the first error",
+            "replaying {count} hidden records leading up to the first error",
+        ],
+    );
+
+    const GERMAN: Translation = Translation::new(
+        ["fehler", "warnung", "info", "debug", "trace"],
+        "Verursacht durch:",
+        "[Testlauf]",
+        [
+            "gestartet",
+            "fertig nach {elapsed}",
+            "fehlgeschlagen nach {elapsed}",
+            "{count} ähnliche Meldungen der Stufe {level} in den letzten {seconds}s unterdrückt",
+            "{dropped} von {seen} Einträgen in den letzten {seconds}s durch Stichproben verworfen",
+            "{count} Eintrag wurde verworfen: {causes}",
+            "{count} Einträge wurden verworfen: {causes}",
+            "{count} durch Stichproben",
+            "{count} durch das Burst-Limit",
+            "{count} durch volle Warteschlangen",
+            "{count} als verschachtelte Einträge",
+            "{count} Diagnose nach Code unterdrückt: {codes}",
+            "{count} Diagnosen nach Code unterdrückt: {codes}",
+            "{count} verborgener Eintrag vor dem ersten Fehler wird wiederholt",
+            "{count} verborgene Einträge vor dem ersten Fehler werden wiederholt",
+        ],
+    );
+
+    const FRENCH: Translation = Translation::new(
+        ["erreur", "avertissement", "info", "débogage", "trace"],
+        "Causé par :",
+        "[simulation]",
+        [
+            "démarré",
+            "terminé en {elapsed}",
+            "échec après {elapsed}",
+            "{count} messages {level} similaires supprimés au cours des dernières {seconds}s",
+            "{dropped} enregistrements sur {seen} écartés par échantillonnage au cours des dernières {seconds}s",
+            "{count} enregistrement a été abandonné : {causes}",
+            "{count} enregistrements ont été abandonnés : {causes}",
+            "{count} par échantillonnage",
+            "{count} par la limite de rafale",
+            "{count} par des files d'attente pleines",
+            "{count} en tant qu'enregistrements réentrants",
+            "{count} diagnostic supprimé par code : {codes}",
+            "{count} diagnostics supprimés par code : {codes}",
+            "relecture de {count} enregistrement masqué précédant la première erreur",
+            "relecture de {count} enregistrements masqués précédant la première erreur",
+        ],
+    );
+
+    const SPANISH: Translation = Translation::new(
+        ["error", "advertencia", "info", "depuración", "traza"],
+        "Causado por:",
+        "[simulación]",
+        [
+            "iniciado",
+            "terminado en {elapsed}",
+            "falló tras {elapsed}",
+            "se suprimieron {count} mensajes {level} similares en los últimos {seconds}s",
+            "se descartaron por muestreo {dropped} de {seen} registros en los últimos {seconds}s",
+            "se descartó {count} registro: {causes}",
+            "se descartaron {count} registros: {causes}",
+            "{count} por muestreo",
+            "{count} por el límite de ráfagas",
+            "{count} por colas llenas",
+            "{count} como registros reentrantes",
+            "se suprimió {count} diagnóstico por código: {codes}",
+            "se suprimieron {count} diagnósticos por código: {codes}",
+            "reproduciendo {count} registro oculto previo al primer error",
+            "reproduciendo {count} registros ocultos previos al primer error",
+        ],
+    );
+
+    const fn new(
+        levels: [&'static str; 5],
+        caused_by: &'static str,
+        dry_run: &'static str,
+        messages: [&'static str; MESSAGES],
+    ) -> Self {
+        Translation {
+            levels: borrowed(levels),
+            caused_by: Cow::Borrowed(caused_by),
+            dry_run: Cow::Borrowed(dry_run),
+            messages: borrowed(messages),
+        }
+    }
+
+    /// The built-in English text. This is the default.
+    pub fn english() -> Self {
+        Translation::ENGLISH
+    }
+
+    /// The built-in German text.
+    pub fn german() -> Self {
+        Translation::GERMAN
+    }
+
+    /// The built-in French text.
+    pub fn french() -> Self {
+        Translation::FRENCH
+    }
+
+    /// The built-in Spanish text.
+    pub fn spanish() -> Self {
+        Translation::SPANISH
+    }
+
+    /// Get the built-in translation for a language, given as an ISO 639-1 code such as `de`.
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Some(Translation::english()),
+            "de" => Some(Translation::german()),
+            "fr" => Some(Translation::french()),
+            "es" => Some(Translation::spanish()),
+            _ => None,
+        }
+    }
+
+    /// Get the built-in translation for the language of the current locale, falling back to English.
+    ///
+    /// The locale is read from the `LC_ALL`, `LC_MESSAGES` and `LANG` environment variables, in that order.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| {
+                let language = locale.split(['_', '.', '@']).next().unwrap_or("");
+                Translation::for_language(language)
+            })
+            .unwrap_or_else(Translation::english)
+    }
+
+    /// Set the name displayed for a level.
+    pub fn level(mut self, level: Level, name: impl Into<Cow<'static, str>>) -> Self {
+        self.levels[level as usize - 1] = name.into();
+        self
+    }
+
+    /// Set the heading displayed above the causes of an error in error banners.
+    pub fn caused_by(mut self, text: impl Into<Cow<'static, str>>) -> Self {
+        self.caused_by = text.into();
+        self
+    }
+
+    /// Set the prefix of actions logged in dry-run mode.
+    pub fn dry_run(mut self, text: impl Into<Cow<'static, str>>) -> Self {
+        self.dry_run = text.into();
+        self
+    }
+
+    /// Set the text of a sentence written by the logger, with the same placeholders as the English text.
+    ///
+    /// Placeholders may be reordered or left out, and unknown placeholders are written as is.
+    pub fn message(mut self, message: Message, text: impl Into<Cow<'static, str>>) -> Self {
+        self.messages[message as usize] = text.into();
+        self
+    }
+
+    pub(crate) fn level_name(&self, level: Level) -> &str {
+        &self.levels[level as usize - 1]
+    }
+
+    pub(crate) fn caused_by_text(&self) -> &str {
+        &self.caused_by
+    }
+
+    pub(crate) fn dry_run_text(&self) -> &str {
+        &self.dry_run
+    }
+
+    /// Get the text of a sentence, with its placeholders replaced by the given values.
+    pub(crate) fn format(&self, message: Message, values: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut text = String::new();
+        let mut rest = &*self.messages[message as usize];
+
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let value = rest.find('}').and_then(|end| {
+                let value = values.iter().find(|(name, _)| *name == &rest[1..end])?;
+                Some((end, value.1))
+            });
+
+            match value {
+                Some((end, value)) => {
+                    let _ = write!(text, "{}", value);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        text.push_str(rest);
+        text
+    }
+}
+
+/// Make an array of borrowed strings, which cannot be done with `map` in a constant.
+const fn borrowed<const N: usize>(texts: [&'static str; N]) -> [Cow<'static, str>; N] {
+    const EMPTY: Cow<'static, str> = Cow::Borrowed("");
+    let mut array = [EMPTY; N];
+    let mut i = 0;
+
+    while i < N {
+        mem::forget(mem::replace(&mut array[i], Cow::Borrowed(texts[i])));
+        i += 1;
+    }
+
+    array
+}
+
+impl Default for Translation {
+    fn default() -> Self {
+        Translation::english()
+    }
+}
+
+/// Get the current translation.
+pub fn translation() -> Translation {
+    current().clone()
+}
+
+/// Set the translation of the text displayed by the logger.
+///
+/// This function may be called at any time.
+pub fn set_translation(translation: Translation) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = translation;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(
+            Translation::english().format(Message::Finished, &[("elapsed", &"3.2s")]),
+            "finished in 3.2s"
+        );
+        assert_eq!(
+            Translation::english().format(
+                Message::SampleSummary,
+                &[("dropped", &3), ("seen", &10), ("seconds", &60)]
+            ),
+            "sampled out 3 of 10 records in the last 60s"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_kept() {
+        let translation =
+            Translation::english().message(Message::Started, "{name} {started} {} {x");
+        assert_eq!(
+            translation.format(Message::Started, &[("name", &"build")]),
+            "build {started} {} {x"
+        );
+    }
+
+    #[test]
+    fn translations_have_the_same_placeholders() {
+        for translation in [
+            Translation::german(),
+            Translation::french(),
+            Translation::spanish(),
+        ]
+        .iter()
+        {
+            for (text, english) in translation
+                .messages
+                .iter()
+                .zip(&Translation::ENGLISH.messages)
+            {
+                let placeholders = |text: &str| {
+                    let mut names = text
+                        .split('{')
+                        .skip(1)
+                        .filter_map(|rest| rest.split_once('}'))
+                        .map(|(name, _)| name.to_owned())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    names
+                };
+                assert_eq!(placeholders(text), placeholders(english), "{}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Display widths of text in a terminal.
+//!
+//! Characters from East Asian scripts and most emoji take up two columns, while combining marks, joiners and
+//! variation selectors take up none. The tables here cover the commonly used ranges rather than the whole Unicode
+//! database, which is enough to keep columns aligned without adding a dependency.
+
+/// Ranges of characters that take up no columns of their own, since they combine with the preceding character.
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0900, 0x0903),
+    (0x093a, 0x094f),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x2060, 0x2064),
+    (0x20d0, 0x20ff),
+    (0x302a, 0x302f),
+    (0x3099, 0x309a),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0x1f3fb, 0x1f3ff),
+    (0xe0000, 0xe0fff),
+];
+
+/// Ranges of characters that take up two columns.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18cff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f251),
+    (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f900, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x3fffd),
+];
+
+const ZWJ: char = '\u{200d}';
+
+fn contains(table: &[(u32, u32)], c: char) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Get the number of columns taken up by a character that follows the given character.
+fn char_width(previous: Option<char>, c: char) -> usize {
+    if c == ZWJ || previous == Some(ZWJ) || c.is_control() || contains(ZERO, c) {
+        // A character joined to the previous one is displayed as part of it.
+        0
+    } else if contains(WIDE, c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Get the number of columns taken up by a string, which must not contain escape sequences.
+pub(crate) fn width(s: &str) -> usize {
+    let mut previous = None;
+
+    s.chars()
+        .map(|c| {
+            let width = char_width(previous, c);
+            previous = Some(c);
+            width
+        })
+        .sum()
+}
+
+/// Get the longest prefix of a string that fits in the given number of columns, without splitting a character from
+/// the marks and joined characters that follow it.
+pub(crate) fn truncate(s: &str, columns: usize) -> &str {
+    let mut used = 0;
+    let mut previous = None;
+
+    for (i, c) in s.char_indices() {
+        let width = char_width(previous, c);
+        if width > 0 && used + width > columns {
+            return &s[..i];
+        }
+
+        used += width;
+        previous = Some(c);
+    }
+
+    s
+}
+
+/// Get the shortest suffix of a string that starts at a character and fits in the given number of columns.
+pub(crate) fn truncate_start(s: &str, columns: usize) -> &str {
+    let mut start = s.len();
+
+    for (i, _) in s.char_indices().rev() {
+        if width(&s[i..]) > columns {
+            break;
+        }
+
+        start = i;
+    }
+
+    // Avoid starting with a mark or joined character that belongs to a character that was cut off.
+    let mut rest = &s[start..];
+    while let Some(c) = rest.chars().next() {
+        if char_width(None, c) > 0 {
+            break;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    rest
+}
+
+/// The alignment of padded text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// Pad a string with spaces to take up at least the given number of columns.
+pub(crate) fn pad(s: &str, align: Align, columns: usize) -> String {
+    let padding = columns.saturating_sub(width(s));
+    let (before, after) = match align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+
+    let mut padded = String::with_capacity(s.len() + padding);
+    padded.extend(std::iter::repeat_n(' ', before));
+    padded.push_str(s);
+    padded.extend(std::iter::repeat_n(' ', after));
+    padded
+}